@@ -0,0 +1,148 @@
+use crate::core::Ray;
+use crate::types::Real;
+use crate::vec3::Vec3;
+
+// axis aligned bounding box
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Bounds3 {
+    pub p_min: Vec3, // minimum corner
+    pub p_max: Vec3, // maximum corner
+}
+
+impl Bounds3 {
+    pub fn new(p_min: Vec3, p_max: Vec3) -> Self {
+        Bounds3 { p_min, p_max }
+    }
+
+    // bounds which contains nothing
+    pub fn empty() -> Self {
+        Bounds3 {
+            p_min: Vec3::new(Real::MAX, Real::MAX, Real::MAX),
+            p_max: Vec3::new(Real::MIN, Real::MIN, Real::MIN),
+        }
+    }
+
+    pub fn from_points(points: &[Vec3]) -> Self {
+        points
+            .iter()
+            .fold(Bounds3::empty(), |bounds, p| bounds.union_point(*p))
+    }
+
+    pub fn union(&self, bounds: &Bounds3) -> Bounds3 {
+        Bounds3 {
+            p_min: self.p_min.min3(bounds.p_min),
+            p_max: self.p_max.max3(bounds.p_max),
+        }
+    }
+
+    pub fn union_point(&self, p: Vec3) -> Bounds3 {
+        Bounds3 {
+            p_min: self.p_min.min3(p),
+            p_max: self.p_max.max3(p),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.p_min.x() > self.p_max.x()
+            || self.p_min.y() > self.p_max.y()
+            || self.p_min.z() > self.p_max.z()
+    }
+
+    pub fn center(&self) -> Vec3 {
+        0.5 * (self.p_min + self.p_max)
+    }
+
+    pub fn diagonal(&self) -> Vec3 {
+        self.p_max - self.p_min
+    }
+
+    pub fn surface_area(&self) -> Real {
+        if self.is_empty() {
+            return 0.0;
+        }
+        let d = self.diagonal();
+        2.0 * (d.x() * d.y() + d.y() * d.z() + d.z() * d.x())
+    }
+
+    // index of the longest axis
+    pub fn max_extent(&self) -> usize {
+        let d = self.diagonal();
+        if d.x() > d.y() && d.x() > d.z() {
+            0
+        } else if d.y() > d.z() {
+            1
+        } else {
+            2
+        }
+    }
+
+    // relative position of p, (0, 0, 0) at p_min and (1, 1, 1) at p_max
+    pub fn offset(&self, p: Vec3) -> Vec3 {
+        let d = self.diagonal();
+        let o = p - self.p_min;
+        Vec3::new(
+            if d.x() > 0.0 { o.x() / d.x() } else { 0.0 },
+            if d.y() > 0.0 { o.y() / d.y() } else { 0.0 },
+            if d.z() > 0.0 { o.z() / d.z() } else { 0.0 },
+        )
+    }
+
+    // returns entering and exiting distance of ray
+    pub fn intersect(&self, ray: &Ray, inv_dir: Vec3) -> Option<(Real, Real)> {
+        let mut t0 = ray.tmin;
+        let mut t1 = ray.tmax;
+        for axis in 0..3 {
+            let t_near = (self.p_min[axis] - ray.origin[axis]) * inv_dir[axis];
+            let t_far = (self.p_max[axis] - ray.origin[axis]) * inv_dir[axis];
+            let (t_near, t_far) = if t_near > t_far {
+                (t_far, t_near)
+            } else {
+                (t_near, t_far)
+            };
+
+            // NOTE: NaN is ignored by max and min
+            t0 = t0.max(t_near);
+            t1 = t1.min(t_far);
+            if t0 > t1 {
+                return None;
+            }
+        }
+        Some((t0, t1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bounds::*;
+
+    #[test]
+    fn bounds_union() {
+        let b1 = Bounds3::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 1.0, 1.0));
+        let b2 = Bounds3::new(Vec3::new(-1.0, 0.5, 0.5), Vec3::new(0.5, 2.0, 0.5));
+        assert_eq!(
+            b1.union(&b2),
+            Bounds3::new(Vec3::new(-1.0, 0.0, 0.0), Vec3::new(1.0, 2.0, 1.0))
+        );
+        assert_eq!(Bounds3::empty().union(&b1), b1);
+    }
+
+    #[test]
+    fn bounds_surface_area() {
+        let bounds = Bounds3::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 2.0, 3.0));
+        assert_eq!(bounds.surface_area(), 22.0);
+        assert_eq!(bounds.max_extent(), 2);
+        assert_eq!(Bounds3::empty().surface_area(), 0.0);
+    }
+
+    #[test]
+    fn bounds_intersect() {
+        let bounds = Bounds3::new(Vec3::new(-1.0, -1.0, -1.0), Vec3::new(1.0, 1.0, 1.0));
+        let ray = Ray::new(Vec3::new(0.0, 0.0, -3.0), Vec3::new(0.0, 0.0, 1.0));
+        let inv_dir = 1.0 / ray.direction;
+        assert_eq!(bounds.intersect(&ray, inv_dir), Some((2.0, 4.0)));
+
+        let ray = Ray::new(Vec3::new(2.0, 0.0, -3.0), Vec3::new(0.0, 0.0, 1.0));
+        let inv_dir = 1.0 / ray.direction;
+        assert_eq!(bounds.intersect(&ray, inv_dir), None);
+    }
+}
@@ -0,0 +1,222 @@
+use crate::bxdf::{BxDF, BxDFSample, BxDFType};
+use crate::core::ShadingInfo;
+use crate::sampler::Sampler;
+use crate::types::Real;
+use crate::vec2::Vec2;
+use crate::vec3::Vec3;
+
+// NOTE: directions given to and returned from BSDF are in world space
+
+// weighted collection of BxDFs sharing shading frame
+pub struct BSDF {
+    x: Vec3, // position
+    t: Vec3, // tangent vector
+    n: Vec3, // shading normal
+    b: Vec3, // bitangent vector
+    uv: Vec2,
+    bxdfs: Vec<Box<dyn BxDF>>,
+    weights: Vec<Real>,
+}
+
+impl BSDF {
+    pub fn new(info: &ShadingInfo) -> Self {
+        BSDF {
+            x: info.x,
+            t: info.t,
+            n: info.n,
+            b: info.b,
+            uv: info.uv,
+            bxdfs: Vec::new(),
+            weights: Vec::new(),
+        }
+    }
+
+    pub fn from_bxdf(info: &ShadingInfo, bxdf: Box<dyn BxDF>) -> Self {
+        let mut bsdf = BSDF::new(info);
+        bsdf.add(bxdf, 1.0);
+        bsdf
+    }
+
+    pub fn add(&mut self, bxdf: Box<dyn BxDF>, weight: Real) {
+        if weight > 0.0 {
+            self.bxdfs.push(bxdf);
+            self.weights.push(weight);
+        }
+    }
+
+    // add all BxDFs of other BSDF, scaling their weights
+    pub fn merge(&mut self, other: BSDF, weight: Real) {
+        for (bxdf, w) in other.bxdfs.into_iter().zip(other.weights) {
+            self.add(bxdf, weight * w);
+        }
+    }
+
+    pub fn n_bxdfs(&self) -> usize {
+        self.bxdfs.len()
+    }
+
+    pub fn normal(&self) -> Vec3 {
+        self.n
+    }
+
+    pub fn bxdf_type(&self) -> BxDFType {
+        self.bxdfs
+            .iter()
+            .fold(BxDFType::NONE, |flags, bxdf| flags | bxdf.bxdf_type())
+    }
+
+    // choose BxDF in proportion to its weight, then sample direction from it
+    pub fn sample_direction(&self, wo: Vec3, sampler: &mut Sampler) -> BxDFSample {
+        let total: Real = self.weights.iter().sum();
+        if self.bxdfs.is_empty() {
+            return BxDFSample {
+                f: Vec3::new(0.0, 0.0, 0.0),
+                wi: -wo,
+                pdf: 0.0,
+                sampled_type: BxDFType::NONE,
+            };
+        }
+
+        let mut target = sampler.next_1d() * total;
+        let mut idx = self.bxdfs.len() - 1;
+        for (i, w) in self.weights.iter().enumerate() {
+            target -= w;
+            if target <= 0.0 {
+                idx = i;
+                break;
+            }
+        }
+
+        let info = ShadingInfo {
+            x: self.x,
+            n: self.n,
+            wo: wo.world_to_local(self.t, self.n, self.b),
+            t: self.t,
+            b: self.b,
+            uv: self.uv,
+        };
+        let sample = self.bxdfs[idx].sample_direction(&info, sampler);
+        let wi = sample.wi.local_to_world(self.t, self.n, self.b);
+        if sample.pdf == 0.0 {
+            return BxDFSample { wi, ..sample };
+        }
+
+        // delta lobe can't be evaluated by the other BxDFs
+        if sample.sampled_type.contains(BxDFType::SPECULAR) {
+            let weight = self.weights[idx];
+            return BxDFSample {
+                f: weight * sample.f,
+                wi,
+                pdf: weight / total * sample.pdf,
+                sampled_type: sample.sampled_type,
+            };
+        }
+
+        BxDFSample {
+            f: self.eval(wo, wi),
+            wi,
+            pdf: self.pdf(wo, wi),
+            sampled_type: sample.sampled_type,
+        }
+    }
+
+    // sum of weighted BxDFs
+    pub fn eval(&self, wo: Vec3, wi: Vec3) -> Vec3 {
+        let wo = wo.world_to_local(self.t, self.n, self.b);
+        let wi = wi.world_to_local(self.t, self.n, self.b);
+        let mut f = Vec3::new(0.0, 0.0, 0.0);
+        for (bxdf, w) in self.bxdfs.iter().zip(&self.weights) {
+            f += *w * bxdf.eval(wo, wi);
+        }
+        f
+    }
+
+    // pdf of sample_direction, mixture of pdfs of BxDFs
+    pub fn pdf(&self, wo: Vec3, wi: Vec3) -> Real {
+        let total: Real = self.weights.iter().sum();
+        if self.bxdfs.is_empty() {
+            return 0.0;
+        }
+
+        let wo = wo.world_to_local(self.t, self.n, self.b);
+        let wi = wi.world_to_local(self.t, self.n, self.b);
+        let mut pdf = 0.0;
+        for (bxdf, w) in self.bxdfs.iter().zip(&self.weights) {
+            pdf += w / total * bxdf.pdf(wo, wi);
+        }
+        pdf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bsdf::*;
+    use crate::bxdf::{IdealReflection, Lambert, MicrofacetConductor};
+    use crate::vec3::build_orthonormal_basis;
+
+    fn shading_info() -> ShadingInfo {
+        let n = Vec3::new(1.0, 1.0, 0.0).normalize();
+        let (t, n, b) = build_orthonormal_basis(n);
+        ShadingInfo {
+            x: Vec3::new(0.0, 0.0, 0.0),
+            n,
+            wo: Vec3::new(0.0, 1.0, 0.0),
+            t,
+            b,
+            uv: Vec2::new(0.0, 0.0),
+        }
+    }
+
+    #[test]
+    fn mixed_sample_matches_eval_pdf() {
+        let info = shading_info();
+        let mut bsdf = BSDF::new(&info);
+        bsdf.add(Box::new(Lambert::new(Vec3::new(0.5, 0.5, 0.5))), 0.3);
+        bsdf.add(
+            Box::new(MicrofacetConductor::new(
+                Vec3::new(1.0, 1.0, 1.0),
+                Vec3::new(2.0, 2.0, 2.0),
+                0.3,
+                0.3,
+            )),
+            0.7,
+        );
+        assert!(bsdf
+            .bxdf_type()
+            .contains(BxDFType::DIFFUSE | BxDFType::GLOSSY));
+
+        let mut sampler = Sampler::new(1);
+        let wo = Vec3::new(0.2, 1.0, 0.1).normalize();
+        for _ in 0..1000 {
+            let sample = bsdf.sample_direction(wo, &mut sampler);
+            if sample.pdf == 0.0 {
+                continue;
+            }
+            let f = bsdf.eval(wo, sample.wi);
+            assert!((sample.f - f).length() < 1e-3 * f.length().max(1.0));
+            let pdf = bsdf.pdf(wo, sample.wi);
+            assert!((sample.pdf - pdf).abs() < 1e-3 * pdf.max(1.0));
+        }
+    }
+
+    #[test]
+    fn merge_scales_weights() {
+        let info = shading_info();
+        let mirror = BSDF::from_bxdf(&info, Box::new(IdealReflection::new()));
+        let mut bsdf = BSDF::new(&info);
+        bsdf.merge(mirror, 0.25);
+        bsdf.merge(
+            BSDF::from_bxdf(&info, Box::new(Lambert::new(Vec3::new(1.0, 1.0, 1.0)))),
+            0.0,
+        );
+        assert_eq!(bsdf.n_bxdfs(), 1);
+
+        // reflected direction keeps weight of merged mirror
+        let mut sampler = Sampler::new(1);
+        let wo = Vec3::new(0.0, 1.0, 1.0).normalize();
+        let sample = bsdf.sample_direction(wo, &mut sampler);
+        let n = bsdf.normal();
+        assert!((sample.wi - (-wo + 2.0 * wo.dot(n) * n)).length() < 1e-4);
+        assert!((sample.f.max() / sample.pdf - 0.25 / sample.wi.dot(n).abs()).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,249 @@
+use crate::bounds::Bounds3;
+use crate::core::{IntersectInfoGlobal, IntersectableGlobal, IntersectableLocal, Ray};
+use crate::intersector::Intersector;
+use crate::types::Real;
+use crate::vec3::Vec3;
+
+use std::sync::Arc;
+
+const N_BINS: usize = 12; // number of bins used for SAH evaluation
+const MAX_PRIMITIVES_IN_NODE: usize = 4; // maximum number of primitives in leaf node
+const TRAVERSAL_COST: Real = 0.125; // cost of node traversal relative to primitive intersection
+
+struct BVHPrimitive {
+    prim_idx: u32,   // index of primitive
+    bounds: Bounds3, // bounds of primitive
+    centroid: Vec3,  // center of bounds
+}
+
+struct BVHNode {
+    bounds: Bounds3,   // bounds of node
+    offset: u32,       // leaf: offset of primitives, interior: index of second child
+    n_primitives: u32, // number of primitives, 0 means interior node
+    axis: u8,          // split axis of interior node
+}
+
+// NOTE: nodes are stored in depth-first order, so the first child is always next to its parent
+pub struct BVH {
+    primitives: Arc<Vec<Box<dyn IntersectableLocal + Send + Sync>>>,
+    prim_indices: Vec<u32>,
+    nodes: Vec<BVHNode>,
+}
+
+impl BVH {
+    pub fn new(primitives: Arc<Vec<Box<dyn IntersectableLocal + Send + Sync>>>) -> Self {
+        let mut bvh_primitives: Vec<BVHPrimitive> = primitives
+            .iter()
+            .enumerate()
+            .map(|(idx, primitive)| {
+                let bounds = primitive.bounds();
+                BVHPrimitive {
+                    prim_idx: idx as u32,
+                    bounds,
+                    centroid: bounds.center(),
+                }
+            })
+            .collect();
+
+        let mut nodes = Vec::new();
+        if !bvh_primitives.is_empty() {
+            build_node(&mut bvh_primitives, 0, &mut nodes);
+        }
+
+        BVH {
+            primitives,
+            prim_indices: bvh_primitives.iter().map(|p| p.prim_idx).collect(),
+            nodes,
+        }
+    }
+
+    pub fn n_nodes(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn bounds(&self) -> Bounds3 {
+        self.nodes
+            .first()
+            .map_or(Bounds3::empty(), |node| node.bounds)
+    }
+}
+
+fn make_leaf(bounds: Bounds3, offset: usize, n_primitives: usize) -> BVHNode {
+    BVHNode {
+        bounds,
+        offset: offset as u32,
+        n_primitives: n_primitives as u32,
+        axis: 0,
+    }
+}
+
+// build subtree from primitives[offset..offset + primitives.len()], returns index of node
+fn build_node(primitives: &mut [BVHPrimitive], offset: usize, nodes: &mut Vec<BVHNode>) -> usize {
+    let bounds = primitives
+        .iter()
+        .fold(Bounds3::empty(), |b, p| b.union(&p.bounds));
+    let node_idx = nodes.len();
+    nodes.push(make_leaf(bounds, offset, primitives.len()));
+
+    let n_primitives = primitives.len();
+    if n_primitives == 1 {
+        return node_idx;
+    }
+
+    // choose split axis by extent of centroids
+    let centroid_bounds = primitives
+        .iter()
+        .fold(Bounds3::empty(), |b, p| b.union_point(p.centroid));
+    let axis = centroid_bounds.max_extent();
+    if centroid_bounds.p_max[axis] == centroid_bounds.p_min[axis] {
+        // every centroid is at the same position, can not split
+        return node_idx;
+    }
+
+    // fill bins
+    let bin_index = |p: &BVHPrimitive| -> usize {
+        let b = (N_BINS as Real * centroid_bounds.offset(p.centroid)[axis]) as usize;
+        b.min(N_BINS - 1)
+    };
+    let mut bin_counts = [0usize; N_BINS];
+    let mut bin_bounds = [Bounds3::empty(); N_BINS];
+    for p in primitives.iter() {
+        let b = bin_index(p);
+        bin_counts[b] += 1;
+        bin_bounds[b] = bin_bounds[b].union(&p.bounds);
+    }
+
+    // evaluate SAH cost of splitting after each bin by sweeping from both sides
+    let mut costs = [0.0 as Real; N_BINS - 1];
+    let mut count_below = 0;
+    let mut bounds_below = Bounds3::empty();
+    for i in 0..N_BINS - 1 {
+        count_below += bin_counts[i];
+        bounds_below = bounds_below.union(&bin_bounds[i]);
+        costs[i] = count_below as Real * bounds_below.surface_area();
+    }
+    let mut count_above = 0;
+    let mut bounds_above = Bounds3::empty();
+    for i in (1..N_BINS).rev() {
+        count_above += bin_counts[i];
+        bounds_above = bounds_above.union(&bin_bounds[i]);
+        costs[i - 1] += count_above as Real * bounds_above.surface_area();
+    }
+
+    let (min_bin, min_cost) =
+        costs
+            .iter()
+            .enumerate()
+            .fold((0, Real::MAX), |(min_bin, min_cost), (bin, cost)| {
+                if *cost < min_cost {
+                    (bin, *cost)
+                } else {
+                    (min_bin, min_cost)
+                }
+            });
+    let leaf_cost = n_primitives as Real;
+    let min_cost = TRAVERSAL_COST + min_cost / bounds.surface_area();
+    if n_primitives <= MAX_PRIMITIVES_IN_NODE && min_cost >= leaf_cost {
+        return node_idx;
+    }
+
+    // partition primitives
+    let mut mid = 0;
+    for i in 0..n_primitives {
+        if bin_index(&primitives[i]) <= min_bin {
+            primitives.swap(i, mid);
+            mid += 1;
+        }
+    }
+    if mid == 0 || mid == n_primitives {
+        // SAH failed to separate primitives, fallback to median split
+        mid = n_primitives / 2;
+        primitives.select_nth_unstable_by(mid, |p1, p2| {
+            p1.centroid[axis].total_cmp(&p2.centroid[axis])
+        });
+    }
+
+    let (left, right) = primitives.split_at_mut(mid);
+    build_node(left, offset, nodes);
+    let second_child = build_node(right, offset + mid, nodes);
+    nodes[node_idx] = BVHNode {
+        bounds,
+        offset: second_child as u32,
+        n_primitives: 0,
+        axis: axis as u8,
+    };
+
+    node_idx
+}
+
+impl IntersectableGlobal for BVH {
+    fn intersect(&self, ray: &Ray) -> Option<IntersectInfoGlobal> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+
+        let mut ray = ray.clone();
+        let inv_dir = 1.0 / ray.direction;
+        let dir_is_neg = [inv_dir.x() < 0.0, inv_dir.y() < 0.0, inv_dir.z() < 0.0];
+
+        let mut info: Option<IntersectInfoGlobal> = None;
+        let mut stack: Vec<usize> = Vec::with_capacity(64);
+        let mut node_idx = 0;
+        loop {
+            let node = &self.nodes[node_idx];
+            if node.bounds.intersect(&ray, inv_dir).is_some() {
+                if node.n_primitives > 0 {
+                    let start = node.offset as usize;
+                    let end = start + node.n_primitives as usize;
+                    for &prim_idx in &self.prim_indices[start..end] {
+                        if let Some(surf_info) = self.primitives[prim_idx as usize].intersect(&ray)
+                        {
+                            ray.tmax = surf_info.t;
+                            info = Some(IntersectInfoGlobal {
+                                t: surf_info.t,
+                                pos: surf_info.pos,
+                                normal: surf_info.normal,
+                                shading_normal: surf_info.shading_normal,
+                                uv: surf_info.uv,
+                                dpdu: surf_info.dpdu,
+                                dpdv: surf_info.dpdv,
+                                prim_idx,
+                            });
+                        }
+                    }
+                } else {
+                    // visit near child first
+                    if dir_is_neg[node.axis as usize] {
+                        stack.push(node_idx + 1);
+                        node_idx = node.offset as usize;
+                    } else {
+                        stack.push(node.offset as usize);
+                        node_idx += 1;
+                    }
+                    continue;
+                }
+            }
+
+            match stack.pop() {
+                Some(idx) => node_idx = idx,
+                None => break,
+            }
+        }
+        info
+    }
+}
+
+impl Intersector for BVH {}
+
+#[cfg(test)]
+mod tests {
+    use crate::bvh::*;
+
+    #[test]
+    fn bvh_empty() {
+        let bvh = BVH::new(Arc::new(Vec::new()));
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0));
+        assert_eq!(bvh.n_nodes(), 0);
+        assert_eq!(bvh.intersect(&ray), None);
+    }
+}
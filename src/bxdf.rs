@@ -1,8 +1,10 @@
-use std::f32::consts::FRAC_1_PI;
+use std::f32::consts::{FRAC_1_PI, PI};
+use std::ops::BitOr;
 
 use crate::core::ShadingInfo;
 use crate::sampler::{cosine_weighted_hemisphere, Sampler};
 use crate::types::Real;
+use crate::vec2::Vec2;
 use crate::vec3::Vec3;
 
 // NOTE: assuming vectors in tangent space
@@ -11,18 +13,126 @@ fn reflect(v: Vec3, n: Vec3) -> Vec3 {
     -v + 2.0 * v.dot(n) * n
 }
 
+// returns None if total internal reflection occurs, eta = eta_i / eta_t
+fn refract(v: Vec3, n: Vec3, eta: Real) -> Option<Vec3> {
+    let cos_i = v.dot(n);
+    let sin2_t = eta * eta * (1.0 - cos_i * cos_i).max(0.0);
+    if sin2_t >= 1.0 {
+        return None;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    Some(-eta * v + (eta * cos_i - cos_t) * n)
+}
+
 fn abs_cos_theta(v: Vec3) -> Real {
     v.y().abs()
 }
 
+// fresnel reflectance at conductor interface with complex ior eta + i k
+fn fresnel_conductor(cos_i: Real, eta: Vec3, k: Vec3) -> Vec3 {
+    let cos_i = cos_i.abs().min(1.0);
+    let fresnel = |eta: Real, k: Real| {
+        let cos2 = cos_i * cos_i;
+        let sin2 = 1.0 - cos2;
+        let eta2 = eta * eta;
+        let k2 = k * k;
+
+        let t0 = eta2 - k2 - sin2;
+        let a2_plus_b2 = (t0 * t0 + 4.0 * eta2 * k2).sqrt();
+        let t1 = a2_plus_b2 + cos2;
+        let a = (0.5 * (a2_plus_b2 + t0)).max(0.0).sqrt();
+        let t2 = 2.0 * cos_i * a;
+        let r_s = (t1 - t2) / (t1 + t2);
+
+        let t3 = cos2 * a2_plus_b2 + sin2 * sin2;
+        let t4 = t2 * sin2;
+        let r_p = r_s * (t3 - t4) / (t3 + t4);
+        0.5 * (r_p + r_s)
+    };
+    Vec3::new(
+        fresnel(eta.x(), k.x()),
+        fresnel(eta.y(), k.y()),
+        fresnel(eta.z(), k.z()),
+    )
+}
+
+// fresnel reflectance of unpolarized light at dielectric interface
+fn fresnel_dielectric(cos_i: Real, eta_i: Real, eta_t: Real) -> Real {
+    let cos_i = cos_i.abs().min(1.0);
+    let sin_i = (1.0 - cos_i * cos_i).sqrt();
+    let sin_t = eta_i / eta_t * sin_i;
+    if sin_t >= 1.0 {
+        return 1.0;
+    }
+    let cos_t = (1.0 - sin_t * sin_t).sqrt();
+
+    let r_parallel = (eta_t * cos_i - eta_i * cos_t) / (eta_t * cos_i + eta_i * cos_t);
+    let r_perpendicular = (eta_i * cos_i - eta_t * cos_t) / (eta_i * cos_i + eta_t * cos_t);
+    0.5 * (r_parallel * r_parallel + r_perpendicular * r_perpendicular)
+}
+
+// (1 - cos)^5 of Schlick's approximation
+fn schlick_weight(cos: Real) -> Real {
+    let m = (1.0 - cos.abs()).clamp(0.0, 1.0);
+    let m2 = m * m;
+    m2 * m2 * m
+}
+
+// Schlick's approximation of fresnel reflectance with reflectance f0 at normal incidence
+fn fresnel_schlick(cos: Real, f0: Vec3) -> Vec3 {
+    f0 + schlick_weight(cos) * (Vec3::new(1.0, 1.0, 1.0) - f0)
+}
+
+// set of lobe flags
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct BxDFType(u8);
+
+impl BxDFType {
+    pub const NONE: BxDFType = BxDFType(0);
+    pub const DIFFUSE: BxDFType = BxDFType(1);
+    pub const GLOSSY: BxDFType = BxDFType(1 << 1);
+    pub const SPECULAR: BxDFType = BxDFType(1 << 2);
+    pub const REFLECTION: BxDFType = BxDFType(1 << 3);
+    pub const TRANSMISSION: BxDFType = BxDFType(1 << 4);
+
+    pub fn contains(&self, flags: BxDFType) -> bool {
+        self.0 & flags.0 == flags.0
+    }
+
+    // true if all lobes are delta distributions
+    pub fn is_specular(&self) -> bool {
+        self.contains(BxDFType::SPECULAR)
+            && !self.contains(BxDFType::DIFFUSE)
+            && !self.contains(BxDFType::GLOSSY)
+    }
+}
+
+impl BitOr for BxDFType {
+    type Output = BxDFType;
+
+    fn bitor(self, rhs: BxDFType) -> Self::Output {
+        BxDFType(self.0 | rhs.0)
+    }
+}
+
 pub struct BxDFSample {
-    pub f: Vec3,   // BxDF value
-    pub wi: Vec3,  // sampled direction
-    pub pdf: Real, // pdf
+    pub f: Vec3,                // BxDF value
+    pub wi: Vec3,               // sampled direction
+    pub pdf: Real,              // pdf
+    pub sampled_type: BxDFType, // lobe of sampled direction
 }
 
 pub trait BxDF {
+    // lobes contained in this BxDF
+    fn bxdf_type(&self) -> BxDFType;
+
     fn sample_direction(&self, info: &ShadingInfo, sampler: &mut Sampler) -> BxDFSample;
+
+    // evaluate BxDF for given pair of directions
+    fn eval(&self, wo: Vec3, wi: Vec3) -> Vec3;
+
+    // pdf of sampling wi given wo in solid angle measure
+    fn pdf(&self, wo: Vec3, wi: Vec3) -> Real;
 }
 
 pub struct Lambert {
@@ -36,26 +146,1355 @@ impl Lambert {
 }
 
 impl BxDF for Lambert {
-    fn sample_direction(&self, _info: &ShadingInfo, sampler: &mut Sampler) -> BxDFSample {
+    fn bxdf_type(&self) -> BxDFType {
+        BxDFType::DIFFUSE | BxDFType::REFLECTION
+    }
+
+    fn sample_direction(&self, info: &ShadingInfo, sampler: &mut Sampler) -> BxDFSample {
         let uv = sampler.next_2d();
-        let (wi, pdf) = cosine_weighted_hemisphere(uv);
+        let (mut wi, pdf) = cosine_weighted_hemisphere(uv);
+        // sample the same side as wo
+        if info.wo.y() < 0.0 {
+            wi = -wi;
+        }
         BxDFSample {
             f: FRAC_1_PI * self.rho,
             wi,
             pdf,
+            sampled_type: self.bxdf_type(),
+        }
+    }
+
+    fn eval(&self, wo: Vec3, wi: Vec3) -> Vec3 {
+        if wo.y() * wi.y() > 0.0 {
+            FRAC_1_PI * self.rho
+        } else {
+            Vec3::new(0.0, 0.0, 0.0)
+        }
+    }
+
+    fn pdf(&self, wo: Vec3, wi: Vec3) -> Real {
+        if wo.y() * wi.y() > 0.0 {
+            FRAC_1_PI * abs_cos_theta(wi)
+        } else {
+            0.0
+        }
+    }
+}
+
+// rough diffuse surface made of V-cavities whose slopes have standard deviation sigma in radians
+pub struct OrenNayar {
+    rho: Vec3, // albedo
+    a: Real,
+    b: Real,
+}
+
+impl OrenNayar {
+    pub fn new(rho: Vec3, sigma: Real) -> Self {
+        let sigma2 = sigma * sigma;
+        OrenNayar {
+            rho,
+            a: 1.0 - sigma2 / (2.0 * (sigma2 + 0.33)),
+            b: 0.45 * sigma2 / (sigma2 + 0.09),
+        }
+    }
+}
+
+impl BxDF for OrenNayar {
+    fn bxdf_type(&self) -> BxDFType {
+        BxDFType::DIFFUSE | BxDFType::REFLECTION
+    }
+
+    fn sample_direction(&self, info: &ShadingInfo, sampler: &mut Sampler) -> BxDFSample {
+        let (mut wi, _) = cosine_weighted_hemisphere(sampler.next_2d());
+        // sample the same side as wo
+        if info.wo.y() < 0.0 {
+            wi = -wi;
+        }
+        BxDFSample {
+            f: self.eval(info.wo, wi),
+            wi,
+            pdf: self.pdf(info.wo, wi),
+            sampled_type: self.bxdf_type(),
+        }
+    }
+
+    fn eval(&self, wo: Vec3, wi: Vec3) -> Vec3 {
+        if wo.y() * wi.y() <= 0.0 {
+            return Vec3::new(0.0, 0.0, 0.0);
+        }
+        let cos_o = abs_cos_theta(wo);
+        let cos_i = abs_cos_theta(wi);
+        let sin_o = (1.0 - cos_o * cos_o).max(0.0).sqrt();
+        let sin_i = (1.0 - cos_i * cos_i).max(0.0).sqrt();
+
+        // cosine of azimuthal difference
+        let max_cos = if sin_o > 1E-4 && sin_i > 1E-4 {
+            ((wo.x() * wi.x() + wo.z() * wi.z()) / (sin_o * sin_i)).max(0.0)
+        } else {
+            0.0
+        };
+
+        // sin(max(theta_i, theta_o)) * tan(min(theta_i, theta_o))
+        let sin_alpha_tan_beta = if cos_i > cos_o {
+            sin_o * sin_i / cos_i
+        } else {
+            sin_i * sin_o / cos_o
+        };
+        FRAC_1_PI * (self.a + self.b * max_cos * sin_alpha_tan_beta) * self.rho
+    }
+
+    fn pdf(&self, wo: Vec3, wi: Vec3) -> Real {
+        if wo.y() * wi.y() > 0.0 {
+            FRAC_1_PI * abs_cos_theta(wi)
+        } else {
+            0.0
+        }
+    }
+}
+
+// energy-preserving Oren-Nayar (Portsmouth et al. 2025)
+// Fujii's single scattering model plus multiple scattering term restoring lost energy
+pub struct EnergyPreservingOrenNayar {
+    rho: Vec3,       // albedo
+    roughness: Real, // in [0, 1]
+}
+
+impl EnergyPreservingOrenNayar {
+    const C1: Real = 0.5 - 2.0 / (3.0 * PI);
+    const C2: Real = 2.0 / 3.0 - 28.0 / (15.0 * PI);
+
+    pub fn new(rho: Vec3, roughness: Real) -> Self {
+        EnergyPreservingOrenNayar {
+            rho,
+            roughness: roughness.clamp(0.0, 1.0),
+        }
+    }
+
+    fn a(&self) -> Real {
+        1.0 / (1.0 + Self::C1 * self.roughness)
+    }
+
+    // directional albedo of single scattering term with unit albedo
+    fn albedo(&self, cos: Real) -> Real {
+        let a = self.a();
+        let sin = (1.0 - cos * cos).max(0.0).sqrt();
+        let g = sin * (cos.acos() - sin * cos)
+            + 2.0 / 3.0 * ((sin / cos) * (1.0 - sin * sin * sin) - sin);
+        a + self.roughness * a * FRAC_1_PI * g
+    }
+
+    // hemispherical average of albedo
+    fn average_albedo(&self) -> Real {
+        self.a() * (1.0 + Self::C2 * self.roughness)
+    }
+}
+
+impl BxDF for EnergyPreservingOrenNayar {
+    fn bxdf_type(&self) -> BxDFType {
+        BxDFType::DIFFUSE | BxDFType::REFLECTION
+    }
+
+    fn sample_direction(&self, info: &ShadingInfo, sampler: &mut Sampler) -> BxDFSample {
+        let (mut wi, _) = cosine_weighted_hemisphere(sampler.next_2d());
+        // sample the same side as wo
+        if info.wo.y() < 0.0 {
+            wi = -wi;
+        }
+        BxDFSample {
+            f: self.eval(info.wo, wi),
+            wi,
+            pdf: self.pdf(info.wo, wi),
+            sampled_type: self.bxdf_type(),
+        }
+    }
+
+    fn eval(&self, wo: Vec3, wi: Vec3) -> Vec3 {
+        if wo.y() * wi.y() <= 0.0 {
+            return Vec3::new(0.0, 0.0, 0.0);
+        }
+        let cos_o = abs_cos_theta(wo).max(1E-6);
+        let cos_i = abs_cos_theta(wi).max(1E-6);
+
+        // single scattering
+        let s = wo.dot(wi) - cos_o * cos_i;
+        let s_over_t = if s > 0.0 { s / cos_o.max(cos_i) } else { s };
+        let single = self.a() * (1.0 + self.roughness * s_over_t);
+
+        // multiple scattering, its albedo accounts for interreflections of colored surface
+        let average = self.average_albedo();
+        let one = Vec3::new(1.0, 1.0, 1.0);
+        let rho_ms = self.rho * self.rho * average / (one - (1.0 - average) * self.rho);
+        let eps = 1E-7;
+        let multiple = (1.0 - self.albedo(cos_o)).max(eps) * (1.0 - self.albedo(cos_i)).max(eps)
+            / (1.0 - average).max(eps);
+
+        FRAC_1_PI * (single * self.rho + multiple * rho_ms)
+    }
+
+    fn pdf(&self, wo: Vec3, wi: Vec3) -> Real {
+        if wo.y() * wi.y() > 0.0 {
+            FRAC_1_PI * abs_cos_theta(wi)
+        } else {
+            0.0
         }
     }
 }
 
 pub struct IdealReflection {}
 
+impl IdealReflection {
+    pub fn new() -> Self {
+        IdealReflection {}
+    }
+}
+
+impl Default for IdealReflection {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl BxDF for IdealReflection {
+    fn bxdf_type(&self) -> BxDFType {
+        BxDFType::SPECULAR | BxDFType::REFLECTION
+    }
+
     fn sample_direction(&self, info: &ShadingInfo, _sampler: &mut Sampler) -> BxDFSample {
-        let wi = reflect(info.wo, info.n);
+        let wi = reflect(info.wo, Vec3::new(0.0, 1.0, 0.0));
         BxDFSample {
             f: Vec3::new(1.0, 1.0, 1.0) / abs_cos_theta(wi),
             wi,
             pdf: 1.0,
+            sampled_type: self.bxdf_type(),
+        }
+    }
+
+    // delta distribution can't be evaluated
+    fn eval(&self, _wo: Vec3, _wi: Vec3) -> Vec3 {
+        Vec3::new(0.0, 0.0, 0.0)
+    }
+
+    fn pdf(&self, _wo: Vec3, _wi: Vec3) -> Real {
+        0.0
+    }
+}
+
+// smooth interface between vacuum and dielectric medium
+pub struct Dielectric {
+    ior: Real, // index of refraction of inside medium
+}
+
+impl Dielectric {
+    pub fn new(ior: Real) -> Self {
+        Dielectric { ior }
+    }
+}
+
+impl BxDF for Dielectric {
+    fn bxdf_type(&self) -> BxDFType {
+        BxDFType::SPECULAR | BxDFType::REFLECTION | BxDFType::TRANSMISSION
+    }
+
+    fn sample_direction(&self, info: &ShadingInfo, sampler: &mut Sampler) -> BxDFSample {
+        // wo is below shading normal when ray exits from the medium
+        let entering = info.wo.y() > 0.0;
+        let (eta_i, eta_t, n) = if entering {
+            (1.0, self.ior, Vec3::new(0.0, 1.0, 0.0))
+        } else {
+            (self.ior, 1.0, Vec3::new(0.0, -1.0, 0.0))
+        };
+
+        // choose reflection or refraction by fresnel reflectance. refract may still report total
+        // internal reflection near critical angle since it rounds differently, that is reflected
+        let fr = fresnel_dielectric(info.wo.y(), eta_i, eta_t);
+        let refracted = if sampler.next_1d() < fr {
+            None
+        } else {
+            refract(info.wo, n, eta_i / eta_t)
+        };
+        match refracted {
+            Some(wi) => {
+                // radiance is compressed into smaller solid angle when entering denser medium
+                let ft = (1.0 - fr) * (eta_i * eta_i) / (eta_t * eta_t);
+                BxDFSample {
+                    f: Vec3::new(ft, ft, ft) / abs_cos_theta(wi),
+                    wi,
+                    pdf: 1.0 - fr,
+                    sampled_type: BxDFType::SPECULAR | BxDFType::TRANSMISSION,
+                }
+            }
+            None => {
+                let wi = reflect(info.wo, n);
+                BxDFSample {
+                    f: Vec3::new(fr, fr, fr) / abs_cos_theta(wi),
+                    wi,
+                    pdf: fr,
+                    sampled_type: BxDFType::SPECULAR | BxDFType::REFLECTION,
+                }
+            }
+        }
+    }
+
+    // delta distribution can't be evaluated
+    fn eval(&self, _wo: Vec3, _wi: Vec3) -> Vec3 {
+        Vec3::new(0.0, 0.0, 0.0)
+    }
+
+    fn pdf(&self, _wo: Vec3, _wi: Vec3) -> Real {
+        0.0
+    }
+}
+
+// map perceptual roughness and anisotropy in [0, 1) to alpha along tangent and bitangent
+pub fn roughness_to_alpha(roughness: Real, anisotropic: Real) -> (Real, Real) {
+    let aspect = (1.0 - 0.9 * anisotropic).sqrt();
+    let alpha = roughness * roughness;
+    ((alpha / aspect).max(1E-3), (alpha * aspect).max(1E-3))
+}
+
+// GGX(Trowbridge-Reitz) microfacet distribution, x is tangent and z is bitangent
+pub struct GGX {
+    alpha_x: Real,
+    alpha_z: Real,
+}
+
+impl GGX {
+    pub fn new(alpha_x: Real, alpha_z: Real) -> Self {
+        GGX { alpha_x, alpha_z }
+    }
+
+    // distribution of microfacet normals
+    pub fn d(&self, wh: Vec3) -> Real {
+        let x = wh.x() / self.alpha_x;
+        let z = wh.z() / self.alpha_z;
+        let t = x * x + z * z + wh.y() * wh.y();
+        1.0 / (PI * self.alpha_x * self.alpha_z * t * t)
+    }
+
+    fn lambda(&self, w: Vec3) -> Real {
+        let x = self.alpha_x * w.x();
+        let z = self.alpha_z * w.z();
+        let tan2_alpha2 = (x * x + z * z) / (w.y() * w.y());
+        0.5 * (-1.0 + (1.0 + tan2_alpha2).sqrt())
+    }
+
+    // masking function
+    pub fn g1(&self, w: Vec3) -> Real {
+        1.0 / (1.0 + self.lambda(w))
+    }
+
+    // height-correlated masking-shadowing function
+    pub fn g2(&self, wo: Vec3, wi: Vec3) -> Real {
+        1.0 / (1.0 + self.lambda(wo) + self.lambda(wi))
+    }
+
+    // sample microfacet normal visible from wo, wo must be in upper hemisphere
+    pub fn sample_visible_normal(&self, wo: Vec3, uv: Vec2) -> Vec3 {
+        // stretch view direction to hemisphere configuration
+        let vh = Vec3::new(self.alpha_x * wo.x(), wo.y(), self.alpha_z * wo.z()).normalize();
+
+        let len2 = vh.x() * vh.x() + vh.z() * vh.z();
+        let t1 = if len2 > 0.0 {
+            Vec3::new(-vh.z(), 0.0, vh.x()) / len2.sqrt()
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let t2 = t1.cross(vh);
+
+        // sample projected area of hemisphere
+        let r = uv.x().sqrt();
+        let phi = 2.0 * PI * uv.y();
+        let p1 = r * phi.cos();
+        let s = 0.5 * (1.0 + vh.y());
+        let p2 = (1.0 - s) * (1.0 - p1 * p1).sqrt() + s * r * phi.sin();
+        let nh = p1 * t1 + p2 * t2 + (1.0 - p1 * p1 - p2 * p2).max(0.0).sqrt() * vh;
+
+        // unstretch
+        Vec3::new(
+            self.alpha_x * nh.x(),
+            nh.y().max(1E-6),
+            self.alpha_z * nh.z(),
+        )
+        .normalize()
+    }
+
+    // pdf of sample_visible_normal
+    pub fn pdf_visible_normal(&self, wo: Vec3, wh: Vec3) -> Real {
+        self.g1(wo) * wo.dot(wh).max(0.0) * self.d(wh) / abs_cos_theta(wo)
+    }
+}
+
+// rough metal described by GGX microfacets
+pub struct MicrofacetConductor {
+    eta: Vec3, // real part of ior
+    k: Vec3,   // imaginary part of ior
+    distribution: GGX,
+}
+
+impl MicrofacetConductor {
+    pub fn new(eta: Vec3, k: Vec3, alpha_x: Real, alpha_z: Real) -> Self {
+        MicrofacetConductor {
+            eta,
+            k,
+            distribution: GGX::new(alpha_x, alpha_z),
+        }
+    }
+}
+
+impl BxDF for MicrofacetConductor {
+    fn bxdf_type(&self) -> BxDFType {
+        BxDFType::GLOSSY | BxDFType::REFLECTION
+    }
+
+    fn sample_direction(&self, info: &ShadingInfo, sampler: &mut Sampler) -> BxDFSample {
+        // flip to upper hemisphere so that both sides reflect
+        let sign = if info.wo.y() < 0.0 { -1.0 } else { 1.0 };
+        let wo = sign * info.wo;
+        let wh = self
+            .distribution
+            .sample_visible_normal(wo, sampler.next_2d());
+        let wi = sign * reflect(wo, wh);
+        BxDFSample {
+            f: self.eval(info.wo, wi),
+            wi,
+            pdf: self.pdf(info.wo, wi),
+            sampled_type: self.bxdf_type(),
+        }
+    }
+
+    fn eval(&self, wo: Vec3, wi: Vec3) -> Vec3 {
+        if wo.y() * wi.y() <= 0.0 {
+            return Vec3::new(0.0, 0.0, 0.0);
+        }
+        let sign = if wo.y() < 0.0 { -1.0 } else { 1.0 };
+        let (wo, wi) = (sign * wo, sign * wi);
+
+        let wh = (wo + wi).normalize();
+        let d = self.distribution.d(wh);
+        let g = self.distribution.g2(wo, wi);
+        let fr = fresnel_conductor(wi.dot(wh), self.eta, self.k);
+        d * g * fr / (4.0 * wo.y() * wi.y())
+    }
+
+    fn pdf(&self, wo: Vec3, wi: Vec3) -> Real {
+        if wo.y() * wi.y() <= 0.0 {
+            return 0.0;
+        }
+        let sign = if wo.y() < 0.0 { -1.0 } else { 1.0 };
+        let (wo, wi) = (sign * wo, sign * wi);
+
+        let wh = (wo + wi).normalize();
+        self.distribution.pdf_visible_normal(wo, wh) / (4.0 * wo.dot(wh))
+    }
+}
+
+// rough interface between vacuum and dielectric medium described by GGX microfacets
+pub struct MicrofacetDielectric {
+    ior: Real,           // index of refraction of inside medium
+    transmittance: Vec3, // color of transmitted light, reflection isn't tinted
+    distribution: GGX,
+}
+
+impl MicrofacetDielectric {
+    pub fn new(ior: Real, alpha_x: Real, alpha_z: Real) -> Self {
+        Self::new_with_tint(ior, alpha_x, alpha_z, Vec3::new(1.0, 1.0, 1.0))
+    }
+
+    pub fn new_with_tint(ior: Real, alpha_x: Real, alpha_z: Real, transmittance: Vec3) -> Self {
+        MicrofacetDielectric {
+            ior,
+            transmittance,
+            distribution: GGX::new(alpha_x, alpha_z),
+        }
+    }
+
+    // ior of the side of wo and of the opposite side
+    fn etas(&self, wo: Vec3) -> (Real, Real) {
+        if wo.y() > 0.0 {
+            (1.0, self.ior)
+        } else {
+            (self.ior, 1.0)
+        }
+    }
+
+    // half vector of refraction, oriented to upper hemisphere
+    fn refraction_half_vector(wo: Vec3, wi: Vec3, eta_i: Real, eta_t: Real) -> Vec3 {
+        let wh = (eta_i * wo + eta_t * wi).normalize();
+        if wh.y() < 0.0 {
+            -wh
+        } else {
+            wh
+        }
+    }
+}
+
+impl BxDF for MicrofacetDielectric {
+    fn bxdf_type(&self) -> BxDFType {
+        BxDFType::GLOSSY | BxDFType::REFLECTION | BxDFType::TRANSMISSION
+    }
+
+    fn sample_direction(&self, info: &ShadingInfo, sampler: &mut Sampler) -> BxDFSample {
+        // flip to upper hemisphere, microfacet normals are always there
+        let sign = if info.wo.y() < 0.0 { -1.0 } else { 1.0 };
+        let wo = sign * info.wo;
+        let (eta_i, eta_t) = self.etas(info.wo);
+        let wh = self
+            .distribution
+            .sample_visible_normal(wo, sampler.next_2d());
+
+        // choose reflection or refraction on microfacet by fresnel reflectance
+        let fr = fresnel_dielectric(wo.dot(wh), eta_i, eta_t);
+        let (wi, sampled_type) = if sampler.next_1d() < fr {
+            (reflect(wo, wh), BxDFType::GLOSSY | BxDFType::REFLECTION)
+        } else {
+            // refract may still report total internal reflection near critical angle since it
+            // rounds differently from fresnel_dielectric, such sample is discarded
+            match refract(wo, wh, eta_i / eta_t) {
+                Some(wi) => (wi, BxDFType::GLOSSY | BxDFType::TRANSMISSION),
+                None => {
+                    return BxDFSample {
+                        f: Vec3::new(0.0, 0.0, 0.0),
+                        wi: sign * reflect(wo, wh),
+                        pdf: 0.0,
+                        sampled_type: BxDFType::GLOSSY | BxDFType::TRANSMISSION,
+                    }
+                }
+            }
+        };
+
+        // direction scattered to the wrong side by microfacet is discarded
+        let is_transmission = sampled_type.contains(BxDFType::TRANSMISSION);
+        if (wi.y() < 0.0) != is_transmission || wi.y() == 0.0 {
+            return BxDFSample {
+                f: Vec3::new(0.0, 0.0, 0.0),
+                wi: sign * wi,
+                pdf: 0.0,
+                sampled_type,
+            };
+        }
+
+        let wi = sign * wi;
+        BxDFSample {
+            f: self.eval(info.wo, wi),
+            wi,
+            pdf: self.pdf(info.wo, wi),
+            sampled_type,
+        }
+    }
+
+    fn eval(&self, wo: Vec3, wi: Vec3) -> Vec3 {
+        if wo.y() == 0.0 || wi.y() == 0.0 {
+            return Vec3::new(0.0, 0.0, 0.0);
+        }
+        let (eta_i, eta_t) = self.etas(wo);
+        let sign = if wo.y() < 0.0 { -1.0 } else { 1.0 };
+        let (wo, wi) = (sign * wo, sign * wi);
+
+        if wi.y() > 0.0 {
+            let wh = (wo + wi).normalize();
+            let d = self.distribution.d(wh);
+            let g = self.distribution.g2(wo, wi);
+            let fr = fresnel_dielectric(wo.dot(wh), eta_i, eta_t);
+            let f = d * g * fr / (4.0 * wo.y() * wi.y());
+            return Vec3::new(f, f, f);
+        }
+
+        let wh = Self::refraction_half_vector(wo, wi, eta_i, eta_t);
+        // wo and wi must be on the opposite sides of microfacet
+        if wo.dot(wh) <= 0.0 || wi.dot(wh) >= 0.0 {
+            return Vec3::new(0.0, 0.0, 0.0);
+        }
+        let d = self.distribution.d(wh);
+        let g = self.distribution.g2(wo, wi);
+        let fr = fresnel_dielectric(wo.dot(wh), eta_i, eta_t);
+        let denom = eta_i * wo.dot(wh) + eta_t * wi.dot(wh);
+        // eta_i^2 instead of eta_t^2 accounts for radiance scaling
+        let f = (1.0 - fr) * d * g * eta_i * eta_i * (wi.dot(wh) * wo.dot(wh)).abs()
+            / (wo.y() * wi.y() * denom * denom).abs();
+        f * self.transmittance
+    }
+
+    fn pdf(&self, wo: Vec3, wi: Vec3) -> Real {
+        if wo.y() == 0.0 || wi.y() == 0.0 {
+            return 0.0;
+        }
+        let (eta_i, eta_t) = self.etas(wo);
+        let sign = if wo.y() < 0.0 { -1.0 } else { 1.0 };
+        let (wo, wi) = (sign * wo, sign * wi);
+
+        if wi.y() > 0.0 {
+            let wh = (wo + wi).normalize();
+            let fr = fresnel_dielectric(wo.dot(wh), eta_i, eta_t);
+            return fr * self.distribution.pdf_visible_normal(wo, wh) / (4.0 * wo.dot(wh));
+        }
+
+        let wh = Self::refraction_half_vector(wo, wi, eta_i, eta_t);
+        if wo.dot(wh) <= 0.0 || wi.dot(wh) >= 0.0 {
+            return 0.0;
+        }
+        let fr = fresnel_dielectric(wo.dot(wh), eta_i, eta_t);
+        let denom = eta_i * wo.dot(wh) + eta_t * wi.dot(wh);
+        // jacobian of half vector with respect to wi
+        let dwh_dwi = eta_t * eta_t * wi.dot(wh).abs() / (denom * denom);
+        (1.0 - fr) * self.distribution.pdf_visible_normal(wo, wh) * dwh_dwi
+    }
+}
+
+// diffuse substrate under glossy dielectric coating
+pub struct CoatedDiffuse {
+    kd: Vec3,  // diffuse albedo
+    ks: Vec3,  // scale of coating reflection
+    ior: Real, // index of refraction of coating
+    distribution: GGX,
+}
+
+impl CoatedDiffuse {
+    pub fn new(kd: Vec3, ks: Vec3, ior: Real, alpha_x: Real, alpha_z: Real) -> Self {
+        CoatedDiffuse {
+            kd,
+            ks,
+            ior,
+            distribution: GGX::new(alpha_x, alpha_z),
+        }
+    }
+
+    // probability of sampling coating reflection
+    fn specular_prob(&self) -> Real {
+        let d = self.kd.max();
+        let s = self.ks.max();
+        if d + s > 0.0 {
+            s / (d + s)
+        } else {
+            0.5
+        }
+    }
+}
+
+impl BxDF for CoatedDiffuse {
+    fn bxdf_type(&self) -> BxDFType {
+        BxDFType::DIFFUSE | BxDFType::GLOSSY | BxDFType::REFLECTION
+    }
+
+    fn sample_direction(&self, info: &ShadingInfo, sampler: &mut Sampler) -> BxDFSample {
+        // flip to upper hemisphere so that both sides reflect
+        let sign = if info.wo.y() < 0.0 { -1.0 } else { 1.0 };
+        let wo = sign * info.wo;
+        let (wi, sampled_type) = if sampler.next_1d() < self.specular_prob() {
+            let wh = self
+                .distribution
+                .sample_visible_normal(wo, sampler.next_2d());
+            (reflect(wo, wh), BxDFType::GLOSSY | BxDFType::REFLECTION)
+        } else {
+            let (wi, _) = cosine_weighted_hemisphere(sampler.next_2d());
+            (wi, BxDFType::DIFFUSE | BxDFType::REFLECTION)
+        };
+
+        let wi = sign * wi;
+        BxDFSample {
+            f: self.eval(info.wo, wi),
+            wi,
+            pdf: self.pdf(info.wo, wi),
+            sampled_type,
+        }
+    }
+
+    fn eval(&self, wo: Vec3, wi: Vec3) -> Vec3 {
+        if wo.y() * wi.y() <= 0.0 {
+            return Vec3::new(0.0, 0.0, 0.0);
+        }
+        let sign = if wo.y() < 0.0 { -1.0 } else { 1.0 };
+        let (wo, wi) = (sign * wo, sign * wi);
+
+        let wh = (wo + wi).normalize();
+        let d = self.distribution.d(wh);
+        let g = self.distribution.g2(wo, wi);
+        let fr = fresnel_dielectric(wo.dot(wh), 1.0, self.ior);
+        // diffuse light is what the coating transmits on entering and leaving
+        let transmittance = (1.0 - fresnel_dielectric(wo.y(), 1.0, self.ior))
+            * (1.0 - fresnel_dielectric(wi.y(), 1.0, self.ior));
+        transmittance * FRAC_1_PI * self.kd + d * g * fr / (4.0 * wo.y() * wi.y()) * self.ks
+    }
+
+    fn pdf(&self, wo: Vec3, wi: Vec3) -> Real {
+        if wo.y() * wi.y() <= 0.0 {
+            return 0.0;
+        }
+        let sign = if wo.y() < 0.0 { -1.0 } else { 1.0 };
+        let (wo, wi) = (sign * wo, sign * wi);
+
+        let wh = (wo + wi).normalize();
+        let pdf_specular = self.distribution.pdf_visible_normal(wo, wh) / (4.0 * wo.dot(wh));
+        let pdf_diffuse = FRAC_1_PI * abs_cos_theta(wi);
+        let p = self.specular_prob();
+        p * pdf_specular + (1.0 - p) * pdf_diffuse
+    }
+}
+
+// BxDF multiplied by color
+pub struct ScaledBxDF {
+    bxdf: Box<dyn BxDF>,
+    scale: Vec3,
+}
+
+impl ScaledBxDF {
+    pub fn new(bxdf: Box<dyn BxDF>, scale: Vec3) -> Self {
+        ScaledBxDF { bxdf, scale }
+    }
+}
+
+impl BxDF for ScaledBxDF {
+    fn bxdf_type(&self) -> BxDFType {
+        self.bxdf.bxdf_type()
+    }
+
+    fn sample_direction(&self, info: &ShadingInfo, sampler: &mut Sampler) -> BxDFSample {
+        let sample = self.bxdf.sample_direction(info, sampler);
+        BxDFSample {
+            f: self.scale * sample.f,
+            ..sample
+        }
+    }
+
+    fn eval(&self, wo: Vec3, wi: Vec3) -> Vec3 {
+        self.scale * self.bxdf.eval(wo, wi)
+    }
+
+    fn pdf(&self, wo: Vec3, wi: Vec3) -> Real {
+        self.bxdf.pdf(wo, wi)
+    }
+}
+
+// diffuse lobe of Disney principled BRDF with retro-reflection, subsurface approximation and sheen
+pub struct DisneyDiffuse {
+    base_color: Vec3,
+    roughness: Real,  // perceptual roughness
+    subsurface: Real, // blend factor toward Hanrahan-Krueger like flattened diffuse
+    sheen: Vec3,      // color of grazing retro-reflection
+}
+
+impl DisneyDiffuse {
+    pub fn new(base_color: Vec3, roughness: Real, subsurface: Real, sheen: Vec3) -> Self {
+        DisneyDiffuse {
+            base_color,
+            roughness,
+            subsurface,
+            sheen,
+        }
+    }
+}
+
+impl BxDF for DisneyDiffuse {
+    fn bxdf_type(&self) -> BxDFType {
+        BxDFType::DIFFUSE | BxDFType::REFLECTION
+    }
+
+    fn sample_direction(&self, info: &ShadingInfo, sampler: &mut Sampler) -> BxDFSample {
+        let (mut wi, _) = cosine_weighted_hemisphere(sampler.next_2d());
+        // sample the same side as wo
+        if info.wo.y() < 0.0 {
+            wi = -wi;
+        }
+        BxDFSample {
+            f: self.eval(info.wo, wi),
+            wi,
+            pdf: self.pdf(info.wo, wi),
+            sampled_type: self.bxdf_type(),
+        }
+    }
+
+    fn eval(&self, wo: Vec3, wi: Vec3) -> Vec3 {
+        if wo.y() * wi.y() <= 0.0 {
+            return Vec3::new(0.0, 0.0, 0.0);
+        }
+        let cos_o = abs_cos_theta(wo);
+        let cos_i = abs_cos_theta(wi);
+        let cos_d = wi.dot((wo + wi).normalize());
+        let fo = schlick_weight(cos_o);
+        let fi = schlick_weight(cos_i);
+
+        // retro-reflection grows with roughness
+        let fd90 = 0.5 + 2.0 * self.roughness * cos_d * cos_d;
+        let fd = (1.0 + (fd90 - 1.0) * fo) * (1.0 + (fd90 - 1.0) * fi);
+
+        let fss90 = self.roughness * cos_d * cos_d;
+        let fss = (1.0 + (fss90 - 1.0) * fo) * (1.0 + (fss90 - 1.0) * fi);
+        let ss = 1.25 * (fss * (1.0 / (cos_o + cos_i) - 0.5) + 0.5);
+
+        let diffuse = (1.0 - self.subsurface) * fd + self.subsurface * ss;
+        FRAC_1_PI * diffuse * self.base_color + schlick_weight(cos_d) * self.sheen
+    }
+
+    fn pdf(&self, wo: Vec3, wi: Vec3) -> Real {
+        if wo.y() * wi.y() > 0.0 {
+            FRAC_1_PI * abs_cos_theta(wi)
+        } else {
+            0.0
+        }
+    }
+}
+
+// GGX reflection lobe whose fresnel is Schlick's approximation, used for specular of Disney BRDF
+pub struct DisneySpecular {
+    f0: Vec3, // reflectance at normal incidence
+    distribution: GGX,
+}
+
+impl DisneySpecular {
+    pub fn new(f0: Vec3, alpha_x: Real, alpha_z: Real) -> Self {
+        DisneySpecular {
+            f0,
+            distribution: GGX::new(alpha_x, alpha_z),
+        }
+    }
+}
+
+impl BxDF for DisneySpecular {
+    fn bxdf_type(&self) -> BxDFType {
+        BxDFType::GLOSSY | BxDFType::REFLECTION
+    }
+
+    fn sample_direction(&self, info: &ShadingInfo, sampler: &mut Sampler) -> BxDFSample {
+        // flip to upper hemisphere so that both sides reflect
+        let sign = if info.wo.y() < 0.0 { -1.0 } else { 1.0 };
+        let wo = sign * info.wo;
+        let wh = self
+            .distribution
+            .sample_visible_normal(wo, sampler.next_2d());
+        let wi = sign * reflect(wo, wh);
+        BxDFSample {
+            f: self.eval(info.wo, wi),
+            wi,
+            pdf: self.pdf(info.wo, wi),
+            sampled_type: self.bxdf_type(),
+        }
+    }
+
+    fn eval(&self, wo: Vec3, wi: Vec3) -> Vec3 {
+        if wo.y() * wi.y() <= 0.0 {
+            return Vec3::new(0.0, 0.0, 0.0);
+        }
+        let sign = if wo.y() < 0.0 { -1.0 } else { 1.0 };
+        let (wo, wi) = (sign * wo, sign * wi);
+
+        let wh = (wo + wi).normalize();
+        let d = self.distribution.d(wh);
+        let g = self.distribution.g2(wo, wi);
+        let fr = fresnel_schlick(wi.dot(wh), self.f0);
+        d * g * fr / (4.0 * wo.y() * wi.y())
+    }
+
+    fn pdf(&self, wo: Vec3, wi: Vec3) -> Real {
+        if wo.y() * wi.y() <= 0.0 {
+            return 0.0;
+        }
+        let sign = if wo.y() < 0.0 { -1.0 } else { 1.0 };
+        let (wo, wi) = (sign * wo, sign * wi);
+
+        let wh = (wo + wi).normalize();
+        self.distribution.pdf_visible_normal(wo, wh) / (4.0 * wo.dot(wh))
+    }
+}
+
+// clearcoat lobe of Disney BRDF, GTR1 distribution with fixed ior 1.5
+pub struct DisneyClearcoat {
+    alpha: Real,
+    masking: GGX, // clearcoat uses GGX masking with fixed roughness
+}
+
+impl DisneyClearcoat {
+    pub fn new(alpha: Real) -> Self {
+        DisneyClearcoat {
+            alpha,
+            masking: GGX::new(0.25, 0.25),
+        }
+    }
+
+    // GTR1(Berry) distribution of microfacet normals
+    fn d(&self, wh: Vec3) -> Real {
+        let a2 = self.alpha * self.alpha;
+        let cos2 = wh.y() * wh.y();
+        (a2 - 1.0) / (PI * a2.ln() * (1.0 + (a2 - 1.0) * cos2))
+    }
+}
+
+impl BxDF for DisneyClearcoat {
+    fn bxdf_type(&self) -> BxDFType {
+        BxDFType::GLOSSY | BxDFType::REFLECTION
+    }
+
+    fn sample_direction(&self, info: &ShadingInfo, sampler: &mut Sampler) -> BxDFSample {
+        // sample microfacet normal in proportion to D(wh) cos(theta_h)
+        let uv = sampler.next_2d();
+        let a2 = self.alpha * self.alpha;
+        let cos_h = ((1.0 - a2.powf(1.0 - uv.x())) / (1.0 - a2)).max(0.0).sqrt();
+        let sin_h = (1.0 - cos_h * cos_h).max(0.0).sqrt();
+        let phi = 2.0 * PI * uv.y();
+        let wh = Vec3::new(sin_h * phi.cos(), cos_h, sin_h * phi.sin());
+
+        let sign = if info.wo.y() < 0.0 { -1.0 } else { 1.0 };
+        let wi = sign * reflect(sign * info.wo, wh);
+        BxDFSample {
+            f: self.eval(info.wo, wi),
+            wi,
+            pdf: self.pdf(info.wo, wi),
+            sampled_type: self.bxdf_type(),
+        }
+    }
+
+    fn eval(&self, wo: Vec3, wi: Vec3) -> Vec3 {
+        if wo.y() * wi.y() <= 0.0 {
+            return Vec3::new(0.0, 0.0, 0.0);
+        }
+        let sign = if wo.y() < 0.0 { -1.0 } else { 1.0 };
+        let (wo, wi) = (sign * wo, sign * wi);
+
+        let wh = (wo + wi).normalize();
+        let d = self.d(wh);
+        let g = self.masking.g1(wo) * self.masking.g1(wi);
+        let fr = fresnel_schlick(wi.dot(wh), Vec3::new(0.04, 0.04, 0.04));
+        d * g * fr / (4.0 * wo.y() * wi.y())
+    }
+
+    fn pdf(&self, wo: Vec3, wi: Vec3) -> Real {
+        if wo.y() * wi.y() <= 0.0 {
+            return 0.0;
+        }
+        let sign = if wo.y() < 0.0 { -1.0 } else { 1.0 };
+        let (wo, wi) = (sign * wo, sign * wi);
+
+        let wh = (wo + wi).normalize();
+        self.d(wh) * wh.y() / (4.0 * wo.dot(wh))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bxdf::*;
+    use crate::sampler::uniform_sphere;
+
+    #[test]
+    fn lambert_sample_matches_eval_pdf() {
+        let lambert = Lambert::new(Vec3::new(0.5, 0.5, 0.5));
+        let info = ShadingInfo {
+            x: Vec3::new(0.0, 0.0, 0.0),
+            n: Vec3::new(0.0, 1.0, 0.0),
+            wo: Vec3::new(1.0, 1.0, 0.0).normalize(),
+            t: Vec3::new(1.0, 0.0, 0.0),
+            b: Vec3::new(0.0, 0.0, 1.0),
+            uv: Vec2::new(0.0, 0.0),
+        };
+
+        let mut sampler = Sampler::new(0);
+        for _ in 0..100 {
+            let sample = lambert.sample_direction(&info, &mut sampler);
+            assert_eq!(sample.f, lambert.eval(info.wo, sample.wi));
+            assert_eq!(sample.sampled_type, lambert.bxdf_type());
+            assert!(!sample.sampled_type.is_specular());
+            assert!((sample.pdf - lambert.pdf(info.wo, sample.wi)).abs() < 1E-5);
+        }
+
+        let below = Vec3::new(0.0, -1.0, 0.0);
+        assert_eq!(lambert.eval(info.wo, below), Vec3::new(0.0, 0.0, 0.0));
+        assert_eq!(lambert.pdf(info.wo, below), 0.0);
+    }
+
+    #[test]
+    fn ideal_reflection_sample() {
+        let reflection = IdealReflection::new();
+        let info = ShadingInfo {
+            x: Vec3::new(0.0, 0.0, 0.0),
+            n: Vec3::new(0.0, 0.0, 1.0),
+            wo: Vec3::new(1.0, 1.0, 0.0).normalize(),
+            t: Vec3::new(1.0, 0.0, 0.0),
+            b: Vec3::new(0.0, -1.0, 0.0),
+            uv: Vec2::new(0.0, 0.0),
+        };
+
+        let mut sampler = Sampler::new(0);
+        let sample = reflection.sample_direction(&info, &mut sampler);
+        assert_eq!(sample.wi, Vec3::new(-1.0, 1.0, 0.0).normalize());
+        assert!(sample.sampled_type.is_specular());
+        assert!(sample.sampled_type.contains(BxDFType::REFLECTION));
+        assert_eq!(
+            reflection.eval(info.wo, sample.wi),
+            Vec3::new(0.0, 0.0, 0.0)
+        );
+        assert_eq!(reflection.pdf(info.wo, sample.wi), 0.0);
+    }
+
+    #[test]
+    fn fresnel() {
+        // normal incidence, ((n1 - n2) / (n1 + n2))^2
+        assert!((fresnel_dielectric(1.0, 1.0, 1.5) - 0.04).abs() < 1E-6);
+        assert!((fresnel_dielectric(1.0, 1.5, 1.0) - 0.04).abs() < 1E-6);
+        // total internal reflection
+        assert_eq!(fresnel_dielectric(0.1, 1.5, 1.0), 1.0);
+    }
+
+    #[test]
+    fn dielectric_sample() {
+        let glass = Dielectric::new(1.5);
+        let mut sampler = Sampler::new(0);
+        for wo in [
+            Vec3::new(1.0, 1.0, 0.0).normalize(),
+            Vec3::new(0.2, -1.0, 0.3).normalize(),
+        ] {
+            let info = ShadingInfo {
+                x: Vec3::new(0.0, 0.0, 0.0),
+                n: Vec3::new(0.0, 1.0, 0.0),
+                wo,
+                t: Vec3::new(1.0, 0.0, 0.0),
+                b: Vec3::new(0.0, 0.0, 1.0),
+                uv: Vec2::new(0.0, 0.0),
+            };
+            for _ in 0..100 {
+                let sample = glass.sample_direction(&info, &mut sampler);
+                assert!((sample.wi.length() - 1.0).abs() < 1E-5);
+                assert!(sample.sampled_type.is_specular());
+                if sample.sampled_type.contains(BxDFType::TRANSMISSION) {
+                    assert!(wo.y() * sample.wi.y() < 0.0);
+                    // snell's law
+                    let (eta_i, eta_t) = if wo.y() > 0.0 { (1.0, 1.5) } else { (1.5, 1.0) };
+                    let sin_i = (1.0 - wo.y() * wo.y()).sqrt();
+                    let sin_t = (1.0 - sample.wi.y() * sample.wi.y()).sqrt();
+                    assert!((eta_i * sin_i - eta_t * sin_t).abs() < 1E-4);
+                } else {
+                    assert_eq!(sample.wi, Vec3::new(-wo.x(), wo.y(), -wo.z()));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn dielectric_near_critical_angle() {
+        // fresnel_dielectric and refract may disagree on total internal reflection by rounding
+        let mut sampler = Sampler::new(0);
+        for i in 0..2000 {
+            let ior = 1.01 + 1.99 * i as Real / 2000.0;
+            let cos_critical = (1.0 - 1.0 / (ior * ior)).sqrt();
+            for ulps in -8..=8 {
+                let cos = Real::from_bits((cos_critical.to_bits() as i32 + ulps) as u32);
+                let wo = Vec3::new((1.0 - cos * cos).sqrt(), -cos, 0.0);
+                let disagree = fresnel_dielectric(cos, ior, 1.0) < 1.0
+                    && refract(wo, Vec3::new(0.0, -1.0, 0.0), ior).is_none();
+                if !disagree {
+                    continue;
+                }
+
+                let glass = Dielectric::new(ior);
+                let info = ShadingInfo {
+                    x: Vec3::new(0.0, 0.0, 0.0),
+                    n: Vec3::new(0.0, 1.0, 0.0),
+                    wo,
+                    t: Vec3::new(1.0, 0.0, 0.0),
+                    b: Vec3::new(0.0, 0.0, 1.0),
+                    uv: Vec2::new(0.0, 0.0),
+                };
+                for _ in 0..1000 {
+                    let sample = glass.sample_direction(&info, &mut sampler);
+                    assert!(sample.sampled_type.contains(BxDFType::REFLECTION));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn fresnel_conductor_without_absorption() {
+        for cos_i in [0.1, 0.5, 1.0] {
+            let fr = fresnel_conductor(cos_i, Vec3::new(1.5, 1.5, 1.5), Vec3::new(0.0, 0.0, 0.0));
+            assert!((fr.x() - fresnel_dielectric(cos_i, 1.0, 1.5)).abs() < 1E-5);
+        }
+    }
+
+    #[test]
+    fn ggx_normalized() {
+        // projected area of microfacets equals to macro surface, also for visible normals
+        let ggx = GGX::new(0.3, 0.6);
+        let wo = Vec3::new(0.5, 0.6, -0.2).normalize();
+        let mut sampler = Sampler::new(0);
+        let n_samples = 100000;
+        let mut projected_area = 0.0;
+        let mut visible = 0.0;
+        for _ in 0..n_samples {
+            let (wh, pdf) = uniform_sphere(sampler.next_2d());
+            if wh.y() > 0.0 {
+                projected_area += ggx.d(wh) * wh.y() / pdf;
+                visible += ggx.pdf_visible_normal(wo, wh) / pdf;
+            }
+        }
+        projected_area /= n_samples as Real;
+        visible /= n_samples as Real;
+        assert!((projected_area - 1.0).abs() < 2E-2);
+        assert!((visible - 1.0).abs() < 2E-2);
+    }
+
+    #[test]
+    fn microfacet_conductor_sample() {
+        let conductor =
+            MicrofacetConductor::new(Vec3::new(0.2, 0.9, 1.1), Vec3::new(3.9, 2.4, 2.2), 0.2, 0.4);
+        let mut sampler = Sampler::new(0);
+        for wo in [
+            Vec3::new(0.3, 1.0, 0.2).normalize(),
+            Vec3::new(0.3, -1.0, 0.2).normalize(),
+        ] {
+            let info = ShadingInfo {
+                x: Vec3::new(0.0, 0.0, 0.0),
+                n: Vec3::new(0.0, 1.0, 0.0),
+                wo,
+                t: Vec3::new(1.0, 0.0, 0.0),
+                b: Vec3::new(0.0, 0.0, 1.0),
+                uv: Vec2::new(0.0, 0.0),
+            };
+            for _ in 0..100 {
+                let sample = conductor.sample_direction(&info, &mut sampler);
+                if wo.y() * sample.wi.y() <= 0.0 {
+                    assert_eq!(sample.f, Vec3::new(0.0, 0.0, 0.0));
+                    continue;
+                }
+                assert!(sample.pdf > 0.0);
+                assert_eq!(sample.f, conductor.eval(wo, sample.wi));
+                // sample weight is F * G2 / G1, never exceeds one
+                let weight = sample.f * sample.wi.y().abs() / sample.pdf;
+                assert!(weight.max() <= 1.0 + 1E-4);
+            }
+        }
+    }
+
+    #[test]
+    fn microfacet_dielectric_sample() {
+        let glass = MicrofacetDielectric::new(1.5, 0.6, 0.5);
+        let mut sampler = Sampler::new(0);
+        for wo in [
+            Vec3::new(0.3, 1.0, 0.2).normalize(),
+            Vec3::new(0.3, -1.0, 0.2).normalize(),
+        ] {
+            let info = ShadingInfo {
+                x: Vec3::new(0.0, 0.0, 0.0),
+                n: Vec3::new(0.0, 1.0, 0.0),
+                wo,
+                t: Vec3::new(1.0, 0.0, 0.0),
+                b: Vec3::new(0.0, 0.0, 1.0),
+                uv: Vec2::new(0.0, 0.0),
+            };
+
+            // fraction of samples with non-zero contribution
+            let n_samples = 100000;
+            let mut n_valid = 0;
+            for _ in 0..n_samples {
+                let sample = glass.sample_direction(&info, &mut sampler);
+                if sample.pdf == 0.0 {
+                    continue;
+                }
+                n_valid += 1;
+                let is_transmission = wo.y() * sample.wi.y() < 0.0;
+                assert_eq!(
+                    sample.sampled_type.contains(BxDFType::TRANSMISSION),
+                    is_transmission
+                );
+                assert_eq!(sample.f, glass.eval(wo, sample.wi));
+            }
+
+            // pdf integrates to the fraction of valid samples
+            let mut integral = 0.0;
+            for _ in 0..n_samples {
+                let (wi, pdf) = uniform_sphere(sampler.next_2d());
+                integral += glass.pdf(wo, wi) / pdf;
+            }
+            integral /= n_samples as Real;
+            let valid = n_valid as Real / n_samples as Real;
+            assert!((integral - valid).abs() < 3E-2);
+        }
+    }
+
+    #[test]
+    fn microfacet_dielectric_tint() {
+        // only transmitted light is tinted
+        let tint = Vec3::new(1.0, 0.5, 0.25);
+        let clear = MicrofacetDielectric::new(1.5, 0.3, 0.3);
+        let tinted = MicrofacetDielectric::new_with_tint(1.5, 0.3, 0.3, tint);
+        let wo = Vec3::new(0.3, 1.0, 0.2).normalize();
+        let reflected = Vec3::new(-0.2, 1.0, -0.3).normalize();
+        let transmitted = Vec3::new(-0.2, -1.0, -0.1).normalize();
+        assert_eq!(tinted.eval(wo, reflected), clear.eval(wo, reflected));
+        assert!(clear.eval(wo, transmitted).max() > 0.0);
+        assert_eq!(
+            tinted.eval(wo, transmitted),
+            tint * clear.eval(wo, transmitted)
+        );
+    }
+
+    #[test]
+    fn microfacet_dielectric_near_critical_angle() {
+        // refract may report total internal reflection which fresnel_dielectric doesn't
+        let mut sampler = Sampler::new(0);
+        for i in 0..200 {
+            let ior = 1.01 + 1.99 * i as Real / 200.0;
+            let glass = MicrofacetDielectric::new(ior, 1E-6, 1E-6);
+            let cos_critical = (1.0 - 1.0 / (ior * ior)).sqrt();
+            for ulps in -8..=8 {
+                let cos = Real::from_bits((cos_critical.to_bits() as i32 + ulps) as u32);
+                let info = ShadingInfo {
+                    x: Vec3::new(0.0, 0.0, 0.0),
+                    n: Vec3::new(0.0, 1.0, 0.0),
+                    wo: Vec3::new((1.0 - cos * cos).sqrt(), -cos, 0.0),
+                    t: Vec3::new(1.0, 0.0, 0.0),
+                    b: Vec3::new(0.0, 0.0, 1.0),
+                    uv: Vec2::new(0.0, 0.0),
+                };
+                for _ in 0..100 {
+                    let sample = glass.sample_direction(&info, &mut sampler);
+                    assert!(sample.f.max().is_finite() && sample.pdf.is_finite());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn coated_diffuse_sample() {
+        let bxdf = CoatedDiffuse::new(
+            Vec3::new(0.5, 0.4, 0.3),
+            Vec3::new(1.0, 1.0, 1.0),
+            1.5,
+            0.2,
+            0.2,
+        );
+        let info = ShadingInfo {
+            x: Vec3::new(0.0, 0.0, 0.0),
+            n: Vec3::new(0.0, 1.0, 0.0),
+            wo: Vec3::new(0.3, 1.0, 0.2).normalize(),
+            t: Vec3::new(1.0, 0.0, 0.0),
+            b: Vec3::new(0.0, 0.0, 1.0),
+            uv: Vec2::new(0.0, 0.0),
+        };
+
+        let mut sampler = Sampler::new(0);
+        for _ in 0..100 {
+            let sample = bxdf.sample_direction(&info, &mut sampler);
+            if sample.pdf == 0.0 {
+                continue;
+            }
+            assert!(sample.wi.y() > 0.0);
+            assert_eq!(sample.f, bxdf.eval(info.wo, sample.wi));
+            assert_eq!(sample.pdf, bxdf.pdf(info.wo, sample.wi));
+        }
+
+        // bright plastic doesn't reflect more than it receives even at grazing angles
+        let bxdf = CoatedDiffuse::new(
+            Vec3::new(0.8, 0.8, 0.8),
+            Vec3::new(1.0, 1.0, 1.0),
+            1.5,
+            0.2,
+            0.2,
+        );
+        for cos in [1.0 as Real, 0.5, 0.1, 0.02] {
+            let info = ShadingInfo {
+                wo: Vec3::new((1.0 - cos * cos).sqrt(), cos, 0.0),
+                ..info
+            };
+            let n_samples = 100000;
+            let mut albedo = 0.0;
+            for _ in 0..n_samples {
+                let sample = bxdf.sample_direction(&info, &mut sampler);
+                if sample.pdf > 0.0 {
+                    albedo += sample.f.x() * abs_cos_theta(sample.wi) / sample.pdf;
+                }
+            }
+            albedo /= n_samples as Real;
+            assert!(albedo < 1.0);
+        }
+    }
+
+    #[test]
+    fn disney_lobes_sample() {
+        let lobes: Vec<Box<dyn BxDF>> = vec![
+            Box::new(DisneyDiffuse::new(
+                Vec3::new(0.8, 0.5, 0.2),
+                0.5,
+                0.5,
+                Vec3::new(0.1, 0.1, 0.1),
+            )),
+            Box::new(DisneySpecular::new(Vec3::new(0.9, 0.6, 0.3), 0.3, 0.2)),
+            Box::new(DisneyClearcoat::new(0.2)),
+        ];
+        let info = ShadingInfo {
+            x: Vec3::new(0.0, 0.0, 0.0),
+            n: Vec3::new(0.0, 1.0, 0.0),
+            wo: Vec3::new(0.3, -1.0, 0.2).normalize(),
+            t: Vec3::new(1.0, 0.0, 0.0),
+            b: Vec3::new(0.0, 0.0, 1.0),
+            uv: Vec2::new(0.0, 0.0),
+        };
+
+        let mut sampler = Sampler::new(0);
+        for lobe in &lobes {
+            let n_samples = 100000;
+            let mut n_valid = 0;
+            for _ in 0..n_samples {
+                let sample = lobe.sample_direction(&info, &mut sampler);
+                if sample.pdf == 0.0 {
+                    continue;
+                }
+                n_valid += 1;
+                assert!(sample.wi.y() < 0.0);
+                assert_eq!(sample.f, lobe.eval(info.wo, sample.wi));
+            }
+
+            // pdf integrates to the fraction of valid samples
+            let mut integral = 0.0;
+            for _ in 0..n_samples {
+                let (wi, pdf) = uniform_sphere(sampler.next_2d());
+                integral += lobe.pdf(info.wo, wi) / pdf;
+            }
+            integral /= n_samples as Real;
+            let valid = n_valid as Real / n_samples as Real;
+            assert!((integral - valid).abs() < 3E-2);
+        }
+    }
+
+    #[test]
+    fn oren_nayar_lambert_limit() {
+        let rho = Vec3::new(0.5, 0.5, 0.5);
+        let lambert = Lambert::new(rho);
+        let oren_nayar = OrenNayar::new(rho, 0.0);
+        let wo = Vec3::new(0.3, 1.0, 0.2).normalize();
+        let mut sampler = Sampler::new(0);
+        for _ in 0..100 {
+            let (wi, _) = uniform_sphere(sampler.next_2d());
+            assert!((oren_nayar.eval(wo, wi) - lambert.eval(wo, wi)).length() < 1E-6);
+        }
+    }
+
+    #[test]
+    fn energy_preserving_oren_nayar_furnace() {
+        let mut sampler = Sampler::new(0);
+        for roughness in [0.0, 0.5, 1.0] {
+            let bxdf = EnergyPreservingOrenNayar::new(Vec3::new(1.0, 1.0, 1.0), roughness);
+            for wo in [
+                Vec3::new(0.0, 1.0, 0.0),
+                Vec3::new(1.0, 1.0, 0.0).normalize(),
+                Vec3::new(1.0, -0.1, 0.3).normalize(),
+            ] {
+                let info = ShadingInfo {
+                    x: Vec3::new(0.0, 0.0, 0.0),
+                    n: Vec3::new(0.0, 1.0, 0.0),
+                    wo,
+                    t: Vec3::new(1.0, 0.0, 0.0),
+                    b: Vec3::new(0.0, 0.0, 1.0),
+                    uv: Vec2::new(0.0, 0.0),
+                };
+
+                // white surface reflects all energy
+                let n_samples = 100000;
+                let mut albedo = 0.0;
+                for _ in 0..n_samples {
+                    let sample = bxdf.sample_direction(&info, &mut sampler);
+                    assert_eq!(sample.f, bxdf.eval(wo, sample.wi));
+                    albedo += sample.f.x() * abs_cos_theta(sample.wi) / sample.pdf;
+                }
+                albedo /= n_samples as Real;
+                assert!((albedo - 1.0).abs() < 1E-2);
+            }
         }
     }
 }
@@ -18,7 +18,17 @@ pub struct PinholeCamera {
 
 impl PinholeCamera {
     pub fn new(position: Vec3, forward: Vec3, fov: Real) -> Self {
-        let right = forward.cross(Vec3::new(0.0, 1.0, 0.0)).normalize();
+        Self::new_with_up(position, forward, Vec3::new(0.0, 1.0, 0.0), fov)
+    }
+
+    // up is projected onto plane perpendicular to forward, -z is used when they are parallel
+    pub fn new_with_up(position: Vec3, forward: Vec3, up: Vec3, fov: Real) -> Self {
+        let right = forward.cross(up);
+        let right = if right.length() > 1E-6 * up.length() {
+            right.normalize()
+        } else {
+            forward.cross(Vec3::new(0.0, 0.0, -1.0)).normalize()
+        };
         let up = right.cross(forward).normalize();
         let f = 1.0 / (0.5 * fov).tan();
         PinholeCamera {
@@ -58,6 +68,25 @@ mod tests {
         assert_eq!(camera.up, Vec3::new(0.0, 1.0, 0.0));
     }
 
+    #[test]
+    fn camera_up() {
+        // looking down with up along -z keeps image upright
+        let down = Vec3::new(0.0, -1.0, 0.0);
+        let camera = PinholeCamera::new(Vec3::new(0.0, 0.0, 0.0), down, FRAC_PI_2);
+        assert_eq!(camera.right, Vec3::new(1.0, 0.0, 0.0));
+        assert_eq!(camera.up, Vec3::new(0.0, 0.0, -1.0));
+
+        // rolled camera
+        let camera = PinholeCamera::new_with_up(
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, -1.0),
+            Vec3::new(1.0, 1.0, 0.0),
+            FRAC_PI_2,
+        );
+        assert!((camera.up - Vec3::new(1.0, 1.0, 0.0).normalize()).length() < 1E-6);
+        assert!((camera.right - Vec3::new(1.0, -1.0, 0.0).normalize()).length() < 1E-6);
+    }
+
     #[test]
     fn sample_ray() {
         let camera = PinholeCamera::new(
@@ -0,0 +1,76 @@
+use crate::types::Real;
+use crate::vec3::Vec3;
+
+// second radiation constant hc/k in micrometer kelvin
+const C2: Real = 14387.77;
+
+// linear sRGB of chromaticity x, y with given luminance, out of gamut colors are clamped
+pub fn xyy_to_rgb(x: Real, y: Real, luminance: Real) -> Vec3 {
+    if y <= 0.0 {
+        return Vec3::new(0.0, 0.0, 0.0);
+    }
+    let cx = x * luminance / y;
+    let cz = (1.0 - x - y) * luminance / y;
+    xyz_to_rgb(Vec3::new(cx, luminance, cz))
+}
+
+pub fn xyz_to_rgb(xyz: Vec3) -> Vec3 {
+    let (cx, cy, cz) = (xyz.x(), xyz.y(), xyz.z());
+    Vec3::new(
+        3.2406 * cx - 1.5372 * cy - 0.4986 * cz,
+        -0.9689 * cx + 1.8758 * cy + 0.0415 * cz,
+        0.0557 * cx - 0.2040 * cy + 1.0570 * cz,
+    )
+    .max3(Vec3::new(0.0, 0.0, 0.0))
+}
+
+// linear sRGB of blackbody at temperature in kelvin, normalized to luminance 1
+pub fn blackbody(temperature: Real) -> Vec3 {
+    // temperatures below about 500K overflow Planck's law and are invisible anyway
+    let temperature = temperature.max(500.0);
+
+    // integrate Planck's law against color matching functions over visible range
+    let mut xyz = Vec3::new(0.0, 0.0, 0.0);
+    for i in 0..=80 {
+        let lambda = 380.0 + 5.0 * i as Real;
+        let lambda_um = 1E-3 * lambda;
+        let radiance = 1.0 / (lambda_um.powi(5) * ((C2 / (lambda_um * temperature)).exp() - 1.0));
+        xyz += radiance * color_matching(lambda);
+    }
+    xyz_to_rgb(xyz / xyz.y())
+}
+
+// CIE 1931 color matching functions at wavelength in nanometers, multi-lobe fit of
+// Wyman et al. 2013
+fn color_matching(lambda: Real) -> Vec3 {
+    let g = |mu: Real, sigma1: Real, sigma2: Real| {
+        let t = (lambda - mu) / if lambda < mu { sigma1 } else { sigma2 };
+        (-0.5 * t * t).exp()
+    };
+    Vec3::new(
+        1.056 * g(599.8, 37.9, 31.0) + 0.362 * g(442.0, 16.0, 26.7) - 0.065 * g(501.1, 20.4, 26.2),
+        0.821 * g(568.8, 46.9, 40.5) + 0.286 * g(530.9, 16.3, 31.1),
+        1.217 * g(437.0, 11.8, 36.0) + 0.681 * g(459.0, 26.0, 13.8),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::color::*;
+
+    #[test]
+    fn blackbody_color() {
+        let luminance = |c: Vec3| 0.2126 * c.x() + 0.7152 * c.y() + 0.0722 * c.z();
+
+        // candle is orange, daylight is nearly white and hot star is blue
+        let candle = blackbody(1900.0);
+        assert!(candle.x() > candle.y() && candle.y() > candle.z());
+        let daylight = blackbody(6500.0);
+        assert!(daylight.max() / daylight.min() < 1.2);
+        assert!((luminance(daylight) - 1.0).abs() < 1E-2);
+        let star = blackbody(12000.0);
+        assert!(star.z() > star.x());
+
+        assert!(blackbody(100.0).max().is_finite());
+    }
+}
@@ -1,4 +1,7 @@
+use crate::bounds::Bounds3;
+use crate::sampler::Sampler;
 use crate::types::Real;
+use crate::vec2::Vec2;
 use crate::vec3::Vec3;
 
 #[derive(Debug, PartialEq, Clone)]
@@ -26,17 +29,25 @@ impl Ray {
 
 #[derive(Debug, PartialEq)]
 pub struct IntersectInfoLocal {
-    pub t: Real,      // distance to hit point
-    pub pos: Vec3,    // hit position
-    pub normal: Vec3, // hit normal
+    pub t: Real,              // distance to hit point
+    pub pos: Vec3,            // hit position
+    pub normal: Vec3,         // geometric normal
+    pub shading_normal: Vec3, // interpolated shading normal
+    pub uv: Vec2,             // surface parameterization
+    pub dpdu: Vec3,           // derivative of position along u
+    pub dpdv: Vec3,           // derivative of position along v
 }
 
 #[derive(Debug, PartialEq)]
 pub struct IntersectInfoGlobal {
-    pub t: Real,       // distance to hit point
-    pub pos: Vec3,     // hit position
-    pub normal: Vec3,  // hit normal
-    pub prim_idx: u32, // index of hit primitive
+    pub t: Real,              // distance to hit point
+    pub pos: Vec3,            // hit position
+    pub normal: Vec3,         // geometric normal
+    pub shading_normal: Vec3, // interpolated shading normal
+    pub uv: Vec2,             // surface parameterization
+    pub dpdu: Vec3,           // derivative of position along u
+    pub dpdv: Vec3,           // derivative of position along v
+    pub prim_idx: u32,        // index of hit primitive
 }
 
 #[derive(Debug, PartialEq)]
@@ -46,11 +57,56 @@ pub struct ShadingInfo {
     pub wo: Vec3, // outgoing direction in tangent space
     pub t: Vec3,  // tangent vector
     pub b: Vec3,  // bitangent vector
+    pub uv: Vec2, // surface parameterization
+}
+
+#[derive(Debug, PartialEq)]
+pub struct SurfaceSample {
+    pub pos: Vec3,    // sampled position
+    pub normal: Vec3, // surface normal at sampled position
+    pub uv: Vec2,     // surface coordinates at sampled position
+    pub pdf: Real,    // pdf in area or solid angle measure
 }
 
 // NOTE: local means it doesn't contain hit primitive index
 pub trait IntersectableLocal {
     fn intersect(&self, ray: &Ray) -> Option<IntersectInfoLocal>;
+    fn bounds(&self) -> Bounds3;
+
+    fn area(&self) -> Real;
+
+    // cone bounding geometric normals over surface as its axis and cosine of spread angle,
+    // default covers every direction
+    fn normal_bounds(&self) -> (Vec3, Real) {
+        (Vec3::new(0.0, 1.0, 0.0), -1.0)
+    }
+
+    // sample point on surface, pdf is in area measure
+    fn sample_area(&self, sampler: &mut Sampler) -> SurfaceSample;
+
+    // sample point on surface seen from x, pdf is in solid angle measure
+    fn sample_solid_angle(&self, x: Vec3, sampler: &mut Sampler) -> SurfaceSample {
+        area_to_solid_angle(x, self.sample_area(sampler))
+    }
+
+    // pdf of sample_solid_angle choosing pos with normal seen from x
+    fn pdf_solid_angle(&self, x: Vec3, pos: Vec3, normal: Vec3) -> Real {
+        area_to_solid_angle_pdf(x, pos, normal, 1.0 / self.area())
+    }
+}
+
+// convert pdf of surface sample from area measure to solid angle measure seen from x
+pub fn area_to_solid_angle(x: Vec3, sample: SurfaceSample) -> SurfaceSample {
+    SurfaceSample {
+        pdf: area_to_solid_angle_pdf(x, sample.pos, sample.normal, sample.pdf),
+        ..sample
+    }
+}
+
+pub fn area_to_solid_angle_pdf(x: Vec3, pos: Vec3, normal: Vec3, pdf_area: Real) -> Real {
+    let wi = pos - x;
+    let cos = wi.normalize().dot(normal).abs();
+    pdf_area * wi.length2() / cos
 }
 
 // NOTE: global means it contains hit primitive index
@@ -0,0 +1,162 @@
+use crate::types::Real;
+use crate::vec2::Vec2;
+
+// piecewise-constant distribution over [0, 1] proportional to func
+pub struct Distribution1D {
+    func: Vec<Real>,
+    cdf: Vec<Real>,
+    integral: Real,
+}
+
+impl Distribution1D {
+    pub fn new(func: Vec<Real>) -> Self {
+        let n = func.len();
+        let mut cdf = vec![0.0; n + 1];
+        for i in 0..n {
+            cdf[i + 1] = cdf[i] + func[i].abs() / n as Real;
+        }
+        let integral = cdf[n];
+
+        // zero function falls back to uniform distribution
+        if integral == 0.0 {
+            for (i, c) in cdf.iter_mut().enumerate() {
+                *c = i as Real / n as Real;
+            }
+        } else {
+            for c in cdf.iter_mut() {
+                *c /= integral;
+            }
+        }
+        Distribution1D {
+            func,
+            cdf,
+            integral,
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.func.len()
+    }
+
+    // integral of func over [0, 1]
+    pub fn integral(&self) -> Real {
+        self.integral
+    }
+
+    // returns sampled point in [0, 1), its pdf and index of its segment
+    pub fn sample(&self, u: Real) -> (Real, Real, usize) {
+        // last segment whose cdf is not greater than u
+        let offset = self
+            .cdf
+            .partition_point(|&c| c <= u)
+            .saturating_sub(1)
+            .min(self.count() - 1);
+
+        let width = self.cdf[offset + 1] - self.cdf[offset];
+        let du = if width > 0.0 {
+            (u - self.cdf[offset]) / width
+        } else {
+            0.0
+        };
+        let x = ((offset as Real + du) / self.count() as Real).min(1.0 - Real::EPSILON);
+        (x, self.pdf(x), offset)
+    }
+
+    pub fn pdf(&self, x: Real) -> Real {
+        let offset = ((x * self.count() as Real) as usize).min(self.count() - 1);
+        if self.integral == 0.0 {
+            1.0
+        } else {
+            self.func[offset].abs() / self.integral
+        }
+    }
+
+    // returns index chosen in proportion to func and its probability
+    pub fn sample_discrete(&self, u: Real) -> (usize, Real) {
+        let (_, _, offset) = self.sample(u);
+        (offset, self.discrete_pmf(offset))
+    }
+
+    pub fn discrete_pmf(&self, offset: usize) -> Real {
+        self.pdf((offset as Real + 0.5) / self.count() as Real) / self.count() as Real
+    }
+}
+
+// piecewise-constant distribution over [0, 1]^2 given by func[v][u]
+pub struct Distribution2D {
+    conditional: Vec<Distribution1D>, // distribution of u for each row
+    marginal: Distribution1D,         // distribution of v
+}
+
+impl Distribution2D {
+    pub fn new(func: Vec<Vec<Real>>) -> Self {
+        let conditional: Vec<Distribution1D> = func.into_iter().map(Distribution1D::new).collect();
+        let marginal = Distribution1D::new(conditional.iter().map(|d| d.integral()).collect());
+        Distribution2D {
+            conditional,
+            marginal,
+        }
+    }
+
+    // returns sampled point in [0, 1)^2 and its pdf
+    pub fn sample(&self, uv: Vec2) -> (Vec2, Real) {
+        let (v, pdf_v, row) = self.marginal.sample(uv.y());
+        let (u, pdf_u, _) = self.conditional[row].sample(uv.x());
+        (Vec2::new(u, v), pdf_u * pdf_v)
+    }
+
+    pub fn pdf(&self, uv: Vec2) -> Real {
+        let n_rows = self.conditional.len();
+        let row = ((uv.y() * n_rows as Real).max(0.0) as usize).min(n_rows - 1);
+        self.marginal.pdf(uv.y()) * self.conditional[row].pdf(uv.x())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::distribution::*;
+
+    #[test]
+    fn distribution_1d() {
+        let distribution = Distribution1D::new(vec![1.0, 3.0, 0.0, 4.0]);
+        assert_eq!(distribution.integral(), 2.0);
+        assert_eq!(distribution.pdf(0.1), 0.5);
+        assert_eq!(distribution.pdf(0.6), 0.0);
+
+        // second segment covers cdf in [1/8, 1/2)
+        let (x, pdf, offset) = distribution.sample(0.3125);
+        assert_eq!(offset, 1);
+        assert!((x - 0.375).abs() < 1e-6);
+        assert_eq!(pdf, 1.5);
+        assert_eq!(distribution.sample_discrete(0.3125), (1, 0.375));
+        assert_eq!(distribution.discrete_pmf(3), 0.5);
+
+        // empty segment is never sampled
+        for i in 0..100 {
+            let (x, pdf, offset) = distribution.sample(i as Real / 100.0);
+            assert_ne!(offset, 2);
+            assert!(pdf > 0.0 && (0.0..1.0).contains(&x));
+        }
+    }
+
+    #[test]
+    fn distribution_2d() {
+        let distribution = Distribution2D::new(vec![vec![1.0, 0.0], vec![2.0, 5.0]]);
+
+        // pdf integrates to 1 over unit square
+        let n = 100;
+        let mut integral = 0.0;
+        for i in 0..n {
+            for j in 0..n {
+                let uv = Vec2::new((j as Real + 0.5) / n as Real, (i as Real + 0.5) / n as Real);
+                integral += distribution.pdf(uv) / (n * n) as Real;
+            }
+        }
+        assert!((integral - 1.0).abs() < 1e-4);
+
+        let (uv, pdf) = distribution.sample(Vec2::new(0.9, 0.9));
+        assert!(uv.x() >= 0.5 && uv.y() >= 0.5);
+        assert!((pdf - distribution.pdf(uv)).abs() < 1e-4);
+        assert!((pdf - 2.5).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,204 @@
+use crate::bounds::Bounds3;
+use crate::core::{IntersectInfoGlobal, IntersectableGlobal, IntersectableLocal, Ray};
+use crate::intersector::Intersector;
+use crate::types::Real;
+use crate::vec3::Vec3;
+
+use std::sync::Arc;
+
+const VOXELS_PER_PRIMITIVE: Real = 3.0; // target number of voxels along the longest axis is 3 * cbrt(N)
+const MAX_RESOLUTION: usize = 64; // maximum number of voxels along each axis
+
+pub struct UniformGrid {
+    primitives: Arc<Vec<Box<dyn IntersectableLocal + Send + Sync>>>,
+    bounds: Bounds3,        // bounds of the whole grid
+    resolution: [usize; 3], // number of voxels along each axis
+    voxel_size: Vec3,       // size of a voxel
+    voxels: Vec<Vec<u32>>,  // indices of primitives overlapping each voxel
+}
+
+impl UniformGrid {
+    pub fn new(primitives: Arc<Vec<Box<dyn IntersectableLocal + Send + Sync>>>) -> Self {
+        let prim_bounds: Vec<Bounds3> = primitives.iter().map(|p| p.bounds()).collect();
+        let bounds = prim_bounds
+            .iter()
+            .fold(Bounds3::empty(), |b, prim_bounds| b.union(prim_bounds));
+
+        // choose resolution so that voxels are roughly cubic
+        let mut resolution = [1usize; 3];
+        if !primitives.is_empty() {
+            let diagonal = bounds.diagonal();
+            let max_width = diagonal[bounds.max_extent()];
+            let voxels_per_unit =
+                VOXELS_PER_PRIMITIVE * (primitives.len() as Real).cbrt() / max_width;
+            for (axis, res) in resolution.iter_mut().enumerate() {
+                *res =
+                    ((diagonal[axis] * voxels_per_unit).round() as usize).clamp(1, MAX_RESOLUTION);
+            }
+        }
+        let diagonal = bounds.diagonal();
+        let voxel_size = Vec3::new(
+            diagonal.x() / resolution[0] as Real,
+            diagonal.y() / resolution[1] as Real,
+            diagonal.z() / resolution[2] as Real,
+        );
+
+        let mut grid = UniformGrid {
+            primitives,
+            bounds,
+            resolution,
+            voxel_size,
+            voxels: vec![Vec::new(); resolution[0] * resolution[1] * resolution[2]],
+        };
+
+        // register primitives to every voxel overlapping its bounds
+        for (prim_idx, prim_bounds) in prim_bounds.iter().enumerate() {
+            let v_min = grid.pos_to_voxel(prim_bounds.p_min);
+            let v_max = grid.pos_to_voxel(prim_bounds.p_max);
+            for z in v_min[2]..=v_max[2] {
+                for y in v_min[1]..=v_max[1] {
+                    for x in v_min[0]..=v_max[0] {
+                        let voxel_idx = grid.voxel_index([x, y, z]);
+                        grid.voxels[voxel_idx].push(prim_idx as u32);
+                    }
+                }
+            }
+        }
+
+        grid
+    }
+
+    pub fn get_resolution(&self) -> [usize; 3] {
+        self.resolution
+    }
+
+    fn pos_to_voxel(&self, p: Vec3) -> [usize; 3] {
+        let mut voxel = [0usize; 3];
+        let o = self.bounds.offset(p);
+        for (axis, v) in voxel.iter_mut().enumerate() {
+            let res = self.resolution[axis];
+            *v = ((o[axis] * res as Real).max(0.0) as usize).min(res - 1);
+        }
+        voxel
+    }
+
+    fn voxel_to_pos(&self, v: usize, axis: usize) -> Real {
+        self.bounds.p_min[axis] + v as Real * self.voxel_size[axis]
+    }
+
+    fn voxel_index(&self, voxel: [usize; 3]) -> usize {
+        voxel[0] + self.resolution[0] * (voxel[1] + self.resolution[1] * voxel[2])
+    }
+}
+
+impl IntersectableGlobal for UniformGrid {
+    fn intersect(&self, ray: &Ray) -> Option<IntersectInfoGlobal> {
+        if self.primitives.is_empty() {
+            return None;
+        }
+
+        let mut ray = ray.clone();
+        let inv_dir = 1.0 / ray.direction;
+        let (t0, _) = self.bounds.intersect(&ray, inv_dir)?;
+
+        // setup 3D-DDA
+        let entry = ray.position(t0);
+        let mut voxel = [0i64; 3];
+        let mut next_crossing = [0.0 as Real; 3];
+        let mut delta = [0.0 as Real; 3];
+        let mut step = [0i64; 3];
+        let mut out = [0i64; 3];
+        let entry_voxel = self.pos_to_voxel(entry);
+        for axis in 0..3 {
+            voxel[axis] = entry_voxel[axis] as i64;
+            let d = ray.direction[axis];
+            if d > 0.0 {
+                let next = self.voxel_to_pos(entry_voxel[axis] + 1, axis);
+                next_crossing[axis] = t0 + (next - entry[axis]) * inv_dir[axis];
+                delta[axis] = self.voxel_size[axis] * inv_dir[axis];
+                step[axis] = 1;
+                out[axis] = self.resolution[axis] as i64;
+            } else if d < 0.0 {
+                let next = self.voxel_to_pos(entry_voxel[axis], axis);
+                next_crossing[axis] = t0 + (next - entry[axis]) * inv_dir[axis];
+                delta[axis] = -self.voxel_size[axis] * inv_dir[axis];
+                step[axis] = -1;
+                out[axis] = -1;
+            } else {
+                next_crossing[axis] = Real::INFINITY;
+                delta[axis] = Real::INFINITY;
+                step[axis] = 0;
+                out[axis] = -1;
+            }
+        }
+
+        let mut info: Option<IntersectInfoGlobal> = None;
+        loop {
+            let voxel_idx =
+                self.voxel_index([voxel[0] as usize, voxel[1] as usize, voxel[2] as usize]);
+            for &prim_idx in &self.voxels[voxel_idx] {
+                if let Some(surf_info) = self.primitives[prim_idx as usize].intersect(&ray) {
+                    ray.tmax = surf_info.t;
+                    info = Some(IntersectInfoGlobal {
+                        t: surf_info.t,
+                        pos: surf_info.pos,
+                        normal: surf_info.normal,
+                        shading_normal: surf_info.shading_normal,
+                        uv: surf_info.uv,
+                        dpdu: surf_info.dpdu,
+                        dpdv: surf_info.dpdv,
+                        prim_idx,
+                    });
+                }
+            }
+
+            // advance to the neighbor voxel which the ray enters first
+            let axis = if next_crossing[0] < next_crossing[1] {
+                if next_crossing[0] < next_crossing[2] {
+                    0
+                } else {
+                    2
+                }
+            } else if next_crossing[1] < next_crossing[2] {
+                1
+            } else {
+                2
+            };
+            // hits inside the current voxel can not be occluded by later voxels
+            if ray.tmax < next_crossing[axis] {
+                break;
+            }
+            voxel[axis] += step[axis];
+            if voxel[axis] == out[axis] {
+                break;
+            }
+            next_crossing[axis] += delta[axis];
+        }
+        info
+    }
+}
+
+impl Intersector for UniformGrid {}
+
+#[cfg(test)]
+mod tests {
+    use crate::grid::*;
+    use crate::shape::Sphere;
+
+    #[test]
+    fn grid_resolution() {
+        let primitives: Vec<Box<dyn IntersectableLocal + Send + Sync>> = vec![
+            Box::new(Sphere::new(Vec3::new(0.0, 0.0, 0.0), 1.0)),
+            Box::new(Sphere::new(Vec3::new(8.0, 0.0, 0.0), 1.0)),
+        ];
+        let grid = UniformGrid::new(Arc::new(primitives));
+        let resolution = grid.get_resolution();
+        assert!(resolution[0] > resolution[1]);
+        assert_eq!(resolution[1], resolution[2]);
+
+        let ray = Ray::new(Vec3::new(-5.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let info = grid.intersect(&ray).unwrap();
+        assert_eq!(info.prim_idx, 0);
+        assert_eq!(info.t, 4.0);
+    }
+}
@@ -1,7 +1,27 @@
 use std::fs;
+use std::path::Path;
 
+use ::image::{ColorType, ImageError};
+
+use crate::loader::LoadError;
+use crate::types::Real;
 use crate::vec3::Vec3;
 
+// how 8-bit and 16-bit texel values map to linear values
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ColorEncoding {
+    SRGB,
+    Linear,
+}
+
+pub fn srgb_to_linear(v: Real) -> Real {
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
 pub struct Image {
     width: usize,
     height: usize,
@@ -17,6 +37,38 @@ impl Image {
         }
     }
 
+    // read PNG or JPEG decoded with encoding, or Radiance HDR or OpenEXR which are linear.
+    // format is determined by extension
+    pub fn load(path: &Path, encoding: ColorEncoding) -> Result<Self, LoadError> {
+        let decoded = ::image::open(path).map_err(|why| match why {
+            ImageError::IoError(source) => LoadError::Io {
+                path: path.to_path_buf(),
+                source,
+            },
+            source => LoadError::Image {
+                path: path.to_path_buf(),
+                source,
+            },
+        })?;
+
+        let is_float = matches!(decoded.color(), ColorType::Rgb32F | ColorType::Rgba32F);
+        let mut image = Image {
+            width: decoded.width() as usize,
+            height: decoded.height() as usize,
+            pixels: decoded.to_rgb32f().into_raw(),
+        };
+        if !is_float && encoding == ColorEncoding::SRGB {
+            image.srgb_to_linear();
+        }
+        Ok(image)
+    }
+
+    pub fn srgb_to_linear(&mut self) {
+        for v in self.pixels.iter_mut() {
+            *v = srgb_to_linear(*v);
+        }
+    }
+
     pub fn get_width(&self) -> usize {
         self.width
     }
@@ -52,7 +104,7 @@ impl Image {
         }
     }
 
-    pub fn write_ppm(&self) {
+    pub fn write_ppm(&self, path: &Path) {
         let mut contents = String::new();
         contents.push_str("P3\n");
         contents.push_str(format!("{} {}\n", self.width, self.height).as_str());
@@ -68,9 +120,77 @@ impl Image {
             }
         }
 
-        match fs::write("output.ppm", contents) {
-            Err(why) => panic!("couldn't write output.ppm: {}", why),
-            Ok(_) => println!("wrote output.ppm"),
+        match fs::write(path, contents) {
+            Err(why) => panic!("couldn't write {}: {}", path.display(), why),
+            Ok(_) => println!("wrote {}", path.display()),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::image::*;
+    use crate::test_dir::TestDir;
+
+    #[test]
+    fn load_png() {
+        let dir = TestDir::new("load_png");
+        let path = dir.join("load.png");
+        ::image::RgbImage::from_raw(2, 1, vec![0, 128, 255, 255, 255, 255])
+            .unwrap()
+            .save(&path)
+            .unwrap();
+
+        let image = Image::load(&path, ColorEncoding::SRGB).unwrap();
+        assert_eq!((image.get_width(), image.get_height()), (2, 1));
+        let rgb = image.get_pixel(0, 0);
+        assert_eq!(rgb.x(), 0.0);
+        assert!((rgb.y() - 0.2158).abs() < 1e-3);
+        assert_eq!(rgb.z(), 1.0);
+
+        let image = Image::load(&path, ColorEncoding::Linear).unwrap();
+        assert!((image.get_pixel(0, 0).y() - 128.0 / 255.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn load_hdr() {
+        let rgb = vec![0.5, 2.0, 10.0, 0.0, 0.0, 0.0];
+        let dir = TestDir::new("load_hdr");
+        for name in ["load.hdr", "load.exr"] {
+            let path = dir.join(name);
+            ::image::Rgb32FImage::from_raw(1, 2, rgb.clone())
+                .unwrap()
+                .save(&path)
+                .unwrap();
+
+            // encoding is ignored by floating point formats
+            let image = Image::load(&path, ColorEncoding::SRGB).unwrap();
+            assert_eq!((image.get_width(), image.get_height()), (1, 2));
+            assert!((image.get_pixel(0, 0) - Vec3::new(0.5, 2.0, 10.0)).length() < 0.1);
+        }
+    }
+
+    #[test]
+    fn load_errors() {
+        let dir = TestDir::new("load_errors");
+        let path = dir.join("missing.png");
+        assert!(matches!(
+            Image::load(&path, ColorEncoding::SRGB),
+            Err(LoadError::Io { .. })
+        ));
+
+        let path = dir.join("broken.png");
+        std::fs::write(&path, "not a png").unwrap();
+        assert!(matches!(
+            Image::load(&path, ColorEncoding::SRGB),
+            Err(LoadError::Image { .. })
+        ));
+
+        let path = dir.join("unknown.xyz");
+        std::fs::write(&path, "").unwrap();
+        assert!(matches!(
+            Image::load(&path, ColorEncoding::SRGB),
+            Err(LoadError::Image { .. })
+        ));
+    }
+}
@@ -1,6 +1,8 @@
+use crate::bxdf::BxDFType;
 use crate::core::{IntersectableGlobal, Ray};
 use crate::sampler::Sampler;
 use crate::scene::Scene;
+use crate::types::Real;
 use crate::vec3::Vec3;
 
 pub trait Integrator {
@@ -16,6 +18,12 @@ impl NormalIntegrator {
     }
 }
 
+impl Default for NormalIntegrator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Integrator for NormalIntegrator {
     #[allow(unused_variables)]
     fn integrate(&self, scene: &Scene, sampler: &mut Sampler, ray: &Ray) -> Vec3 {
@@ -27,6 +35,16 @@ impl Integrator for NormalIntegrator {
     }
 }
 
+// MIS weight of strategy a against strategy b
+pub fn balance_heuristic(pdf_a: Real, pdf_b: Real) -> Real {
+    pdf_a / (pdf_a + pdf_b)
+}
+
+pub fn power_heuristic(pdf_a: Real, pdf_b: Real) -> Real {
+    let a2 = pdf_a * pdf_a;
+    a2 / (a2 + pdf_b * pdf_b)
+}
+
 pub struct PathTracingIntegrator {
     max_depth: u32,
 }
@@ -42,9 +60,30 @@ impl Integrator for PathTracingIntegrator {
         let mut radiance = Vec3::new(0.0, 0.0, 0.0);
         let mut ray = ray_in.clone();
         let mut throughput = Vec3::new(1.0, 1.0, 1.0);
+        // pdf of BxDF sampling at previous vertex, used for weighting emission
+        let mut bxdf_pdf: Real = 0.0;
+        // emission can't be sampled by lights after delta lobe
+        let mut specular_bounce = false;
 
-        for _depth in 0..self.max_depth {
+        // ray leaving the last vertex is still traced to collect emission weighted against
+        // light sampling at that vertex
+        for depth in 0..=self.max_depth {
             if let Some(info) = scene.intersect(&ray) {
+                // emission after first bounce is weighted against light sampling, path goes on
+                // since emissive material may reflect too
+                if scene.has_emission(info.prim_idx) {
+                    let le = scene.get_emission(-ray.direction, &info);
+                    if depth == 0 || specular_bounce {
+                        radiance += throughput * le;
+                    } else {
+                        let light_pdf = scene.pdf_light(ray.origin, ray.direction, info.prim_idx);
+                        radiance += throughput * power_heuristic(bxdf_pdf, light_pdf) * le;
+                    }
+                }
+                if depth == self.max_depth {
+                    break;
+                }
+
                 // russian roulette
                 let russian_roulette_prob = throughput.max().min(1.0);
                 if sampler.next_1d() >= russian_roulette_prob {
@@ -52,33 +91,150 @@ impl Integrator for PathTracingIntegrator {
                 }
                 throughput /= russian_roulette_prob;
 
-                // terminate if ray hits light
-                if scene.has_emission(info.prim_idx) {
-                    radiance += throughput * scene.get_emission(info.prim_idx);
-                    break;
-                }
-
-                // sample direction by BxDF
                 let shading_info = scene.get_shading_info(-ray.direction, &info);
+                let bsdf = scene.get_bsdf(info.prim_idx, &shading_info);
+                let wo = -ray.direction;
 
-                // sample direction
-                let bxdf = scene.get_bxdf(info.prim_idx);
-                let bxdf_sample = bxdf.sample_direction(&shading_info, sampler);
+                // light sampling, skipped for delta lobes since they can't be evaluated
+                let light_sample = if bsdf.bxdf_type().is_specular() {
+                    None
+                } else {
+                    scene.sample_light(info.pos, sampler)
+                };
+                if let Some(light_sample) = light_sample {
+                    let wi = light_sample.wi;
+                    let f = bsdf.eval(wo, wi);
+                    if f.max() > 0.0 && !scene.is_occluded(info.pos, wi, light_sample.distance) {
+                        // delta lights can't be hit by BSDF sampling
+                        let weight = if light_sample.delta {
+                            1.0
+                        } else {
+                            power_heuristic(light_sample.pdf, bsdf.pdf(wo, wi))
+                        };
+                        radiance += throughput
+                            * weight
+                            * f
+                            * wi.dot(shading_info.n).abs()
+                            * light_sample.le
+                            / light_sample.pdf;
+                    }
+                }
+
+                // sample direction by BSDF
+                let bsdf_sample = bsdf.sample_direction(wo, sampler);
+                if bsdf_sample.pdf <= 0.0 {
+                    break;
+                }
 
                 // update throughput
-                throughput *= bxdf_sample.f * bxdf_sample.wi.y().abs() / bxdf_sample.pdf;
+                throughput *=
+                    bsdf_sample.f * bsdf_sample.wi.dot(shading_info.n).abs() / bsdf_sample.pdf;
+                bxdf_pdf = bsdf_sample.pdf;
+                specular_bounce = bsdf_sample.sampled_type.contains(BxDFType::SPECULAR);
 
                 // update ray
                 ray.origin = info.pos;
-                ray.direction =
-                    bxdf_sample
-                        .wi
-                        .local_to_world(shading_info.t, shading_info.n, shading_info.b);
+                ray.direction = bsdf_sample.wi;
             } else {
-                radiance += throughput * Vec3::new(0.0, 0.0, 0.0);
+                // emission of infinite lights is weighted in the same way as
+                // emission of surfaces
+                for (le, light_pdf) in scene.infinite_light_emissions(ray.origin, ray.direction) {
+                    if depth == 0 || specular_bounce {
+                        radiance += throughput * le;
+                    } else {
+                        radiance += throughput * power_heuristic(bxdf_pdf, light_pdf) * le;
+                    }
+                }
                 break;
             }
         }
         radiance
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+    use std::sync::Arc;
+
+    use crate::image::Image;
+    use crate::integrator::*;
+    use crate::light::{EnvironmentLight, PointLight};
+    use crate::material::{Diffuse, Emissive};
+    use crate::shape::Plane;
+    use crate::texture::ConstantTexture;
+
+    // white diffuse floor under environment which is 1 above horizon and brighter below,
+    // so reflected radiance is 1 at any depth
+    fn furnace_scene() -> Scene {
+        let floor = Plane::new(
+            Vec3::new(-50.0, 0.0, -50.0),
+            Vec3::new(0.0, 0.0, 100.0),
+            Vec3::new(100.0, 0.0, 0.0),
+        );
+        let white = Arc::new(ConstantTexture::new(Vec3::new(1.0, 1.0, 1.0)));
+        let mut scene = Scene::new(vec![Box::new(floor)], vec![Arc::new(Diffuse::new(white))]);
+
+        let mut image = Image::new(4, 4);
+        for i in 0..4 {
+            let value = if i < 2 { 1.0 } else { 4.0 };
+            for j in 0..4 {
+                image.set_pixel(i, j, Vec3::new(value, value, value));
+            }
+        }
+        scene.add_light(Box::new(EnvironmentLight::new(Arc::new(image), 1.0, 0.0)));
+        scene
+    }
+
+    #[test]
+    fn emissive_surface_reflects() {
+        // floor lit by point light of intensity pi at height 1 reflects radiance 1
+        let floor = Plane::new(
+            Vec3::new(-50.0, 0.0, -50.0),
+            Vec3::new(0.0, 0.0, 100.0),
+            Vec3::new(100.0, 0.0, 0.0),
+        );
+        let white = Arc::new(ConstantTexture::new(Vec3::new(1.0, 1.0, 1.0)));
+        let glow = Arc::new(ConstantTexture::new(Vec3::new(0.001, 0.001, 0.001)));
+        let material = Emissive::new(Arc::new(Diffuse::new(white)), glow, true);
+        let mut scene = Scene::new(vec![Box::new(floor)], vec![Arc::new(material)]);
+        scene.add_light(Box::new(PointLight::new(
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(PI, PI, PI),
+        )));
+
+        let ray = Ray::new(
+            Vec3::new(0.0, 1.0, 1.0),
+            Vec3::new(0.0, -1.0, -1.0).normalize(),
+        );
+        let mut sampler = Sampler::new(0);
+        let integrator = PathTracingIntegrator::new(1);
+        let n_samples = 1000;
+        let mut sum = Vec3::new(0.0, 0.0, 0.0);
+        for _ in 0..n_samples {
+            sum += integrator.integrate(&scene, &mut sampler, &ray);
+        }
+        let radiance = sum / n_samples as Real;
+        assert!((radiance.y() - 1.001).abs() < 1E-2);
+    }
+
+    #[test]
+    fn path_tracing_furnace() {
+        let scene = furnace_scene();
+        let ray = Ray::new(
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(0.3, -1.0, 0.2).normalize(),
+        );
+        let mut sampler = Sampler::new(0);
+        for max_depth in [1, 2] {
+            let integrator = PathTracingIntegrator::new(max_depth);
+            let n_samples = 20000;
+            let mut sum = Vec3::new(0.0, 0.0, 0.0);
+            for _ in 0..n_samples {
+                sum += integrator.integrate(&scene, &mut sampler, &ray);
+            }
+            let radiance = sum / n_samples as Real;
+            assert!((radiance.y() - 1.0).abs() < 2E-2);
+        }
+    }
+}
@@ -1,18 +1,44 @@
+use crate::bvh::BVH;
 use crate::core::{IntersectInfoGlobal, IntersectableGlobal, IntersectableLocal, Ray};
+use crate::grid::UniformGrid;
+use crate::kdtree::KdTree;
 
 use std::sync::Arc;
 
-pub struct Intersector {
+// acceleration structure which finds the closest hit among primitives
+pub trait Intersector: IntersectableGlobal + Send + Sync {}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum IntersectorType {
+    Linear,
+    BVH,
+    Grid,
+    KdTree,
+}
+
+pub fn create_intersector(
+    intersector_type: IntersectorType,
+    primitives: Arc<Vec<Box<dyn IntersectableLocal + Send + Sync>>>,
+) -> Box<dyn Intersector> {
+    match intersector_type {
+        IntersectorType::Linear => Box::new(LinearIntersector::new(primitives)),
+        IntersectorType::BVH => Box::new(BVH::new(primitives)),
+        IntersectorType::Grid => Box::new(UniformGrid::new(primitives)),
+        IntersectorType::KdTree => Box::new(KdTree::new(primitives)),
+    }
+}
+
+pub struct LinearIntersector {
     intersectables: Arc<Vec<Box<dyn IntersectableLocal + Send + Sync>>>,
 }
 
-impl Intersector {
+impl LinearIntersector {
     pub fn new(intersectables: Arc<Vec<Box<dyn IntersectableLocal + Send + Sync>>>) -> Self {
-        Intersector { intersectables }
+        LinearIntersector { intersectables }
     }
 }
 
-impl IntersectableGlobal for Intersector {
+impl IntersectableGlobal for LinearIntersector {
     fn intersect(&self, ray: &Ray) -> Option<IntersectInfoGlobal> {
         let mut t = ray.tmax;
         let mut info: Option<IntersectInfoGlobal> = None;
@@ -24,6 +50,10 @@ impl IntersectableGlobal for Intersector {
                         t: surf_info.t,
                         pos: surf_info.pos,
                         normal: surf_info.normal,
+                        shading_normal: surf_info.shading_normal,
+                        uv: surf_info.uv,
+                        dpdu: surf_info.dpdu,
+                        dpdv: surf_info.dpdv,
                         prim_idx: idx as u32,
                     });
                 }
@@ -32,3 +62,177 @@ impl IntersectableGlobal for Intersector {
         info
     }
 }
+
+impl Intersector for LinearIntersector {}
+
+#[cfg(test)]
+mod tests {
+    use crate::intersector::*;
+    use crate::sampler::Sampler;
+    use crate::shape::{Plane, Sphere, TriangleMesh};
+    use crate::types::Real;
+    use crate::vec3::Vec3;
+
+    // random spheres and triangles inside box of Cornell-style walls, with flat meshes whose
+    // bounds have zero extent along an axis
+    fn random_primitives(sampler: &mut Sampler) -> Vec<Box<dyn IntersectableLocal + Send + Sync>> {
+        let mut primitives: Vec<Box<dyn IntersectableLocal + Send + Sync>> = Vec::new();
+        let mut random_point =
+            || Vec3::new(sampler.next_1d(), sampler.next_1d(), sampler.next_1d());
+        for _ in 0..100 {
+            let center = random_point();
+            let radius = 0.5 * random_point().x();
+            primitives.push(Box::new(Sphere::new(10.0 * center, radius)));
+        }
+
+        // small triangles of random orientation
+        let mut vertices = Vec::new();
+        for _ in 0..50 {
+            let p = 10.0 * random_point();
+            vertices.push(p);
+            vertices.push(p + random_point() - Vec3::new(0.5, 0.5, 0.5));
+            vertices.push(p + random_point() - Vec3::new(0.5, 0.5, 0.5));
+        }
+        let indices = (0..vertices.len() as u32).collect();
+        add_mesh(&mut primitives, vertices, indices);
+
+        // grid of quads in plane y = 5 and box whose faces are axis-aligned
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+        for i in 0..5 {
+            for j in 0..5 {
+                vertices.push(Vec3::new(2.0 + 1.5 * j as Real, 5.0, 2.0 + 1.5 * i as Real));
+            }
+        }
+        for i in 0..4 {
+            for j in 0..4 {
+                let v = 5 * i + j;
+                indices.extend_from_slice(&[v, v + 1, v + 6, v, v + 6, v + 5]);
+            }
+        }
+        let corner = |k: u32| {
+            Vec3::new(
+                if k & 1 == 0 { 6.0 } else { 8.0 },
+                if k & 2 == 0 { 1.0 } else { 3.0 },
+                if k & 4 == 0 { 6.0 } else { 8.0 },
+            )
+        };
+        let offset = vertices.len() as u32;
+        vertices.extend((0..8).map(corner));
+        for face in [
+            [0, 1, 3, 2],
+            [4, 6, 7, 5],
+            [0, 4, 5, 1],
+            [2, 3, 7, 6],
+            [0, 2, 6, 4],
+            [1, 5, 7, 3],
+        ] {
+            let [a, b, c, d] = face.map(|k| offset + k);
+            indices.extend_from_slice(&[a, b, c, a, c, d]);
+        }
+        add_mesh(&mut primitives, vertices, indices);
+
+        // floor, ceiling, side walls and back wall
+        for (corner, right, up) in [
+            ((0.0, 0.0, 0.0), (0.0, 0.0, 10.0), (10.0, 0.0, 0.0)),
+            ((0.0, 10.0, 0.0), (10.0, 0.0, 0.0), (0.0, 0.0, 10.0)),
+            ((0.0, 0.0, 0.0), (0.0, 10.0, 0.0), (0.0, 0.0, 10.0)),
+            ((10.0, 0.0, 0.0), (0.0, 0.0, 10.0), (0.0, 10.0, 0.0)),
+            ((0.0, 0.0, 10.0), (10.0, 0.0, 0.0), (0.0, 10.0, 0.0)),
+        ] {
+            let vec3 = |(x, y, z): (Real, Real, Real)| Vec3::new(x, y, z);
+            primitives.push(Box::new(Plane::new(vec3(corner), vec3(right), vec3(up))));
+        }
+        primitives
+    }
+
+    fn add_mesh(
+        primitives: &mut Vec<Box<dyn IntersectableLocal + Send + Sync>>,
+        vertices: Vec<Vec3>,
+        indices: Vec<u32>,
+    ) {
+        let mesh = Arc::new(TriangleMesh::new(vertices, indices, None, None));
+        for triangle in TriangleMesh::triangles(&mesh) {
+            primitives.push(Box::new(triangle));
+        }
+    }
+
+    fn assert_intersectors_match(
+        primitives: Vec<Box<dyn IntersectableLocal + Send + Sync>>,
+        mut ray: impl FnMut() -> Ray,
+    ) {
+        let primitives = Arc::new(primitives);
+        let linear = create_intersector(IntersectorType::Linear, primitives.clone());
+        for intersector_type in [
+            IntersectorType::BVH,
+            IntersectorType::Grid,
+            IntersectorType::KdTree,
+        ] {
+            let intersector = create_intersector(intersector_type, primitives.clone());
+            for _ in 0..1000 {
+                let ray = ray();
+                assert_eq!(
+                    intersector.intersect(&ray),
+                    linear.intersect(&ray),
+                    "{:?} {:?}",
+                    intersector_type,
+                    ray
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn intersectors_match_linear() {
+        let mut sampler = Sampler::new(0);
+        let primitives = random_primitives(&mut sampler);
+        assert_intersectors_match(primitives, || {
+            let origin = Vec3::new(5.0, 5.0, -10.0);
+            let target = 10.0 * Vec3::new(sampler.next_1d(), sampler.next_1d(), sampler.next_1d());
+            Ray::new(origin, (target - origin).normalize())
+        });
+    }
+
+    #[test]
+    fn intersectors_inside_scene() {
+        let mut sampler = Sampler::new(1);
+        let primitives = random_primitives(&mut sampler);
+        assert_intersectors_match(primitives, || {
+            let origin = 10.0 * Vec3::new(sampler.next_1d(), sampler.next_1d(), sampler.next_1d());
+            let direction = Vec3::new(
+                2.0 * sampler.next_1d() - 1.0,
+                2.0 * sampler.next_1d() - 1.0,
+                2.0 * sampler.next_1d() - 1.0,
+            );
+            Ray::new(origin, direction.normalize())
+        });
+    }
+
+    #[test]
+    fn intersectors_axis_parallel_rays() {
+        // rays parallel to slabs, some starting in planes of flat primitives
+        let mut sampler = Sampler::new(2);
+        let primitives = random_primitives(&mut sampler);
+        assert_intersectors_match(primitives, || {
+            let mut origin =
+                10.0 * Vec3::new(sampler.next_1d(), sampler.next_1d(), sampler.next_1d());
+            if sampler.next_1d() < 0.3 {
+                origin = Vec3::new(
+                    origin.x(),
+                    [0.0, 1.0, 5.0][(3.0 * sampler.next_1d()) as usize % 3],
+                    origin.z(),
+                );
+            }
+            let axis = (6.0 * sampler.next_1d()) as usize % 6;
+            let sign = if axis < 3 { 1.0 } else { -1.0 };
+            let mut direction = [0.0; 3];
+            direction[axis % 3] = sign;
+            // one more component keeps ray parallel to only one slab
+            if sampler.next_1d() < 0.5 {
+                direction[(axis + 1) % 3] = 2.0 * sampler.next_1d() - 1.0;
+            }
+            let direction = Vec3::new(direction[0], direction[1], direction[2]);
+            Ray::new(origin, direction.normalize())
+        });
+    }
+}
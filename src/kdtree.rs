@@ -0,0 +1,321 @@
+use crate::bounds::Bounds3;
+use crate::core::{IntersectInfoGlobal, IntersectableGlobal, IntersectableLocal, Ray};
+use crate::intersector::Intersector;
+use crate::types::Real;
+use crate::vec3::Vec3;
+
+use std::sync::Arc;
+
+const TRAVERSAL_COST: Real = 1.0; // cost of node traversal
+const INTERSECTION_COST: Real = 80.0; // cost of primitive intersection
+const EMPTY_BONUS: Real = 0.5; // cost reduction when one side of split is empty
+const MAX_PRIMITIVES_IN_NODE: usize = 1; // leaf is created below this number of primitives
+const MAX_BAD_REFINES: u32 = 3; // number of splits allowed which do not reduce cost
+
+struct KdNode {
+    split: Real,       // split position of interior node
+    axis: u8,          // split axis of interior node, 3 means leaf
+    offset: u32,       // leaf: offset of primitives, interior: index of above child
+    n_primitives: u32, // number of primitives in leaf
+}
+
+impl KdNode {
+    fn is_leaf(&self) -> bool {
+        self.axis == 3
+    }
+}
+
+#[derive(Clone, Copy)]
+struct BoundEdge {
+    t: Real,        // position of edge
+    is_start: bool, // whether edge is the start of primitive bounds
+}
+
+// NOTE: nodes are stored in depth-first order, so the below child is always next to its parent
+pub struct KdTree {
+    primitives: Arc<Vec<Box<dyn IntersectableLocal + Send + Sync>>>,
+    prim_indices: Vec<u32>,
+    nodes: Vec<KdNode>,
+    bounds: Bounds3,
+}
+
+struct KdTreeBuilder<'a> {
+    prim_bounds: &'a [Bounds3],
+    prim_indices: Vec<u32>,
+    nodes: Vec<KdNode>,
+}
+
+impl KdTree {
+    pub fn new(primitives: Arc<Vec<Box<dyn IntersectableLocal + Send + Sync>>>) -> Self {
+        let prim_bounds: Vec<Bounds3> = primitives.iter().map(|p| p.bounds()).collect();
+        let bounds = prim_bounds
+            .iter()
+            .fold(Bounds3::empty(), |b, prim_bounds| b.union(prim_bounds));
+
+        let mut builder = KdTreeBuilder {
+            prim_bounds: &prim_bounds,
+            prim_indices: Vec::new(),
+            nodes: Vec::new(),
+        };
+        if !primitives.is_empty() {
+            let max_depth = (8.0 + 1.3 * (primitives.len() as Real).log2()).round() as u32;
+            let prims: Vec<u32> = (0..primitives.len() as u32).collect();
+            builder.build_node(&bounds, prims, max_depth, 0);
+        }
+
+        KdTree {
+            prim_indices: builder.prim_indices,
+            nodes: builder.nodes,
+            primitives,
+            bounds,
+        }
+    }
+
+    pub fn n_nodes(&self) -> usize {
+        self.nodes.len()
+    }
+}
+
+impl<'a> KdTreeBuilder<'a> {
+    fn make_leaf(&mut self, prims: &[u32]) {
+        self.nodes.push(KdNode {
+            split: 0.0,
+            axis: 3,
+            offset: self.prim_indices.len() as u32,
+            n_primitives: prims.len() as u32,
+        });
+        self.prim_indices.extend_from_slice(prims);
+    }
+
+    // find split which minimizes SAH cost, returns (cost, axis, position)
+    fn find_split(&self, node_bounds: &Bounds3, prims: &[u32]) -> Option<(Real, usize, Real)> {
+        let diagonal = node_bounds.diagonal();
+        let inv_area = 1.0 / node_bounds.surface_area();
+        let n_primitives = prims.len();
+
+        let mut best: Option<(Real, usize, Real)> = None;
+        let mut axis = node_bounds.max_extent();
+        for _ in 0..3 {
+            let mut edges: Vec<BoundEdge> = Vec::with_capacity(2 * n_primitives);
+            for &prim_idx in prims {
+                let bounds = &self.prim_bounds[prim_idx as usize];
+                edges.push(BoundEdge {
+                    t: bounds.p_min[axis],
+                    is_start: true,
+                });
+                edges.push(BoundEdge {
+                    t: bounds.p_max[axis],
+                    is_start: false,
+                });
+            }
+            // start edges come first at the same position
+            edges.sort_by(|e1, e2| {
+                e1.t.total_cmp(&e2.t)
+                    .then_with(|| e2.is_start.cmp(&e1.is_start))
+            });
+
+            let other_axis0 = (axis + 1) % 3;
+            let other_axis1 = (axis + 2) % 3;
+            let mut n_below = 0;
+            let mut n_above = n_primitives;
+            for edge in &edges {
+                if !edge.is_start {
+                    n_above -= 1;
+                }
+                if edge.t > node_bounds.p_min[axis] && edge.t < node_bounds.p_max[axis] {
+                    let below_width = edge.t - node_bounds.p_min[axis];
+                    let above_width = node_bounds.p_max[axis] - edge.t;
+                    let cap_area = diagonal[other_axis0] * diagonal[other_axis1];
+                    let side_length = diagonal[other_axis0] + diagonal[other_axis1];
+                    let below_area = 2.0 * (cap_area + below_width * side_length);
+                    let above_area = 2.0 * (cap_area + above_width * side_length);
+
+                    let bonus = if n_below == 0 || n_above == 0 {
+                        EMPTY_BONUS
+                    } else {
+                        0.0
+                    };
+                    let cost = TRAVERSAL_COST
+                        + INTERSECTION_COST
+                            * (1.0 - bonus)
+                            * inv_area
+                            * (below_area * n_below as Real + above_area * n_above as Real);
+                    if best.is_none_or(|(best_cost, _, _)| cost < best_cost) {
+                        best = Some((cost, axis, edge.t));
+                    }
+                }
+                if edge.is_start {
+                    n_below += 1;
+                }
+            }
+
+            if best.is_some() {
+                break;
+            }
+            // retry with another axis
+            axis = (axis + 1) % 3;
+        }
+        best
+    }
+
+    fn build_node(&mut self, node_bounds: &Bounds3, prims: Vec<u32>, depth: u32, bad_refines: u32) {
+        if prims.len() <= MAX_PRIMITIVES_IN_NODE || depth == 0 {
+            self.make_leaf(&prims);
+            return;
+        }
+
+        let (cost, axis, split) = match self.find_split(node_bounds, &prims) {
+            Some(best) => best,
+            None => {
+                self.make_leaf(&prims);
+                return;
+            }
+        };
+        let leaf_cost = INTERSECTION_COST * prims.len() as Real;
+        let bad_refines = if cost > leaf_cost {
+            bad_refines + 1
+        } else {
+            bad_refines
+        };
+        if (cost > 4.0 * leaf_cost && prims.len() < 16) || bad_refines == MAX_BAD_REFINES {
+            self.make_leaf(&prims);
+            return;
+        }
+
+        // primitives straddling the split plane are referenced by both children
+        let below_prims: Vec<u32> = prims
+            .iter()
+            .copied()
+            .filter(|&idx| self.prim_bounds[idx as usize].p_min[axis] <= split)
+            .collect();
+        let above_prims: Vec<u32> = prims
+            .iter()
+            .copied()
+            .filter(|&idx| self.prim_bounds[idx as usize].p_max[axis] >= split)
+            .collect();
+
+        let mut below_bounds = *node_bounds;
+        let mut above_bounds = *node_bounds;
+        below_bounds.p_max = set_axis(below_bounds.p_max, axis, split);
+        above_bounds.p_min = set_axis(above_bounds.p_min, axis, split);
+
+        let node_idx = self.nodes.len();
+        self.nodes.push(KdNode {
+            split,
+            axis: axis as u8,
+            offset: 0,
+            n_primitives: 0,
+        });
+        self.build_node(&below_bounds, below_prims, depth - 1, bad_refines);
+        self.nodes[node_idx].offset = self.nodes.len() as u32;
+        self.build_node(&above_bounds, above_prims, depth - 1, bad_refines);
+    }
+}
+
+fn set_axis(v: Vec3, axis: usize, value: Real) -> Vec3 {
+    Vec3::new(
+        if axis == 0 { value } else { v.x() },
+        if axis == 1 { value } else { v.y() },
+        if axis == 2 { value } else { v.z() },
+    )
+}
+
+impl IntersectableGlobal for KdTree {
+    fn intersect(&self, ray: &Ray) -> Option<IntersectInfoGlobal> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+
+        let mut ray = ray.clone();
+        let inv_dir = 1.0 / ray.direction;
+        let (mut tmin, mut tmax) = self.bounds.intersect(&ray, inv_dir)?;
+
+        let mut info: Option<IntersectInfoGlobal> = None;
+        let mut stack: Vec<(usize, Real, Real)> = Vec::with_capacity(64);
+        let mut node_idx = 0;
+        loop {
+            // closer hit is already found
+            if ray.tmax < tmin {
+                break;
+            }
+
+            let node = &self.nodes[node_idx];
+            if !node.is_leaf() {
+                let axis = node.axis as usize;
+                let t_plane = (node.split - ray.origin[axis]) * inv_dir[axis];
+
+                // visit the child containing ray origin first
+                let below_first = ray.origin[axis] < node.split
+                    || (ray.origin[axis] == node.split && ray.direction[axis] <= 0.0);
+                let (first, second) = if below_first {
+                    (node_idx + 1, node.offset as usize)
+                } else {
+                    (node.offset as usize, node_idx + 1)
+                };
+
+                // NaN means ray lies in split plane, primitives on it are in both children
+                if t_plane > tmax || t_plane <= 0.0 || t_plane.is_nan() {
+                    node_idx = first;
+                } else if t_plane < tmin {
+                    node_idx = second;
+                } else {
+                    stack.push((second, t_plane, tmax));
+                    node_idx = first;
+                    tmax = t_plane;
+                }
+                continue;
+            }
+
+            let start = node.offset as usize;
+            let end = start + node.n_primitives as usize;
+            for &prim_idx in &self.prim_indices[start..end] {
+                if let Some(surf_info) = self.primitives[prim_idx as usize].intersect(&ray) {
+                    ray.tmax = surf_info.t;
+                    info = Some(IntersectInfoGlobal {
+                        t: surf_info.t,
+                        pos: surf_info.pos,
+                        normal: surf_info.normal,
+                        shading_normal: surf_info.shading_normal,
+                        uv: surf_info.uv,
+                        dpdu: surf_info.dpdu,
+                        dpdv: surf_info.dpdv,
+                        prim_idx,
+                    });
+                }
+            }
+
+            match stack.pop() {
+                Some((idx, t0, t1)) => {
+                    node_idx = idx;
+                    tmin = t0;
+                    tmax = t1;
+                }
+                None => break,
+            }
+        }
+        info
+    }
+}
+
+impl Intersector for KdTree {}
+
+#[cfg(test)]
+mod tests {
+    use crate::kdtree::*;
+    use crate::shape::Sphere;
+
+    #[test]
+    fn kdtree_split() {
+        let primitives: Vec<Box<dyn IntersectableLocal + Send + Sync>> = vec![
+            Box::new(Sphere::new(Vec3::new(0.0, 0.0, 0.0), 1.0)),
+            Box::new(Sphere::new(Vec3::new(8.0, 0.0, 0.0), 1.0)),
+        ];
+        let kdtree = KdTree::new(Arc::new(primitives));
+        assert!(kdtree.n_nodes() > 1);
+
+        let ray = Ray::new(Vec3::new(12.0, 0.0, 0.0), Vec3::new(-1.0, 0.0, 0.0));
+        let info = kdtree.intersect(&ray).unwrap();
+        assert_eq!(info.prim_idx, 1);
+        assert_eq!(info.t, 3.0);
+    }
+}
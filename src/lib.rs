@@ -1,12 +1,28 @@
+pub mod bounds;
+pub mod bsdf;
+pub mod bvh;
 pub mod bxdf;
 pub mod camera;
+pub mod color;
 pub mod core;
+pub mod distribution;
+pub mod grid;
 pub mod image;
 pub mod integrator;
 pub mod intersector;
+pub mod kdtree;
+pub mod light;
+pub mod light_sampler;
+pub mod loader;
+pub mod material;
 pub mod sampler;
 pub mod scene;
 pub mod shape;
+pub mod sky;
+#[cfg(test)]
+mod test_dir;
+pub mod texture;
+pub mod transform;
 pub mod types;
 pub mod vec2;
 pub mod vec3;
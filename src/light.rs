@@ -0,0 +1,802 @@
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+use crate::bounds::Bounds3;
+use crate::core::{spherical_to_cartesian, IntersectableLocal, Ray, ShadingInfo, SurfaceSample};
+use crate::distribution::Distribution2D;
+use crate::image::Image;
+use crate::light_sampler::LightBounds;
+use crate::material::Material;
+use crate::sampler::{uniform_cone, Sampler};
+use crate::types::Real;
+use crate::vec2::Vec2;
+use crate::vec3::{build_orthonormal_basis, Vec3};
+
+pub struct LightSample {
+    pub wi: Vec3,       // direction towards light
+    pub distance: Real, // distance to sampled point
+    pub le: Vec3,       // incoming radiance, or irradiance for delta lights
+    pub pdf: Real,      // pdf in solid angle measure, 1 for delta lights
+    pub delta: bool,    // sampled from delta light which BSDF sampling can't hit
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum LightType {
+    DeltaPosition,  // emits from single point
+    DeltaDirection, // arrives from single direction
+    Area,           // emissive primitive of the scene
+    Infinite,       // seen by rays escaping the scene
+}
+
+impl LightType {
+    pub fn is_delta(&self) -> bool {
+        matches!(self, LightType::DeltaPosition | LightType::DeltaDirection)
+    }
+}
+
+pub trait Light: Send + Sync {
+    fn light_type(&self) -> LightType;
+
+    // called once the scene is built, bounds are needed by lights at infinity
+    fn preprocess(&mut self, _bounds: &Bounds3) {}
+
+    // sample incoming direction at x
+    fn sample_li(&self, x: Vec3, sampler: &mut Sampler) -> Option<LightSample>;
+
+    // pdf of sample_li choosing direction wi at x, always 0 for delta lights
+    fn pdf_li(&self, x: Vec3, wi: Vec3) -> Real;
+
+    // total emitted power
+    fn power(&self) -> Vec3;
+
+    // extent of emission used by light BVH, none for lights at infinity
+    fn bounds(&self) -> Option<LightBounds> {
+        None
+    }
+
+    // radiance arriving from direction -wi along ray escaping the scene
+    fn le(&self, _wi: Vec3) -> Vec3 {
+        Vec3::new(0.0, 0.0, 0.0)
+    }
+}
+
+// emissive primitive of the scene, radiance is given by emission of its material
+pub struct AreaLight {
+    primitives: Arc<Vec<Box<dyn IntersectableLocal + Send + Sync>>>,
+    prim_idx: u32,
+    material: Arc<dyn Material>,
+    front: Vec3, // average radiance leaving front side
+    back: Vec3,  // average radiance leaving back side
+}
+
+impl AreaLight {
+    pub fn new(
+        primitives: Arc<Vec<Box<dyn IntersectableLocal + Send + Sync>>>,
+        prim_idx: u32,
+        material: Arc<dyn Material>,
+    ) -> Self {
+        // averages are estimated at fixed set of points since emission may be textured
+        const N_SAMPLES: usize = 64;
+        let shape = primitives[prim_idx as usize].as_ref();
+        let mut sampler = Sampler::new(prim_idx as u64);
+        let mut front = Vec3::new(0.0, 0.0, 0.0);
+        let mut back = Vec3::new(0.0, 0.0, 0.0);
+        for _ in 0..N_SAMPLES {
+            let sample = shape.sample_area(&mut sampler);
+            let le = |wo: Vec3| material.emission(&emitter_info(&sample, wo));
+            front += le(sample.normal);
+            back += le(-sample.normal);
+        }
+
+        AreaLight {
+            primitives,
+            prim_idx,
+            material,
+            front: front / N_SAMPLES as Real,
+            back: back / N_SAMPLES as Real,
+        }
+    }
+
+    pub fn prim_idx(&self) -> u32 {
+        self.prim_idx
+    }
+
+    fn shape(&self) -> &dyn IntersectableLocal {
+        self.primitives[self.prim_idx as usize].as_ref()
+    }
+}
+
+// shading info of point on light whose frame is built around geometric normal,
+// wo_global points towards receiver
+fn emitter_info(sample: &SurfaceSample, wo_global: Vec3) -> ShadingInfo {
+    let (t, n, b) = build_orthonormal_basis(sample.normal);
+    ShadingInfo {
+        x: sample.pos,
+        n,
+        wo: wo_global.world_to_local(t, n, b),
+        t,
+        b,
+        uv: sample.uv,
+    }
+}
+
+impl Light for AreaLight {
+    fn light_type(&self) -> LightType {
+        LightType::Area
+    }
+
+    // sample incoming direction at x from the shape of this light
+    fn sample_li(&self, x: Vec3, sampler: &mut Sampler) -> Option<LightSample> {
+        let sample = self.shape().sample_solid_angle(x, sampler);
+        let wi = sample.pos - x;
+        let distance = wi.length();
+        if distance == 0.0 || !(sample.pdf > 0.0 && sample.pdf.is_finite()) {
+            return None;
+        }
+        let wi = wi / distance;
+
+        // back side of one-sided light is dark
+        let le = self.material.emission(&emitter_info(&sample, -wi));
+        if le.max() <= 0.0 {
+            return None;
+        }
+
+        Some(LightSample {
+            wi,
+            distance,
+            le,
+            pdf: sample.pdf,
+            delta: false,
+        })
+    }
+
+    // shape is intersected again to find the point seen from x
+    fn pdf_li(&self, x: Vec3, wi: Vec3) -> Real {
+        match self.shape().intersect(&Ray::new(x, wi)) {
+            Some(info) => self.shape().pdf_solid_angle(x, info.pos, info.normal),
+            None => 0.0,
+        }
+    }
+
+    fn power(&self) -> Vec3 {
+        PI * self.shape().area() * (self.front + self.back)
+    }
+
+    fn bounds(&self) -> Option<LightBounds> {
+        let (w, cos_theta_o) = self.shape().normal_bounds();
+        let front = self.front.max();
+        let back = self.back.max();
+        // cone of one-sided light faces the side which emits
+        let w = if front > 0.0 || back <= 0.0 { w } else { -w };
+        Some(LightBounds {
+            bounds: self.shape().bounds(),
+            w,
+            phi: front.max(back) * self.shape().area(),
+            cos_theta_o,
+            cos_theta_e: 0.0,
+            two_sided: front > 0.0 && back > 0.0,
+        })
+    }
+}
+
+// isotropic light at single point
+pub struct PointLight {
+    position: Vec3,
+    intensity: Vec3, // radiant intensity
+}
+
+impl PointLight {
+    pub fn new(position: Vec3, intensity: Vec3) -> Self {
+        PointLight {
+            position,
+            intensity,
+        }
+    }
+}
+
+impl Light for PointLight {
+    fn light_type(&self) -> LightType {
+        LightType::DeltaPosition
+    }
+
+    fn sample_li(&self, x: Vec3, _sampler: &mut Sampler) -> Option<LightSample> {
+        let wi = self.position - x;
+        let distance2 = wi.length2();
+        if distance2 == 0.0 {
+            return None;
+        }
+        let distance = distance2.sqrt();
+        Some(LightSample {
+            wi: wi / distance,
+            distance,
+            le: self.intensity / distance2,
+            pdf: 1.0,
+            delta: true,
+        })
+    }
+
+    fn pdf_li(&self, _x: Vec3, _wi: Vec3) -> Real {
+        0.0
+    }
+
+    fn power(&self) -> Vec3 {
+        4.0 * PI * self.intensity
+    }
+
+    fn bounds(&self) -> Option<LightBounds> {
+        Some(LightBounds {
+            bounds: Bounds3::new(self.position, self.position),
+            w: Vec3::new(0.0, 1.0, 0.0),
+            phi: self.intensity.max(),
+            cos_theta_o: -1.0,
+            cos_theta_e: 0.0,
+            two_sided: false,
+        })
+    }
+}
+
+// point light emitting into cone around direction, intensity falls off smoothly
+// from cos_inner to cos_outer
+pub struct SpotLight {
+    position: Vec3,
+    direction: Vec3, // direction light travels along cone axis
+    intensity: Vec3,
+    cos_inner: Real,
+    cos_outer: Real,
+}
+
+impl SpotLight {
+    pub fn new(
+        position: Vec3,
+        direction: Vec3,
+        intensity: Vec3,
+        inner_angle: Real,
+        outer_angle: Real,
+    ) -> Self {
+        let cos_outer = outer_angle.cos();
+        SpotLight {
+            position,
+            direction: direction.normalize(),
+            intensity,
+            cos_inner: inner_angle.cos().max(cos_outer),
+            cos_outer,
+        }
+    }
+
+    fn falloff(&self, cos_theta: Real) -> Real {
+        if cos_theta >= self.cos_inner {
+            return 1.0;
+        }
+        let t = ((cos_theta - self.cos_outer) / (self.cos_inner - self.cos_outer)).clamp(0.0, 1.0);
+        t * t * (3.0 - 2.0 * t)
+    }
+}
+
+impl Light for SpotLight {
+    fn light_type(&self) -> LightType {
+        LightType::DeltaPosition
+    }
+
+    fn sample_li(&self, x: Vec3, _sampler: &mut Sampler) -> Option<LightSample> {
+        let wi = self.position - x;
+        let distance2 = wi.length2();
+        if distance2 == 0.0 {
+            return None;
+        }
+        let distance = distance2.sqrt();
+        let wi = wi / distance;
+        let falloff = self.falloff(-wi.dot(self.direction));
+        if falloff == 0.0 {
+            return None;
+        }
+        Some(LightSample {
+            wi,
+            distance,
+            le: falloff * self.intensity / distance2,
+            pdf: 1.0,
+            delta: true,
+        })
+    }
+
+    fn pdf_li(&self, _x: Vec3, _wi: Vec3) -> Real {
+        0.0
+    }
+
+    // smoothstep integrates to half of solid angle between inner and outer cone
+    fn power(&self) -> Vec3 {
+        2.0 * PI
+            * ((1.0 - self.cos_inner) + 0.5 * (self.cos_inner - self.cos_outer))
+            * self.intensity
+    }
+
+    fn bounds(&self) -> Option<LightBounds> {
+        // falloff spans angle between inner and outer cone
+        let theta_e = self.cos_outer.acos() - self.cos_inner.acos();
+        Some(LightBounds {
+            bounds: Bounds3::new(self.position, self.position),
+            w: self.direction,
+            phi: self.intensity.max(),
+            cos_theta_o: self.cos_inner,
+            cos_theta_e: theta_e.cos(),
+            two_sided: false,
+        })
+    }
+}
+
+// light arriving from single direction with constant irradiance, e.g. distant sun
+pub struct DirectionalLight {
+    direction: Vec3, // direction towards light
+    irradiance: Vec3,
+    scene_radius: Real,
+}
+
+impl DirectionalLight {
+    pub fn new(direction: Vec3, irradiance: Vec3) -> Self {
+        DirectionalLight {
+            direction: direction.normalize(),
+            irradiance,
+            scene_radius: 0.0,
+        }
+    }
+}
+
+impl Light for DirectionalLight {
+    fn light_type(&self) -> LightType {
+        LightType::DeltaDirection
+    }
+
+    fn preprocess(&mut self, bounds: &Bounds3) {
+        self.scene_radius = bounding_radius(bounds);
+    }
+
+    fn sample_li(&self, _x: Vec3, _sampler: &mut Sampler) -> Option<LightSample> {
+        Some(LightSample {
+            wi: self.direction,
+            distance: Real::INFINITY,
+            le: self.irradiance,
+            pdf: 1.0,
+            delta: true,
+        })
+    }
+
+    fn pdf_li(&self, _x: Vec3, _wi: Vec3) -> Real {
+        0.0
+    }
+
+    // power falling onto disk covering the scene
+    fn power(&self) -> Vec3 {
+        PI * self.scene_radius * self.scene_radius * self.irradiance
+    }
+}
+
+// infinitely distant light given by equirectangular image whose top row is +y,
+// u follows azimuth from +x towards +z as in spherical_to_cartesian
+pub struct EnvironmentLight {
+    image: Arc<Image>,
+    intensity: Real,
+    rotation: Real, // rotation around y axis in radians
+    distribution: Distribution2D,
+    scene_radius: Real,
+}
+
+impl EnvironmentLight {
+    pub fn new(image: Arc<Image>, intensity: Real, rotation: Real) -> Self {
+        // texels near poles cover smaller solid angle
+        let width = image.get_width();
+        let height = image.get_height();
+        let func = (0..height)
+            .map(|i| {
+                let sin_theta = (PI * (i as Real + 0.5) / height as Real).sin();
+                (0..width)
+                    .map(|j| image.get_pixel(i, j).max().max(0.0) * sin_theta)
+                    .collect()
+            })
+            .collect();
+
+        EnvironmentLight {
+            image,
+            intensity,
+            rotation,
+            distribution: Distribution2D::new(func),
+            scene_radius: 0.0,
+        }
+    }
+
+    // image coordinates of world direction
+    fn direction_to_uv(&self, wi: Vec3) -> (Vec2, Real) {
+        let w = rotate_y(wi, -self.rotation);
+        // sin(theta) from x and z keeps precision near poles
+        let sin_theta = (w.x() * w.x() + w.z() * w.z()).sqrt();
+        let mut phi = w.z().atan2(w.x());
+        if phi < 0.0 {
+            phi += 2.0 * PI;
+        }
+        let uv = Vec2::new(phi / (2.0 * PI), sin_theta.atan2(w.y()) / PI);
+        (uv, sin_theta)
+    }
+}
+
+impl Light for EnvironmentLight {
+    fn light_type(&self) -> LightType {
+        LightType::Infinite
+    }
+
+    fn preprocess(&mut self, bounds: &Bounds3) {
+        self.scene_radius = bounding_radius(bounds);
+    }
+
+    // sample incoming direction in proportion to brightness of image
+    fn sample_li(&self, _x: Vec3, sampler: &mut Sampler) -> Option<LightSample> {
+        let (uv, pdf_uv) = self.distribution.sample(sampler.next_2d());
+        let theta = PI * uv.y();
+        let sin_theta = theta.sin();
+        if pdf_uv == 0.0 || sin_theta == 0.0 {
+            return None;
+        }
+
+        let wi = rotate_y(
+            spherical_to_cartesian(theta, 2.0 * PI * uv.x()),
+            self.rotation,
+        );
+        Some(LightSample {
+            wi,
+            distance: Real::INFINITY,
+            le: self.le(wi),
+            pdf: pdf_uv / (2.0 * PI * PI * sin_theta),
+            delta: false,
+        })
+    }
+
+    fn pdf_li(&self, _x: Vec3, wi: Vec3) -> Real {
+        let (uv, sin_theta) = self.direction_to_uv(wi);
+        if sin_theta == 0.0 {
+            return 0.0;
+        }
+        self.distribution.pdf(uv) / (2.0 * PI * PI * sin_theta)
+    }
+
+    // radiance integrated over sphere falling onto disk covering the scene
+    fn power(&self) -> Vec3 {
+        let width = self.image.get_width();
+        let height = self.image.get_height();
+        let mut sum = Vec3::new(0.0, 0.0, 0.0);
+        for i in 0..height {
+            let sin_theta = (PI * (i as Real + 0.5) / height as Real).sin();
+            for j in 0..width {
+                sum += sin_theta * self.image.get_pixel(i, j);
+            }
+        }
+        let d_omega = 2.0 * PI * PI / (width * height) as Real;
+        PI * self.scene_radius * self.scene_radius * self.intensity * d_omega * sum
+    }
+
+    fn le(&self, wi: Vec3) -> Vec3 {
+        let (uv, _) = self.direction_to_uv(wi);
+        let width = self.image.get_width();
+        let height = self.image.get_height();
+        let i = ((uv.y() * height as Real) as usize).min(height - 1);
+        let j = ((uv.x() * width as Real) as usize).min(width - 1);
+        self.intensity * self.image.get_pixel(i, j)
+    }
+}
+
+// distant light of small disk such as sun, seen within angular radius of direction
+pub struct SunLight {
+    direction: Vec3, // direction towards light
+    le: Vec3,
+    cos_theta_max: Real,
+    one_minus_cos_theta_max: Real, // kept separately for precision of small disk
+    scene_radius: Real,
+}
+
+impl SunLight {
+    pub fn new(direction: Vec3, le: Vec3, angular_radius: Real) -> Self {
+        SunLight {
+            direction: direction.normalize(),
+            le,
+            cos_theta_max: angular_radius.cos(),
+            one_minus_cos_theta_max: 2.0 * (0.5 * angular_radius).sin().powi(2),
+            scene_radius: 0.0,
+        }
+    }
+}
+
+impl Light for SunLight {
+    fn light_type(&self) -> LightType {
+        LightType::Infinite
+    }
+
+    fn preprocess(&mut self, bounds: &Bounds3) {
+        self.scene_radius = bounding_radius(bounds);
+    }
+
+    // sample incoming direction uniformly inside disk
+    fn sample_li(&self, _x: Vec3, sampler: &mut Sampler) -> Option<LightSample> {
+        let (w, pdf) = uniform_cone(sampler.next_2d(), self.one_minus_cos_theta_max);
+        let (t, n, b) = build_orthonormal_basis(self.direction);
+        Some(LightSample {
+            wi: w.local_to_world(t, n, b),
+            distance: Real::INFINITY,
+            le: self.le,
+            pdf,
+            delta: false,
+        })
+    }
+
+    fn pdf_li(&self, _x: Vec3, wi: Vec3) -> Real {
+        if wi.dot(self.direction) >= self.cos_theta_max {
+            1.0 / (2.0 * PI * self.one_minus_cos_theta_max)
+        } else {
+            0.0
+        }
+    }
+
+    fn power(&self) -> Vec3 {
+        let solid_angle = 2.0 * PI * self.one_minus_cos_theta_max;
+        PI * self.scene_radius * self.scene_radius * solid_angle * self.le
+    }
+
+    fn le(&self, wi: Vec3) -> Vec3 {
+        if wi.dot(self.direction) >= self.cos_theta_max {
+            self.le
+        } else {
+            Vec3::new(0.0, 0.0, 0.0)
+        }
+    }
+}
+
+// radius of sphere enclosing bounds
+fn bounding_radius(bounds: &Bounds3) -> Real {
+    if bounds.is_empty() {
+        0.0
+    } else {
+        0.5 * bounds.diagonal().length()
+    }
+}
+
+// rotate v around y axis, positive angle turns +x towards +z
+fn rotate_y(v: Vec3, angle: Real) -> Vec3 {
+    let (sin, cos) = angle.sin_cos();
+    Vec3::new(cos * v.x() - sin * v.z(), v.y(), sin * v.x() + cos * v.z())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::light::*;
+    use crate::material::{Diffuse, Emissive};
+    use crate::shape::{Plane, Sphere};
+    use crate::texture::{ConstantTexture, Texture};
+
+    fn emitter(radiance: Arc<dyn Texture<Vec3>>, two_sided: bool) -> Arc<dyn Material> {
+        let black = Arc::new(ConstantTexture::new(Vec3::new(0.0, 0.0, 0.0)));
+        Arc::new(Emissive::new(
+            Arc::new(Diffuse::new(black)),
+            radiance,
+            two_sided,
+        ))
+    }
+
+    fn area_light(shape: Box<dyn IntersectableLocal + Send + Sync>) -> AreaLight {
+        let white = Arc::new(ConstantTexture::new(Vec3::new(1.0, 1.0, 1.0)));
+        AreaLight::new(Arc::new(vec![shape]), 0, emitter(white, true))
+    }
+
+    // color given by surface coordinates
+    struct UvTexture {}
+
+    impl Texture<Vec3> for UvTexture {
+        fn eval(&self, info: &ShadingInfo) -> Vec3 {
+            Vec3::new(info.uv.x(), info.uv.y(), 0.0)
+        }
+    }
+
+    // unit square placed 1 above origin
+    fn unit_square() -> Box<Plane> {
+        Box::new(Plane::new(
+            Vec3::new(-0.5, 1.0, -0.5),
+            Vec3::new(0.0, 0.0, 1.0),
+            Vec3::new(1.0, 0.0, 0.0),
+        ))
+    }
+
+    #[test]
+    fn area_light_solid_angle() {
+        let light = area_light(unit_square());
+        let x = Vec3::new(0.0, 0.0, 0.0);
+
+        // estimate solid angle subtended by light
+        let mut sampler = Sampler::new(0);
+        let n_samples = 100000;
+        let mut solid_angle = 0.0;
+        for _ in 0..n_samples {
+            let sample = light.sample_li(x, &mut sampler).unwrap();
+            assert!(sample.wi.y() > 0.0);
+            solid_angle += 1.0 / sample.pdf;
+        }
+        solid_angle /= n_samples as Real;
+
+        let expected = 4.0 * (1.0 / (5.0 as Real)).asin();
+        assert!((solid_angle - expected).abs() < 1E-2 * expected);
+        assert_eq!(light.power(), Vec3::new(2.0 * PI, 2.0 * PI, 2.0 * PI));
+    }
+
+    #[test]
+    fn area_light_pdf_matches_sample() {
+        let lights = [
+            area_light(unit_square()),
+            area_light(Box::new(Sphere::new(Vec3::new(0.0, 3.0, 0.0), 1.0))),
+        ];
+        let x = Vec3::new(0.2, 0.0, 0.1);
+
+        let mut sampler = Sampler::new(0);
+        for light in lights {
+            for _ in 0..100 {
+                let sample = light.sample_li(x, &mut sampler).unwrap();
+                let pdf = light.pdf_li(x, sample.wi);
+                assert!((pdf - sample.pdf).abs() < 1E-3 * sample.pdf);
+            }
+            // direction missing the light
+            assert_eq!(light.pdf_li(x, Vec3::new(0.0, -1.0, 0.0)), 0.0);
+        }
+    }
+
+    #[test]
+    fn one_sided_textured_area_light() {
+        // front side of unit square faces +y
+        let light = AreaLight::new(
+            Arc::new(vec![unit_square()]),
+            0,
+            emitter(Arc::new(UvTexture {}), false),
+        );
+        let mut sampler = Sampler::new(0);
+        assert!(light
+            .sample_li(Vec3::new(0.0, 0.0, 0.0), &mut sampler)
+            .is_none());
+
+        // u runs along z and v along x
+        let x = Vec3::new(0.1, 2.0, 0.2);
+        for _ in 0..100 {
+            let sample = light.sample_li(x, &mut sampler).unwrap();
+            let pos = x + sample.distance * sample.wi;
+            let expected = Vec3::new(pos.z() + 0.5, pos.x() + 0.5, 0.0);
+            assert!((sample.le - expected).length() < 1E-4);
+        }
+
+        // average radiance of texture is 0.5 on front side only, estimated from few samples
+        let power = light.power();
+        assert!((power.x() - 0.5 * PI).abs() < 0.2 * PI);
+        assert!((power.y() - 0.5 * PI).abs() < 0.2 * PI);
+        assert_eq!(power.z(), 0.0);
+        let bounds = light.bounds().unwrap();
+        assert!(!bounds.two_sided);
+        assert_eq!(bounds.w, Vec3::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn point_light() {
+        let light = PointLight::new(Vec3::new(0.0, 2.0, 0.0), Vec3::new(4.0, 8.0, 12.0));
+        let mut sampler = Sampler::new(0);
+        let sample = light
+            .sample_li(Vec3::new(0.0, 0.0, 0.0), &mut sampler)
+            .unwrap();
+        assert_eq!(sample.wi, Vec3::new(0.0, 1.0, 0.0));
+        assert_eq!(sample.distance, 2.0);
+        assert_eq!(sample.le, Vec3::new(1.0, 2.0, 3.0));
+        assert!(sample.delta && light.light_type().is_delta());
+        assert_eq!(light.pdf_li(Vec3::new(0.0, 0.0, 0.0), sample.wi), 0.0);
+        assert_eq!(light.power(), 4.0 * PI * Vec3::new(4.0, 8.0, 12.0));
+    }
+
+    #[test]
+    fn spot_light() {
+        // points down, full intensity within 30 degrees and none beyond 60 degrees
+        let light = SpotLight::new(
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(0.0, -1.0, 0.0),
+            Vec3::new(1.0, 1.0, 1.0),
+            PI / 6.0,
+            PI / 3.0,
+        );
+        let mut sampler = Sampler::new(0);
+        let mut le_at = |x: Real| {
+            light
+                .sample_li(Vec3::new(x, 0.0, 0.0), &mut sampler)
+                .map_or(0.0, |sample| sample.le.x() * (1.0 + x * x))
+        };
+        assert_eq!(le_at(0.0), 1.0);
+        assert!((le_at(0.5) - 1.0).abs() < 1E-5);
+        let half = le_at((PI / 4.0).tan());
+        assert!(0.0 < half && half < 1.0);
+        assert_eq!(le_at(2.0), 0.0);
+
+        // power lies between those of cones with inner and outer angles
+        let power = light.power().x();
+        assert!(power > 2.0 * PI * (1.0 - (PI / 6.0).cos()));
+        assert!(power < 2.0 * PI * (1.0 - (PI / 3.0).cos()));
+    }
+
+    #[test]
+    fn directional_light() {
+        let direction = Vec3::new(0.0, 1.0, 0.0);
+        let mut light = DirectionalLight::new(direction, Vec3::new(2.0, 2.0, 2.0));
+        light.preprocess(&Bounds3::new(
+            Vec3::new(-1.0, -1.0, -1.0),
+            Vec3::new(1.0, 1.0, 1.0),
+        ));
+
+        let mut sampler = Sampler::new(0);
+        let sample = light
+            .sample_li(Vec3::new(5.0, 0.0, 0.0), &mut sampler)
+            .unwrap();
+        assert_eq!(sample.wi, direction);
+        assert_eq!(sample.distance, Real::INFINITY);
+        assert_eq!(sample.le, Vec3::new(2.0, 2.0, 2.0));
+        assert!((light.power().x() - 6.0 * PI).abs() < 1E-4);
+    }
+
+    // 4x2 image whose brightest texel is in upper row
+    fn environment_image() -> Arc<Image> {
+        let mut image = Image::new(4, 2);
+        for i in 0..2 {
+            for j in 0..4 {
+                image.set_pixel(i, j, Vec3::new(0.1, 0.2, 0.1));
+            }
+        }
+        image.set_pixel(0, 1, Vec3::new(10.0, 8.0, 6.0));
+        Arc::new(image)
+    }
+
+    #[test]
+    fn environment_light_lookup() {
+        let light = EnvironmentLight::new(environment_image(), 2.0, 0.0);
+        // second column of upper row spans azimuth from +z to -x
+        let wi = Vec3::new(-1.0, 1.0, 1.0).normalize();
+        assert_eq!(light.le(wi), Vec3::new(20.0, 16.0, 12.0));
+        assert_eq!(light.le(-wi), Vec3::new(0.2, 0.4, 0.2));
+
+        // rotation by 90 degrees turns +z towards -x
+        let rotated = EnvironmentLight::new(environment_image(), 2.0, 0.5 * PI);
+        let wi = Vec3::new(-1.0, 1.0, -1.0).normalize();
+        assert_eq!(rotated.le(wi), Vec3::new(20.0, 16.0, 12.0));
+    }
+
+    #[test]
+    fn environment_light_sampling() {
+        let light = EnvironmentLight::new(environment_image(), 1.0, 0.3);
+        let x = Vec3::new(0.0, 0.0, 0.0);
+
+        // monte carlo estimate of irradiance integral over sphere
+        let mut sampler = Sampler::new(0);
+        let n_samples = 100000;
+        let mut estimate = Vec3::new(0.0, 0.0, 0.0);
+        for _ in 0..n_samples {
+            let Some(sample) = light.sample_li(x, &mut sampler) else {
+                continue;
+            };
+            assert!((sample.pdf - light.pdf_li(x, sample.wi)).abs() < 1E-3 * sample.pdf);
+            estimate += sample.le / sample.pdf;
+        }
+        estimate /= n_samples as Real;
+
+        // each texel spans 2pi^2 / 8 * integral of sin(theta) over its row
+        let texel = 0.5 * PI;
+        let expected = texel * Vec3::new(10.0 + 0.7, 8.0 + 1.4, 6.0 + 0.7);
+        assert!((estimate - expected).length() < 1E-2 * expected.length());
+    }
+
+    #[test]
+    fn sun_light() {
+        let direction = Vec3::new(1.0, 2.0, 0.5).normalize();
+        let light = SunLight::new(direction, Vec3::new(5.0, 4.0, 3.0), 0.01);
+        let x = Vec3::new(0.0, 0.0, 0.0);
+        assert_eq!(light.le(direction), Vec3::new(5.0, 4.0, 3.0));
+        assert_eq!(light.le(Vec3::new(0.0, 1.0, 0.0)), Vec3::new(0.0, 0.0, 0.0));
+
+        let mut sampler = Sampler::new(0);
+        for _ in 0..100 {
+            let sample = light.sample_li(x, &mut sampler).unwrap();
+            assert!(sample.wi.dot(direction) > (0.011 as Real).cos());
+            assert!((sample.pdf - light.pdf_li(x, sample.wi)).abs() < 1E-2 * sample.pdf);
+        }
+    }
+}
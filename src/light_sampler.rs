@@ -0,0 +1,470 @@
+use std::f32::consts::PI;
+
+use crate::bounds::Bounds3;
+use crate::distribution::Distribution1D;
+use crate::light::Light;
+use crate::types::Real;
+use crate::vec3::Vec3;
+
+// chooses which light is sampled at shading point
+pub trait LightSampler: Send + Sync {
+    // returns index of light chosen for x by u in [0, 1) and probability of choosing it
+    fn sample(&self, x: Vec3, u: Real) -> Option<(usize, Real)>;
+
+    // probability of sample choosing light at x
+    fn pmf(&self, x: Vec3, light_idx: usize) -> Real;
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum LightSamplerType {
+    Uniform,
+    Power,
+    BVH,
+}
+
+pub fn create_light_sampler(
+    light_sampler_type: LightSamplerType,
+    lights: &[Box<dyn Light>],
+) -> Box<dyn LightSampler> {
+    match light_sampler_type {
+        LightSamplerType::Uniform => Box::new(UniformLightSampler::new(lights)),
+        LightSamplerType::Power => Box::new(PowerLightSampler::new(lights)),
+        LightSamplerType::BVH => Box::new(BVHLightSampler::new(lights)),
+    }
+}
+
+pub struct UniformLightSampler {
+    n_lights: usize,
+}
+
+impl UniformLightSampler {
+    pub fn new(lights: &[Box<dyn Light>]) -> Self {
+        UniformLightSampler {
+            n_lights: lights.len(),
+        }
+    }
+}
+
+impl LightSampler for UniformLightSampler {
+    fn sample(&self, _x: Vec3, u: Real) -> Option<(usize, Real)> {
+        if self.n_lights == 0 {
+            return None;
+        }
+        let light_idx = ((u * self.n_lights as Real) as usize).min(self.n_lights - 1);
+        Some((light_idx, 1.0 / self.n_lights as Real))
+    }
+
+    fn pmf(&self, _x: Vec3, _light_idx: usize) -> Real {
+        1.0 / self.n_lights as Real
+    }
+}
+
+// chooses light in proportion to its emitted power regardless of shading point
+pub struct PowerLightSampler {
+    distribution: Option<Distribution1D>, // none if there is no light
+}
+
+impl PowerLightSampler {
+    pub fn new(lights: &[Box<dyn Light>]) -> Self {
+        let distribution = if lights.is_empty() {
+            None
+        } else {
+            Some(Distribution1D::new(
+                lights.iter().map(|light| light.power().max()).collect(),
+            ))
+        };
+        PowerLightSampler { distribution }
+    }
+}
+
+impl LightSampler for PowerLightSampler {
+    fn sample(&self, _x: Vec3, u: Real) -> Option<(usize, Real)> {
+        let distribution = self.distribution.as_ref()?;
+        Some(distribution.sample_discrete(u))
+    }
+
+    fn pmf(&self, _x: Vec3, light_idx: usize) -> Real {
+        self.distribution
+            .as_ref()
+            .map_or(0.0, |distribution| distribution.discrete_pmf(light_idx))
+    }
+}
+
+// spatial and directional extent of emission, phi bounds emitted intensity so that
+// irradiance at distance d is about phi / d^2
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct LightBounds {
+    pub bounds: Bounds3,
+    pub w: Vec3,           // axis of cone bounding emitting normals or directions
+    pub phi: Real,         // bound of emitted intensity
+    pub cos_theta_o: Real, // cosine of spread of cone around w
+    pub cos_theta_e: Real, // cosine of angle beyond cone where emission falls off to zero
+    pub two_sided: bool,   // emits on both sides of normals in cone
+}
+
+impl LightBounds {
+    pub fn union(&self, other: &LightBounds) -> LightBounds {
+        let (w, cos_theta_o) = union_cones(self.w, self.cos_theta_o, other.w, other.cos_theta_o);
+        LightBounds {
+            bounds: self.bounds.union(&other.bounds),
+            w,
+            phi: self.phi + other.phi,
+            cos_theta_o,
+            cos_theta_e: self.cos_theta_e.min(other.cos_theta_e),
+            two_sided: self.two_sided || other.two_sided,
+        }
+    }
+
+    // conservative estimate of contribution of lights inside bounds at x
+    pub fn importance(&self, x: Vec3) -> Real {
+        let center = self.bounds.center();
+        let d = x - center;
+        let d2 = d.length2();
+        let radius2 = 0.25 * self.bounds.diagonal().length2();
+
+        // angle between cone axis and direction towards x
+        let mut cos_theta_w = if d2 > 0.0 {
+            self.w.dot(d.normalize())
+        } else {
+            1.0
+        };
+        if self.two_sided {
+            cos_theta_w = cos_theta_w.abs();
+        }
+
+        // angle subtended by bounds seen from x
+        let cos_theta_b = if d2 > radius2 {
+            (1.0 - radius2 / d2).sqrt()
+        } else {
+            -1.0
+        };
+
+        // minimum angle between emission cone and direction towards x for any point inside bounds
+        let cos_theta_x = cos_sub_clamped(cos_theta_w, self.cos_theta_o);
+        let cos_theta_p = cos_sub_clamped(cos_theta_x, cos_theta_b);
+        if cos_theta_p <= self.cos_theta_e {
+            return 0.0;
+        }
+
+        // distance is bounded below to avoid blowing up near or inside bounds
+        self.phi * cos_theta_p / d2.max(radius2).max(Real::EPSILON)
+    }
+}
+
+// cosine of max(0, a - b) for angles a and b given by cosines
+fn cos_sub_clamped(cos_a: Real, cos_b: Real) -> Real {
+    if cos_a > cos_b {
+        return 1.0;
+    }
+    let sin_a = (1.0 - cos_a * cos_a).max(0.0).sqrt();
+    let sin_b = (1.0 - cos_b * cos_b).max(0.0).sqrt();
+    cos_a * cos_b + sin_a * sin_b
+}
+
+// smallest cone containing two cones given by axis and cosine of spread angle
+fn union_cones(wa: Vec3, cos_a: Real, wb: Vec3, cos_b: Real) -> (Vec3, Real) {
+    let theta_a = cos_a.clamp(-1.0, 1.0).acos();
+    let theta_b = cos_b.clamp(-1.0, 1.0).acos();
+    let theta_d = wa.dot(wb).clamp(-1.0, 1.0).acos();
+    if (theta_d + theta_b).min(PI) <= theta_a {
+        return (wa, cos_a);
+    }
+    if (theta_d + theta_a).min(PI) <= theta_b {
+        return (wb, cos_b);
+    }
+
+    let theta_o = 0.5 * (theta_a + theta_d + theta_b);
+    let axis = wa.cross(wb);
+    if theta_o >= PI || axis.length2() == 0.0 {
+        return (wa, -1.0);
+    }
+
+    // rotate wa towards wb around their common normal
+    let theta_r = theta_o - theta_a;
+    let w = theta_r.cos() * wa + theta_r.sin() * axis.normalize().cross(wa);
+    (w.normalize(), theta_o.cos())
+}
+
+struct LightNode {
+    light_bounds: LightBounds,
+    offset: usize, // leaf: index of light, interior: index of second child
+    is_leaf: bool,
+}
+
+// NOTE: nodes are stored in depth-first order, so the first child is always next to its parent
+// lights are chosen by traversing tree in proportion to importance of each child, lights
+// without bounds such as environment light are chosen uniformly beside the tree
+pub struct BVHLightSampler {
+    nodes: Vec<LightNode>,
+    infinite_lights: Vec<usize>,
+    // path from root to leaf of each light, bit i is set if second child is taken at depth i
+    bit_trails: Vec<Option<u64>>,
+}
+
+impl BVHLightSampler {
+    pub fn new(lights: &[Box<dyn Light>]) -> Self {
+        let mut infinite_lights = Vec::new();
+        let mut bounded_lights = Vec::new();
+        for (light_idx, light) in lights.iter().enumerate() {
+            match light.bounds() {
+                // light which emits nothing is never chosen
+                Some(light_bounds) if light_bounds.phi > 0.0 => {
+                    bounded_lights.push((light_idx, light_bounds))
+                }
+                Some(_) => {}
+                None => infinite_lights.push(light_idx),
+            }
+        }
+
+        let mut nodes = Vec::new();
+        let mut bit_trails = vec![None; lights.len()];
+        if !bounded_lights.is_empty() {
+            build_node(&mut bounded_lights, 0, 0, &mut nodes, &mut bit_trails);
+        }
+
+        BVHLightSampler {
+            nodes,
+            infinite_lights,
+            bit_trails,
+        }
+    }
+
+    // probability of choosing infinite lights instead of traversing tree
+    fn p_infinite(&self) -> Real {
+        let n_infinite = self.infinite_lights.len();
+        n_infinite as Real / (n_infinite + usize::from(!self.nodes.is_empty())) as Real
+    }
+
+    // probability of choosing first child of interior node, none if both children are unimportant
+    fn p_first_child(&self, x: Vec3, node_idx: usize) -> Option<Real> {
+        let importance0 = self.nodes[node_idx + 1].light_bounds.importance(x);
+        let importance1 = self.nodes[self.nodes[node_idx].offset]
+            .light_bounds
+            .importance(x);
+        if importance0 == 0.0 && importance1 == 0.0 {
+            None
+        } else {
+            Some(importance0 / (importance0 + importance1))
+        }
+    }
+}
+
+// build subtree from lights by splitting at median of centroids along largest extent,
+// returns bounds of subtree
+fn build_node(
+    lights: &mut [(usize, LightBounds)],
+    bit_trail: u64,
+    depth: u32,
+    nodes: &mut Vec<LightNode>,
+    bit_trails: &mut [Option<u64>],
+) -> LightBounds {
+    if lights.len() == 1 {
+        let (light_idx, light_bounds) = lights[0];
+        nodes.push(LightNode {
+            light_bounds,
+            offset: light_idx,
+            is_leaf: true,
+        });
+        bit_trails[light_idx] = Some(bit_trail);
+        return light_bounds;
+    }
+
+    let centroid_bounds = lights
+        .iter()
+        .fold(Bounds3::empty(), |b, (_, light_bounds)| {
+            b.union_point(light_bounds.bounds.center())
+        });
+    let axis = centroid_bounds.max_extent();
+    let mid = lights.len() / 2;
+    lights.select_nth_unstable_by(mid, |(_, a), (_, b)| {
+        a.bounds.center()[axis].total_cmp(&b.bounds.center()[axis])
+    });
+
+    let node_idx = nodes.len();
+    nodes.push(LightNode {
+        light_bounds: lights[0].1,
+        offset: 0,
+        is_leaf: false,
+    });
+    let (first, second) = lights.split_at_mut(mid);
+    let bounds0 = build_node(first, bit_trail, depth + 1, nodes, bit_trails);
+    nodes[node_idx].offset = nodes.len();
+    let bounds1 = build_node(
+        second,
+        bit_trail | (1 << depth),
+        depth + 1,
+        nodes,
+        bit_trails,
+    );
+
+    let light_bounds = bounds0.union(&bounds1);
+    nodes[node_idx].light_bounds = light_bounds;
+    light_bounds
+}
+
+impl LightSampler for BVHLightSampler {
+    fn sample(&self, x: Vec3, u: Real) -> Option<(usize, Real)> {
+        let p_infinite = self.p_infinite();
+        if u < p_infinite {
+            let n_infinite = self.infinite_lights.len();
+            let idx = ((u / p_infinite * n_infinite as Real) as usize).min(n_infinite - 1);
+            return Some((self.infinite_lights[idx], p_infinite / n_infinite as Real));
+        }
+        if self.nodes.is_empty() {
+            return None;
+        }
+
+        // reuse u for choosing child at each level
+        let mut u = ((u - p_infinite) / (1.0 - p_infinite)).min(1.0 - Real::EPSILON);
+        let mut pmf = 1.0 - p_infinite;
+        let mut node_idx = 0;
+        loop {
+            let node = &self.nodes[node_idx];
+            if node.is_leaf {
+                // single light may still be unreachable from x
+                if node_idx == 0 && node.light_bounds.importance(x) == 0.0 {
+                    return None;
+                }
+                return Some((node.offset, pmf));
+            }
+
+            let p0 = self.p_first_child(x, node_idx)?;
+            if u < p0 {
+                node_idx += 1;
+                u = (u / p0).min(1.0 - Real::EPSILON);
+                pmf *= p0;
+            } else {
+                node_idx = node.offset;
+                u = ((u - p0) / (1.0 - p0)).min(1.0 - Real::EPSILON);
+                pmf *= 1.0 - p0;
+            }
+        }
+    }
+
+    fn pmf(&self, x: Vec3, light_idx: usize) -> Real {
+        let p_infinite = self.p_infinite();
+        if self.infinite_lights.contains(&light_idx) {
+            return p_infinite / self.infinite_lights.len() as Real;
+        }
+        let Some(bit_trail) = self.bit_trails.get(light_idx).copied().flatten() else {
+            return 0.0;
+        };
+
+        // follow path to leaf of light
+        let mut pmf = 1.0 - p_infinite;
+        let mut node_idx = 0;
+        let mut depth = 0;
+        loop {
+            let node = &self.nodes[node_idx];
+            if node.is_leaf {
+                if node_idx == 0 && node.light_bounds.importance(x) == 0.0 {
+                    return 0.0;
+                }
+                return pmf;
+            }
+
+            let Some(p0) = self.p_first_child(x, node_idx) else {
+                return 0.0;
+            };
+            if bit_trail & (1 << depth) == 0 {
+                node_idx += 1;
+                pmf *= p0;
+            } else {
+                node_idx = node.offset;
+                pmf *= 1.0 - p0;
+            }
+            depth += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::light::{PointLight, SpotLight};
+    use crate::light_sampler::*;
+    use crate::sampler::Sampler;
+
+    fn point_lights() -> Vec<Box<dyn Light>> {
+        (0..8)
+            .map(|i| {
+                let position = Vec3::new(4.0 * i as Real, 1.0, 0.0);
+                let intensity = Vec3::new(1.0, 1.0, 1.0) * (i + 1) as Real;
+                Box::new(PointLight::new(position, intensity)) as Box<dyn Light>
+            })
+            .collect()
+    }
+
+    // pmf sums to one and agrees with sample
+    fn check_pmf(light_sampler: &dyn LightSampler, x: Vec3, n_lights: usize) {
+        let sum: Real = (0..n_lights).map(|i| light_sampler.pmf(x, i)).sum();
+        assert!((sum - 1.0).abs() < 1E-4);
+
+        let mut sampler = Sampler::new(0);
+        for _ in 0..100 {
+            let (light_idx, pmf) = light_sampler.sample(x, sampler.next_1d()).unwrap();
+            assert!((pmf - light_sampler.pmf(x, light_idx)).abs() < 1E-5);
+        }
+    }
+
+    #[test]
+    fn uniform_and_power_light_samplers() {
+        let lights = point_lights();
+        let x = Vec3::new(0.0, 0.0, 0.0);
+
+        let uniform = UniformLightSampler::new(&lights);
+        check_pmf(&uniform, x, lights.len());
+        assert_eq!(uniform.pmf(x, 7), 0.125);
+
+        // brightest light is chosen 8 times as often as darkest one
+        let power = PowerLightSampler::new(&lights);
+        check_pmf(&power, x, lights.len());
+        assert!((power.pmf(x, 7) - 8.0 * power.pmf(x, 0)).abs() < 1E-6);
+
+        assert!(PowerLightSampler::new(&[]).sample(x, 0.5).is_none());
+    }
+
+    #[test]
+    fn bvh_light_sampler() {
+        let lights = point_lights();
+        let light_sampler = BVHLightSampler::new(&lights);
+
+        // nearby light dominates even though it is darkest
+        let x = Vec3::new(0.0, 0.0, 0.0);
+        check_pmf(&light_sampler, x, lights.len());
+        assert!(light_sampler.pmf(x, 0) > 0.5);
+        let x = Vec3::new(28.0, 0.0, 0.0);
+        check_pmf(&light_sampler, x, lights.len());
+        assert!(light_sampler.pmf(x, 7) > 0.5);
+
+        // spot light pointing away from x is never chosen
+        let mut lights = point_lights();
+        lights.push(Box::new(SpotLight::new(
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(100.0, 100.0, 100.0),
+            0.1,
+            0.2,
+        )));
+        let light_sampler = BVHLightSampler::new(&lights);
+        let x = Vec3::new(1.0, 0.0, 0.0);
+        check_pmf(&light_sampler, x, lights.len());
+        assert_eq!(light_sampler.pmf(x, 8), 0.0);
+    }
+
+    #[test]
+    fn light_cone_union() {
+        let (w, cos_theta) =
+            union_cones(Vec3::new(1.0, 0.0, 0.0), 1.0, Vec3::new(0.0, 1.0, 0.0), 1.0);
+        assert!((w - Vec3::new(1.0, 1.0, 0.0).normalize()).length() < 1E-5);
+        assert!((cos_theta - (PI / 4.0).cos()).abs() < 1E-5);
+
+        // cone containing the other is kept
+        let (w, cos_theta) = union_cones(
+            Vec3::new(0.0, 1.0, 0.0),
+            0.0,
+            Vec3::new(1.0, 1.0, 0.0).normalize(),
+            0.99,
+        );
+        assert_eq!((w, cos_theta), (Vec3::new(0.0, 1.0, 0.0), 0.0));
+    }
+}
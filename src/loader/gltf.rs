@@ -0,0 +1,605 @@
+use std::path::Path;
+
+use ::gltf::camera::Projection;
+use ::gltf::image::Format;
+use ::gltf::khr_lights_punctual::Kind;
+use ::gltf::mesh::Mode;
+use ::gltf::Node;
+
+use crate::bounds::Bounds3;
+use crate::camera::PinholeCamera;
+use crate::core::IntersectableLocal;
+use crate::image::Image;
+use crate::light::{DirectionalLight, Light, PointLight, SpotLight};
+use crate::loader::{fit_camera, LoadError};
+use crate::material::{Diffuse, Emissive, Material, NormalMapped, Principled};
+use crate::scene::Scene;
+use crate::shape::TriangleMesh;
+use crate::texture::{
+    ChannelTexture, ConstantTexture, ImageTexture, ScaledTexture, Texture, WrapMode,
+};
+use crate::transform::Transform;
+use crate::types::Real;
+use crate::vec2::Vec2;
+use crate::vec3::Vec3;
+
+use std::sync::Arc;
+
+// metallic-roughness material, textures are indices of GltfScene::images
+#[derive(Debug, PartialEq, Clone)]
+pub struct GltfMaterial {
+    pub name: String,
+    pub base_color: Vec3,
+    pub base_color_texture: Option<usize>,
+    pub metallic: Real,
+    pub roughness: Real,
+    pub metallic_roughness_texture: Option<usize>, // metallic in blue, roughness in green
+    pub transmission: Real,
+    pub ior: Real,
+    pub emissive: Vec3,
+    pub emissive_texture: Option<usize>,
+    pub normal_texture: Option<usize>,
+}
+
+impl GltfMaterial {
+    // metallic-roughness parameters are a subset of principled ones, images are indexed by
+    // texture indices
+    pub fn to_material(&self, images: &[Arc<Image>]) -> Arc<dyn Material> {
+        let image_texture =
+            |idx: usize| Arc::new(ImageTexture::new(images[idx].clone(), WrapMode::Repeat));
+        let base_color: Arc<dyn Texture<Vec3>> = match self.base_color_texture {
+            Some(idx) => Arc::new(ScaledTexture::new(image_texture(idx), self.base_color)),
+            None => Arc::new(ConstantTexture::new(self.base_color)),
+        };
+        // factors multiply metallic in blue and roughness in green channel of texture
+        let (metallic, roughness): (Arc<dyn Texture<Real>>, Arc<dyn Texture<Real>>) =
+            match self.metallic_roughness_texture {
+                Some(idx) => (
+                    Arc::new(ChannelTexture::new(image_texture(idx), 2, self.metallic)),
+                    Arc::new(ChannelTexture::new(image_texture(idx), 1, self.roughness)),
+                ),
+                None => (
+                    Arc::new(ConstantTexture::new(self.metallic)),
+                    Arc::new(ConstantTexture::new(self.roughness)),
+                ),
+            };
+
+        let material: Arc<dyn Material> = Arc::new(Principled {
+            specular: Principled::specular_from_ior(self.ior),
+            transmission: self.transmission,
+            ior: self.ior,
+            ..Principled::new(base_color, metallic, roughness)
+        });
+        let material: Arc<dyn Material> = match self.normal_texture {
+            Some(idx) => Arc::new(NormalMapped::new(material, image_texture(idx))),
+            None => material,
+        };
+
+        // emission is scaled by emissive texture when it exists
+        match self.emissive_texture {
+            Some(idx) => Arc::new(Emissive::new(
+                material,
+                Arc::new(ScaledTexture::new(image_texture(idx), self.emissive)),
+                true,
+            )),
+            None if self.emissive.max() > 0.0 => Arc::new(Emissive::new(
+                material,
+                Arc::new(ConstantTexture::new(self.emissive)),
+                true,
+            )),
+            None => material,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum GltfLightType {
+    Directional,
+    Point,
+    Spot {
+        inner_cone_angle: Real,
+        outer_cone_angle: Real,
+    },
+}
+
+// KHR_lights_punctual light placed in world space
+#[derive(Debug, PartialEq, Clone)]
+pub struct GltfLight {
+    pub light_type: GltfLightType,
+    pub position: Vec3,
+    pub direction: Vec3, // direction light travels
+    pub color: Vec3,
+    pub intensity: Real,
+    pub range: Option<Real>,
+}
+
+impl GltfLight {
+    // range is ignored, intensity falls off with inverse square of distance
+    pub fn to_light(&self) -> Box<dyn Light> {
+        let intensity = self.intensity * self.color;
+        match self.light_type {
+            GltfLightType::Directional => {
+                Box::new(DirectionalLight::new(-self.direction, intensity))
+            }
+            GltfLightType::Point => Box::new(PointLight::new(self.position, intensity)),
+            GltfLightType::Spot {
+                inner_cone_angle,
+                outer_cone_angle,
+            } => Box::new(SpotLight::new(
+                self.position,
+                self.direction,
+                intensity,
+                inner_cone_angle,
+                outer_cone_angle,
+            )),
+        }
+    }
+}
+
+pub struct GltfScene {
+    pub scene: Scene,
+    pub camera: PinholeCamera,
+    pub materials: Vec<GltfMaterial>,
+    pub lights: Vec<GltfLight>,
+    pub images: Vec<Arc<Image>>, // linear texels, color images are decoded from sRGB
+}
+
+// geometry and lights collected while traversing node hierarchy
+#[derive(Default)]
+struct SceneBuilder {
+    primitives: Vec<Box<dyn IntersectableLocal + Send + Sync>>,
+    material_indices: Vec<Option<usize>>,
+    bounds: Option<Bounds3>,
+    camera: Option<PinholeCamera>,
+    lights: Vec<GltfLight>,
+}
+
+pub fn load_gltf(path: &Path) -> Result<GltfScene, LoadError> {
+    let (document, buffers, images) = ::gltf::import(path).map_err(|source| LoadError::Gltf {
+        path: path.to_path_buf(),
+        source,
+    })?;
+
+    let materials: Vec<GltfMaterial> = document
+        .materials()
+        .map(|m| convert_material(&m, path))
+        .collect::<Result<_, _>>()?;
+
+    let mut builder = SceneBuilder::default();
+    let scene = document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+        .ok_or_else(|| LoadError::invalid(path, "no scene"))?;
+    for node in scene.nodes() {
+        visit_node(&node, &Transform::identity(), &buffers, &mut builder, path)?;
+    }
+
+    let camera = match builder.camera.take() {
+        Some(camera) => camera,
+        None => fit_camera(&builder.bounds.unwrap_or(Bounds3::empty())),
+    };
+
+    // primitives without material become gray diffuse
+    let default_material: Arc<dyn Material> = Arc::new(Diffuse::new(Arc::new(
+        ConstantTexture::new(Vec3::new(0.8, 0.8, 0.8)),
+    )));
+    // NOTE: image used both as color and as data is decoded as color
+    let images: Vec<Arc<Image>> = images
+        .iter()
+        .enumerate()
+        .map(|(idx, data)| {
+            let mut image = convert_image(data);
+            if materials
+                .iter()
+                .any(|m| m.base_color_texture == Some(idx) || m.emissive_texture == Some(idx))
+            {
+                image.srgb_to_linear();
+            }
+            Arc::new(image)
+        })
+        .collect();
+    let converted: Vec<Arc<dyn Material>> =
+        materials.iter().map(|m| m.to_material(&images)).collect();
+    let scene_materials: Vec<Arc<dyn Material>> = builder
+        .material_indices
+        .iter()
+        .map(|idx| match idx {
+            Some(idx) => converted[*idx].clone(),
+            None => default_material.clone(),
+        })
+        .collect();
+
+    let mut scene = Scene::new(builder.primitives, scene_materials);
+    for light in &builder.lights {
+        scene.add_light(light.to_light());
+    }
+
+    Ok(GltfScene {
+        scene,
+        camera,
+        materials,
+        lights: builder.lights,
+        images,
+    })
+}
+
+fn convert_material(material: &::gltf::Material, path: &Path) -> Result<GltfMaterial, LoadError> {
+    let pbr = material.pbr_metallic_roughness();
+    let base_color = pbr.base_color_factor();
+    let emissive = material.emissive_factor();
+    let emissive_strength = material.emissive_strength().unwrap_or(1.0);
+    let info_index = |info: ::gltf::texture::Info| {
+        if info.texture_transform().is_some() {
+            return Err(LoadError::invalid(
+                path,
+                "KHR_texture_transform is not supported",
+            ));
+        }
+        texture_index(&info.texture(), info.tex_coord(), path)
+    };
+    Ok(GltfMaterial {
+        name: material.name().unwrap_or("").to_string(),
+        base_color: Vec3::new(base_color[0], base_color[1], base_color[2]),
+        base_color_texture: pbr.base_color_texture().map(info_index).transpose()?,
+        metallic: pbr.metallic_factor(),
+        roughness: pbr.roughness_factor(),
+        metallic_roughness_texture: pbr
+            .metallic_roughness_texture()
+            .map(info_index)
+            .transpose()?,
+        transmission: material
+            .transmission()
+            .map_or(0.0, |t| t.transmission_factor()),
+        ior: material.ior().unwrap_or(1.5),
+        emissive: emissive_strength * Vec3::new(emissive[0], emissive[1], emissive[2]),
+        emissive_texture: material.emissive_texture().map(info_index).transpose()?,
+        normal_texture: material
+            .normal_texture()
+            .map(|normal| texture_index(&normal.texture(), normal.tex_coord(), path))
+            .transpose()?,
+    })
+}
+
+// returns image index of texture. only TEXCOORD_0 is loaded from primitives
+fn texture_index(
+    texture: &::gltf::Texture,
+    tex_coord: u32,
+    path: &Path,
+) -> Result<usize, LoadError> {
+    if tex_coord != 0 {
+        return Err(LoadError::invalid(
+            path,
+            format!(
+                "texture {} uses TEXCOORD_{}, only TEXCOORD_0 is supported",
+                texture.index(),
+                tex_coord
+            ),
+        ));
+    }
+    Ok(texture.source().index())
+}
+
+fn visit_node(
+    node: &Node,
+    parent_transform: &Transform,
+    buffers: &[::gltf::buffer::Data],
+    builder: &mut SceneBuilder,
+    path: &Path,
+) -> Result<(), LoadError> {
+    let transform = *parent_transform * Transform::from_cols(node.transform().matrix());
+
+    if let Some(mesh) = node.mesh() {
+        for primitive in mesh.primitives() {
+            add_primitive(&primitive, &transform, buffers, builder, path)?;
+        }
+    }
+
+    // cameras and lights look at -z in local space
+    let position = transform.transform_point(Vec3::new(0.0, 0.0, 0.0));
+    let direction = transform
+        .transform_vector(Vec3::new(0.0, 0.0, -1.0))
+        .normalize();
+
+    // first perspective camera is used
+    if let Some(camera) = node.camera() {
+        if let (Projection::Perspective(perspective), None) =
+            (camera.projection(), builder.camera.as_ref())
+        {
+            let up = transform.transform_vector(Vec3::new(0.0, 1.0, 0.0));
+            builder.camera = Some(PinholeCamera::new_with_up(
+                position,
+                direction,
+                up,
+                perspective.yfov(),
+            ));
+        }
+    }
+
+    if let Some(light) = node.light() {
+        let color = light.color();
+        let light_type = match light.kind() {
+            Kind::Directional => GltfLightType::Directional,
+            Kind::Point => GltfLightType::Point,
+            Kind::Spot {
+                inner_cone_angle,
+                outer_cone_angle,
+            } => GltfLightType::Spot {
+                inner_cone_angle,
+                outer_cone_angle,
+            },
+        };
+        builder.lights.push(GltfLight {
+            light_type,
+            position,
+            direction,
+            color: Vec3::new(color[0], color[1], color[2]),
+            intensity: light.intensity(),
+            range: light.range(),
+        });
+    }
+
+    for child in node.children() {
+        visit_node(&child, &transform, buffers, builder, path)?;
+    }
+    Ok(())
+}
+
+fn add_primitive(
+    primitive: &::gltf::Primitive,
+    transform: &Transform,
+    buffers: &[::gltf::buffer::Data],
+    builder: &mut SceneBuilder,
+    path: &Path,
+) -> Result<(), LoadError> {
+    let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+
+    let vertices: Vec<Vec3> = reader
+        .read_positions()
+        .ok_or_else(|| LoadError::invalid(path, "primitive without positions"))?
+        .map(|p| transform.transform_point(Vec3::new(p[0], p[1], p[2])))
+        .collect();
+    let normals: Option<Vec<Vec3>> = reader.read_normals().map(|normals| {
+        normals
+            .map(|n| transform.transform_normal(Vec3::new(n[0], n[1], n[2])))
+            .collect()
+    });
+    let texcoords: Option<Vec<Vec2>> = reader.read_tex_coords(0).map(|texcoords| {
+        texcoords
+            .into_f32()
+            // origin of glTF uv is top left corner of image
+            .map(|t| Vec2::new(t[0], 1.0 - t[1]))
+            .collect()
+    });
+    let indices: Vec<u32> = match reader.read_indices() {
+        Some(indices) => indices.into_u32().collect(),
+        None => (0..vertices.len() as u32).collect(),
+    };
+
+    let indices = match primitive.mode() {
+        Mode::Triangles => indices,
+        Mode::TriangleStrip => (2..indices.len())
+            .flat_map(|k| {
+                // keep winding order of odd triangles
+                if k % 2 == 0 {
+                    [indices[k - 2], indices[k - 1], indices[k]]
+                } else {
+                    [indices[k - 1], indices[k - 2], indices[k]]
+                }
+            })
+            .collect(),
+        Mode::TriangleFan => (2..indices.len())
+            .flat_map(|k| [indices[0], indices[k - 1], indices[k]])
+            .collect(),
+        // points and lines can not be rendered
+        _ => return Ok(()),
+    };
+    if indices.len() < 3 {
+        return Ok(());
+    }
+    let indices: Vec<u32> = indices[..indices.len() - indices.len() % 3].to_vec();
+    if let Some(&idx) = indices.iter().find(|&&idx| idx as usize >= vertices.len()) {
+        return Err(LoadError::invalid(
+            path,
+            format!("vertex index {} is out of range", idx),
+        ));
+    }
+
+    let bounds = Bounds3::from_points(&vertices);
+    builder.bounds = Some(match builder.bounds {
+        Some(b) => b.union(&bounds),
+        None => bounds,
+    });
+
+    let mesh = Arc::new(TriangleMesh::new(vertices, indices, normals, texcoords));
+    for triangle in TriangleMesh::triangles(&mesh) {
+        builder.primitives.push(Box::new(triangle));
+        builder.material_indices.push(primitive.material().index());
+    }
+    Ok(())
+}
+
+fn convert_image(data: &::gltf::image::Data) -> Image {
+    let (n_channels, bytes_per_channel) = match data.format {
+        Format::R8 => (1, 1),
+        Format::R8G8 => (2, 1),
+        Format::R8G8B8 => (3, 1),
+        Format::R8G8B8A8 => (4, 1),
+        Format::R16 => (1, 2),
+        Format::R16G16 => (2, 2),
+        Format::R16G16B16 => (3, 2),
+        Format::R16G16B16A16 => (4, 2),
+        Format::R32G32B32FLOAT => (3, 4),
+        Format::R32G32B32A32FLOAT => (4, 4),
+    };
+    let channel = |offset: usize| -> Real {
+        let bytes = &data.pixels[offset..offset + bytes_per_channel];
+        match bytes_per_channel {
+            1 => bytes[0] as Real / 255.0,
+            2 => u16::from_le_bytes([bytes[0], bytes[1]]) as Real / 65535.0,
+            _ => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+        }
+    };
+
+    let width = data.width as usize;
+    let height = data.height as usize;
+    let mut image = Image::new(width, height);
+    for i in 0..height {
+        for j in 0..width {
+            let base = (width * i + j) * n_channels * bytes_per_channel;
+            let r = channel(base);
+            let rgb = match n_channels {
+                1 => Vec3::new(r, r, r),
+                _ => Vec3::new(
+                    r,
+                    channel(base + bytes_per_channel),
+                    if n_channels > 2 {
+                        channel(base + 2 * bytes_per_channel)
+                    } else {
+                        0.0
+                    },
+                ),
+            };
+            image.set_pixel(i, j, rgb);
+        }
+    }
+    image
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::{IntersectableGlobal, Ray, ShadingInfo};
+    use crate::loader::gltf::*;
+    use crate::test_dir::TestDir;
+
+    // one triangle translated by (0, 0, -5), a camera and a point light
+    const TRIANGLE_GLTF: &str = r#"{
+        "asset": { "version": "2.0" },
+        "extensionsUsed": ["KHR_lights_punctual"],
+        "extensions": {
+            "KHR_lights_punctual": {
+                "lights": [{ "type": "point", "color": [1.0, 0.5, 0.5], "intensity": 10.0 }]
+            }
+        },
+        "scene": 0,
+        "scenes": [{ "nodes": [0, 1, 2] }],
+        "nodes": [
+            { "mesh": 0, "translation": [0.0, 0.0, -5.0] },
+            { "camera": 0 },
+            { "translation": [0.0, 3.0, 0.0], "extensions": { "KHR_lights_punctual": { "light": 0 } } }
+        ],
+        "cameras": [{ "type": "perspective", "perspective": { "yfov": 0.8, "znear": 0.1 } }],
+        "materials": [{
+            "name": "red",
+            "pbrMetallicRoughness": { "baseColorFactor": [1.0, 0.0, 0.0, 1.0], "metallicFactor": 0.0 }
+        }],
+        "meshes": [{ "primitives": [{ "attributes": { "POSITION": 0 }, "material": 0 }] }],
+        "accessors": [{
+            "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+            "min": [0.0, 0.0, 0.0], "max": [1.0, 1.0, 0.0]
+        }],
+        "bufferViews": [{ "buffer": 0, "byteLength": 36 }],
+        "buffers": [{
+            "byteLength": 36,
+            "uri": "data:application/octet-stream;base64,AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAgD8AAAAA"
+        }]
+    }"#;
+
+    #[test]
+    fn load_triangle_gltf() {
+        let dir = TestDir::new("load_triangle_gltf");
+        let path = dir.join("triangle.gltf");
+        std::fs::write(&path, TRIANGLE_GLTF).unwrap();
+        let gltf_scene = load_gltf(&path).unwrap();
+
+        assert_eq!(gltf_scene.materials.len(), 1);
+        assert_eq!(gltf_scene.materials[0].name, "red");
+        assert_eq!(gltf_scene.materials[0].base_color, Vec3::new(1.0, 0.0, 0.0));
+
+        let ray = Ray::new(Vec3::new(0.25, 0.25, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let info = gltf_scene.scene.intersect(&ray).unwrap();
+        assert_eq!(info.t, 5.0);
+
+        assert_eq!(gltf_scene.lights.len(), 1);
+        assert_eq!(gltf_scene.lights[0].light_type, GltfLightType::Point);
+        assert_eq!(gltf_scene.lights[0].position, Vec3::new(0.0, 3.0, 0.0));
+        assert_eq!(gltf_scene.lights[0].intensity, 10.0);
+        assert_eq!(gltf_scene.scene.n_lights(), 1);
+    }
+
+    #[test]
+    fn metallic_roughness_texture() {
+        // metallic in blue and roughness in green are multiplied by factors
+        let mut image = Image::new(1, 1);
+        image.set_pixel(0, 0, Vec3::new(0.0, 0.25, 0.5));
+        let images = [Arc::new(image)];
+        let material = |metallic: Real, roughness: Real, texture: Option<usize>| {
+            GltfMaterial {
+                name: String::new(),
+                base_color: Vec3::new(0.8, 0.5, 0.2),
+                base_color_texture: None,
+                metallic,
+                roughness,
+                metallic_roughness_texture: texture,
+                transmission: 0.0,
+                ior: 1.5,
+                emissive: Vec3::new(0.0, 0.0, 0.0),
+                emissive_texture: None,
+                normal_texture: None,
+            }
+            .to_material(&images)
+        };
+
+        let info = ShadingInfo {
+            x: Vec3::new(0.0, 0.0, 0.0),
+            n: Vec3::new(0.0, 1.0, 0.0),
+            wo: Vec3::new(0.0, 1.0, 0.0),
+            t: Vec3::new(1.0, 0.0, 0.0),
+            b: Vec3::new(0.0, 0.0, 1.0),
+            uv: Vec2::new(0.5, 0.5),
+        };
+        let wo = Vec3::new(0.0, 0.8, 0.6);
+        let wi = Vec3::new(0.0, 0.8, -0.6);
+        let textured = material(1.0, 0.8, Some(0)).get_bsdf(&info).eval(wo, wi);
+        let constant = material(0.5, 0.2, None).get_bsdf(&info).eval(wo, wi);
+        assert!((textured - constant).length() < 1E-5 * constant.length());
+    }
+
+    #[test]
+    fn unsupported_texture_binding() {
+        // 1x1 png as base color texture of triangle with given texture info
+        let textured = |info: &str| {
+            TRIANGLE_GLTF
+                .replace(
+                    r#""metallicFactor": 0.0 }"#,
+                    &format!(r#""metallicFactor": 0.0, "baseColorTexture": {} }}"#, info),
+                )
+                .replace(
+                    r#""meshes""#,
+                    r#""textures": [{ "source": 0 }],
+        "images": [{ "uri": "data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAIAAACQd1PeAAAADElEQVR4nGP4z8AAAAMBAQDJ/pLvAAAAAElFTkSuQmCC" }],
+        "meshes""#,
+                )
+        };
+        let dir = TestDir::new("unsupported_texture_binding");
+        let path = dir.join("textured.gltf");
+
+        std::fs::write(&path, textured(r#"{ "index": 0 }"#)).unwrap();
+        let gltf_scene = load_gltf(&path).unwrap();
+        assert_eq!(gltf_scene.materials[0].base_color_texture, Some(0));
+
+        for info in [
+            r#"{ "index": 0, "texCoord": 1 }"#,
+            r#"{ "index": 0, "extensions": { "KHR_texture_transform": { "scale": [2.0, 2.0] } } }"#,
+        ] {
+            std::fs::write(&path, textured(info)).unwrap();
+            assert!(matches!(load_gltf(&path), Err(LoadError::Invalid { .. })));
+        }
+    }
+
+    #[test]
+    fn load_missing_gltf() {
+        let dir = TestDir::new("load_missing_gltf");
+        let path = dir.join("missing.gltf");
+        assert!(matches!(load_gltf(&path), Err(LoadError::Gltf { .. })));
+    }
+}
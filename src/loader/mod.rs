@@ -0,0 +1,171 @@
+pub mod gltf;
+pub mod obj;
+pub mod scene_file;
+
+use std::error::Error;
+use std::f32::consts::FRAC_PI_4;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::bounds::Bounds3;
+use crate::camera::PinholeCamera;
+use crate::integrator::PathTracingIntegrator;
+use crate::loader::gltf::load_gltf;
+use crate::loader::obj::load_obj_scene;
+use crate::loader::scene_file::{load_scene_file, FilmSettings, SamplerSettings, SceneDescription};
+use crate::vec3::Vec3;
+
+#[derive(Debug)]
+pub enum LoadError {
+    // file could not be read
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    // file content is malformed
+    Parse {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+    // file content is inconsistent
+    Invalid {
+        path: PathBuf,
+        message: String,
+    },
+    // glTF file could not be imported
+    Gltf {
+        path: PathBuf,
+        source: ::gltf::Error,
+    },
+    // image file could not be decoded
+    Image {
+        path: PathBuf,
+        source: ::image::ImageError,
+    },
+}
+
+impl LoadError {
+    pub fn parse(path: &Path, line: usize, message: impl Into<String>) -> Self {
+        LoadError::Parse {
+            path: path.to_path_buf(),
+            line,
+            message: message.into(),
+        }
+    }
+
+    pub fn invalid(path: &Path, message: impl Into<String>) -> Self {
+        LoadError::Invalid {
+            path: path.to_path_buf(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Io { path, source } => {
+                write!(f, "couldn't read {}: {}", path.display(), source)
+            }
+            LoadError::Parse {
+                path,
+                line,
+                message,
+            } => write!(f, "{}:{}: {}", path.display(), line, message),
+            LoadError::Invalid { path, message } => write!(f, "{}: {}", path.display(), message),
+            LoadError::Gltf { path, source } => {
+                write!(f, "couldn't import {}: {}", path.display(), source)
+            }
+            LoadError::Image { path, source } => {
+                write!(f, "couldn't decode {}: {}", path.display(), source)
+            }
+        }
+    }
+}
+
+impl Error for LoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            LoadError::Io { source, .. } => Some(source),
+            LoadError::Parse { .. } | LoadError::Invalid { .. } => None,
+            LoadError::Gltf { source, .. } => Some(source),
+            LoadError::Image { source, .. } => Some(source),
+        }
+    }
+}
+
+pub(crate) fn read_to_string(path: &Path) -> Result<String, LoadError> {
+    std::fs::read_to_string(path).map_err(|source| LoadError::Io {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+// loads scene file, glTF or Wavefront OBJ by extension. glTF and OBJ are rendered with
+// default settings
+pub fn load_scene(path: &Path) -> Result<SceneDescription, LoadError> {
+    let default_description = |camera, scene| SceneDescription {
+        film: FilmSettings::default(),
+        sampler: SamplerSettings::default(),
+        camera: Box::new(camera),
+        scene,
+        integrator: Box::new(PathTracingIntegrator::new(100)),
+    };
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("gltf") | Some("glb") => {
+            let gltf_scene = load_gltf(path)?;
+            Ok(default_description(gltf_scene.camera, gltf_scene.scene))
+        }
+        Some("obj") => {
+            let scene = load_obj_scene(path)?;
+            Ok(default_description(fit_camera(&scene.bounds()), scene))
+        }
+        _ => load_scene_file(path),
+    }
+}
+
+// camera looking at the whole scene along -z, empty scene is taken as unit cube
+pub fn fit_camera(bounds: &Bounds3) -> PinholeCamera {
+    let bounds = if bounds.is_empty() {
+        Bounds3::new(Vec3::new(-1.0, -1.0, -1.0), Vec3::new(1.0, 1.0, 1.0))
+    } else {
+        *bounds
+    };
+    let fov = FRAC_PI_4;
+    let radius = 0.5 * bounds.diagonal().length();
+    let distance = radius / (0.5 * fov).sin();
+    PinholeCamera::new(
+        bounds.center() + Vec3::new(0.0, 0.0, distance),
+        Vec3::new(0.0, 0.0, -1.0),
+        fov,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::IntersectableGlobal;
+    use crate::loader::*;
+    use crate::sampler::Sampler;
+    use crate::test_dir::TestDir;
+    use crate::vec2::Vec2;
+
+    #[test]
+    fn load_obj_with_camera() {
+        let dir = TestDir::new("load_obj_with_camera");
+        let path = dir.join("quad.obj");
+        std::fs::write(&path, "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nf 1 2 3 4\n").unwrap();
+        let desc = load_scene(&path).unwrap();
+
+        // camera looks at center of quad
+        let ray = desc
+            .camera
+            .sample_ray(Vec2::new(0.0, 0.0), &mut Sampler::new(0));
+        let info = desc.scene.intersect(&ray).unwrap();
+        assert!((info.pos - Vec3::new(0.5, 0.5, 0.0)).length() < 1E-4);
+
+        let broken = dir.join("broken.obj");
+        std::fs::write(&broken, "f 1 2 3\n").unwrap();
+        assert!(matches!(load_scene(&broken), Err(LoadError::Parse { .. })));
+    }
+}
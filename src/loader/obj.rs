@@ -0,0 +1,615 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::core::IntersectableLocal;
+use crate::image::{ColorEncoding, Image};
+use crate::loader::{read_to_string, LoadError};
+use crate::material::{Diffuse, Emissive, Glass, Material, Plastic, Principled};
+use crate::scene::Scene;
+use crate::shape::TriangleMesh;
+use crate::texture::{ConstantTexture, ImageTexture, ScaledTexture, Texture, WrapMode};
+use crate::types::Real;
+use crate::vec2::Vec2;
+use crate::vec3::Vec3;
+
+// material parsed from .mtl
+#[derive(Debug, PartialEq, Clone)]
+pub struct ObjMaterial {
+    pub name: String,
+    pub kd: Vec3,                // diffuse color
+    pub ks: Vec3,                // specular color
+    pub ke: Vec3,                // emission
+    pub ns: Real,                // specular exponent
+    pub ni: Real,                // index of refraction
+    pub d: Real,                 // opacity
+    pub pr: Option<Real>,        // roughness of PBR extension
+    pub pm: Option<Real>,        // metallic of PBR extension
+    pub ps: Real,                // sheen of PBR extension
+    pub pc: Real,                // clearcoat of PBR extension
+    pub pcr: Real,               // clearcoat roughness of PBR extension
+    pub map_kd: Option<PathBuf>, // diffuse texture
+    pub map_ke: Option<PathBuf>, // emission texture
+}
+
+impl ObjMaterial {
+    pub fn new(name: &str) -> Self {
+        ObjMaterial {
+            name: name.to_string(),
+            kd: Vec3::new(0.8, 0.8, 0.8),
+            ks: Vec3::new(0.0, 0.0, 0.0),
+            ke: Vec3::new(0.0, 0.0, 0.0),
+            ns: 0.0,
+            ni: 1.0,
+            d: 1.0,
+            pr: None,
+            pm: None,
+            ps: 0.0,
+            pc: 0.0,
+            pcr: 0.0,
+            map_kd: None,
+            map_ke: None,
+        }
+    }
+
+    // materials with PBR extension become principled, transparent materials become glass
+    // and materials with Ks become plastic. map_Kd and map_Ke are multiplied by Kd and Ke
+    pub fn to_material(&self) -> Result<Arc<dyn Material>, LoadError> {
+        let kd = color_texture(&self.map_kd, self.kd)?;
+        let ior = if self.ni > 1.0 { self.ni } else { 1.5 };
+        let material: Arc<dyn Material> = if self.pr.is_some() || self.pm.is_some() {
+            Arc::new(Principled {
+                specular: Principled::specular_from_ior(ior),
+                sheen: self.ps,
+                clearcoat: self.pc,
+                clearcoat_gloss: 1.0 - self.pcr,
+                transmission: 1.0 - self.d,
+                ior,
+                ..Principled::new(
+                    kd,
+                    Arc::new(ConstantTexture::new(self.pm.unwrap_or(0.0))),
+                    Arc::new(ConstantTexture::new(self.pr.unwrap_or(0.5))),
+                )
+            })
+        } else if self.d < 1.0 {
+            Arc::new(Glass::new(ior))
+        } else if self.ks.max() > 0.0 {
+            // convert phong exponent to microfacet roughness, alpha is squared roughness
+            let alpha = (2.0 / (self.ns + 2.0)).sqrt();
+            Arc::new(Plastic::new(
+                kd,
+                Arc::new(ConstantTexture::new(self.ks)),
+                Arc::new(ConstantTexture::new(alpha.sqrt())),
+            ))
+        } else {
+            Arc::new(Diffuse::new(kd))
+        };
+
+        if self.ke.max() > 0.0 {
+            Ok(Arc::new(Emissive::new(
+                material,
+                color_texture(&self.map_ke, self.ke)?,
+                true,
+            )))
+        } else {
+            Ok(material)
+        }
+    }
+}
+
+// image texture scaled by color if given, otherwise the constant color
+fn color_texture(path: &Option<PathBuf>, color: Vec3) -> Result<Arc<dyn Texture<Vec3>>, LoadError> {
+    match path {
+        Some(path) => {
+            let image = Arc::new(Image::load(path, ColorEncoding::SRGB)?);
+            Ok(Arc::new(ScaledTexture::new(
+                Arc::new(ImageTexture::new(image, WrapMode::Repeat)),
+                color,
+            )))
+        }
+        None => Ok(Arc::new(ConstantTexture::new(color))),
+    }
+}
+
+pub struct ObjMesh {
+    pub name: String,            // group name
+    pub material: Option<usize>, // index of material in ObjModel::materials
+    pub mesh: Arc<TriangleMesh>,
+}
+
+pub struct ObjModel {
+    pub meshes: Vec<ObjMesh>,
+    pub materials: Vec<ObjMaterial>,
+}
+
+impl ObjModel {
+    // convert every material once so that its textures are shared by meshes
+    pub fn build_materials(&self) -> Result<Vec<Arc<dyn Material>>, LoadError> {
+        self.materials.iter().map(|m| m.to_material()).collect()
+    }
+
+    // register every triangle as a primitive, faces without material become gray diffuse
+    pub fn build_scene(&self) -> Result<Scene, LoadError> {
+        let default_material = ObjMaterial::new("default").to_material()?;
+        let converted = self.build_materials()?;
+
+        let mut primitives: Vec<Box<dyn IntersectableLocal + Send + Sync>> = Vec::new();
+        let mut materials: Vec<Arc<dyn Material>> = Vec::new();
+        for mesh in &self.meshes {
+            let material = match mesh.material {
+                Some(idx) => converted[idx].clone(),
+                None => default_material.clone(),
+            };
+            for triangle in TriangleMesh::triangles(&mesh.mesh) {
+                primitives.push(Box::new(triangle));
+                materials.push(material.clone());
+            }
+        }
+
+        Ok(Scene::new(primitives, materials))
+    }
+}
+
+pub fn load_obj(path: &Path) -> Result<ObjModel, LoadError> {
+    parse_obj(&read_to_string(path)?, path)
+}
+
+pub fn load_obj_scene(path: &Path) -> Result<Scene, LoadError> {
+    load_obj(path)?.build_scene()
+}
+
+pub fn load_mtl(path: &Path) -> Result<Vec<ObjMaterial>, LoadError> {
+    parse_mtl(&read_to_string(path)?, path)
+}
+
+// faces sharing the same group and material
+struct MeshBuilder {
+    name: String,
+    material: Option<usize>,
+    vertices: Vec<Vec3>,
+    normals: Vec<Vec3>,
+    texcoords: Vec<Vec2>,
+    indices: Vec<u32>,
+    has_normals: bool,
+    has_texcoords: bool,
+    // (position, texcoord, normal) index to mesh vertex index
+    vertex_map: HashMap<(usize, Option<usize>, Option<usize>), u32>,
+}
+
+impl MeshBuilder {
+    fn new(name: &str, material: Option<usize>) -> Self {
+        MeshBuilder {
+            name: name.to_string(),
+            material,
+            vertices: Vec::new(),
+            normals: Vec::new(),
+            texcoords: Vec::new(),
+            indices: Vec::new(),
+            has_normals: true,
+            has_texcoords: true,
+            vertex_map: HashMap::new(),
+        }
+    }
+
+    fn add_vertex(
+        &mut self,
+        key: (usize, Option<usize>, Option<usize>),
+        positions: &[Vec3],
+        texcoords: &[Vec2],
+        normals: &[Vec3],
+    ) -> u32 {
+        if let Some(idx) = self.vertex_map.get(&key) {
+            return *idx;
+        }
+
+        let idx = self.vertices.len() as u32;
+        let (p, vt, vn) = key;
+        self.vertices.push(positions[p]);
+        match vt {
+            Some(vt) => self.texcoords.push(texcoords[vt]),
+            None => {
+                self.has_texcoords = false;
+                self.texcoords.push(Vec2::new(0.0, 0.0));
+            }
+        }
+        match vn {
+            Some(vn) => self.normals.push(normals[vn]),
+            None => {
+                self.has_normals = false;
+                self.normals.push(Vec3::new(0.0, 0.0, 0.0));
+            }
+        }
+        self.vertex_map.insert(key, idx);
+        idx
+    }
+
+    fn build(self) -> Option<ObjMesh> {
+        if self.indices.is_empty() {
+            return None;
+        }
+
+        let normals = if self.has_normals {
+            Some(self.normals)
+        } else {
+            None
+        };
+        let texcoords = if self.has_texcoords {
+            Some(self.texcoords)
+        } else {
+            None
+        };
+        Some(ObjMesh {
+            name: self.name,
+            material: self.material,
+            mesh: Arc::new(TriangleMesh::new(
+                self.vertices,
+                self.indices,
+                normals,
+                texcoords,
+            )),
+        })
+    }
+}
+
+fn parse_reals(
+    args: &[&str],
+    n_min: usize,
+    n_max: usize,
+    path: &Path,
+    line: usize,
+) -> Result<Vec<Real>, LoadError> {
+    if args.len() < n_min || args.len() > n_max {
+        return Err(LoadError::parse(
+            path,
+            line,
+            format!(
+                "expected {} to {} numbers, found {}",
+                n_min,
+                n_max,
+                args.len()
+            ),
+        ));
+    }
+    args.iter()
+        .map(|arg| {
+            arg.parse::<Real>()
+                .map_err(|_| LoadError::parse(path, line, format!("invalid number '{}'", arg)))
+        })
+        .collect()
+}
+
+fn parse_vec3(args: &[&str], path: &Path, line: usize) -> Result<Vec3, LoadError> {
+    let v = parse_reals(args, 3, 3, path, line)?;
+    Ok(Vec3::new(v[0], v[1], v[2]))
+}
+
+fn parse_real(args: &[&str], path: &Path, line: usize) -> Result<Real, LoadError> {
+    Ok(parse_reals(args, 1, 1, path, line)?[0])
+}
+
+// convert 1-based (or negative relative) index to 0-based index
+fn resolve_index(token: &str, count: usize, path: &Path, line: usize) -> Result<usize, LoadError> {
+    let idx: i64 = token
+        .parse()
+        .map_err(|_| LoadError::parse(path, line, format!("invalid index '{}'", token)))?;
+    let resolved = if idx > 0 { idx - 1 } else { count as i64 + idx };
+    if idx == 0 || resolved < 0 || resolved >= count as i64 {
+        return Err(LoadError::parse(
+            path,
+            line,
+            format!("index {} is out of range", idx),
+        ));
+    }
+    Ok(resolved as usize)
+}
+
+pub fn parse_obj(source: &str, path: &Path) -> Result<ObjModel, LoadError> {
+    let mut positions: Vec<Vec3> = Vec::new();
+    let mut texcoords: Vec<Vec2> = Vec::new();
+    let mut normals: Vec<Vec3> = Vec::new();
+    let mut materials: Vec<ObjMaterial> = Vec::new();
+    let mut meshes: Vec<ObjMesh> = Vec::new();
+
+    let mut builder = MeshBuilder::new("default", None);
+    for (line_idx, line) in source.lines().enumerate() {
+        let line_number = line_idx + 1;
+        let line = line.split('#').next().unwrap_or("");
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let (keyword, args) = match tokens.split_first() {
+            Some((keyword, args)) => (*keyword, args),
+            None => continue,
+        };
+
+        match keyword {
+            "v" => {
+                // optional vertex color is ignored
+                let v = parse_reals(args, 3, 6, path, line_number)?;
+                positions.push(Vec3::new(v[0], v[1], v[2]));
+            }
+            "vt" => {
+                let v = parse_reals(args, 1, 3, path, line_number)?;
+                texcoords.push(Vec2::new(v[0], *v.get(1).unwrap_or(&0.0)));
+            }
+            "vn" => normals.push(parse_vec3(args, path, line_number)?),
+            "f" => {
+                if args.len() < 3 {
+                    return Err(LoadError::parse(
+                        path,
+                        line_number,
+                        "face needs at least 3 vertices",
+                    ));
+                }
+
+                let mut face = Vec::with_capacity(args.len());
+                for arg in args {
+                    let refs: Vec<&str> = arg.split('/').collect();
+                    if refs.len() > 3 {
+                        return Err(LoadError::parse(
+                            path,
+                            line_number,
+                            format!("invalid face vertex '{}'", arg),
+                        ));
+                    }
+                    let p = resolve_index(refs[0], positions.len(), path, line_number)?;
+                    let vt = match refs.get(1) {
+                        Some(token) if !token.is_empty() => {
+                            Some(resolve_index(token, texcoords.len(), path, line_number)?)
+                        }
+                        _ => None,
+                    };
+                    let vn = match refs.get(2) {
+                        Some(token) if !token.is_empty() => {
+                            Some(resolve_index(token, normals.len(), path, line_number)?)
+                        }
+                        _ => None,
+                    };
+                    face.push(builder.add_vertex((p, vt, vn), &positions, &texcoords, &normals));
+                }
+
+                // triangulate polygon as a fan
+                for k in 1..face.len() - 1 {
+                    builder
+                        .indices
+                        .extend_from_slice(&[face[0], face[k], face[k + 1]]);
+                }
+            }
+            "g" | "o" => {
+                let name = args.join(" ");
+                let material = builder.material;
+                if let Some(mesh) =
+                    std::mem::replace(&mut builder, MeshBuilder::new(&name, material)).build()
+                {
+                    meshes.push(mesh);
+                }
+            }
+            "usemtl" => {
+                let name = args.join(" ");
+                let material = materials
+                    .iter()
+                    .position(|m| m.name == name)
+                    .ok_or_else(|| {
+                        LoadError::parse(path, line_number, format!("unknown material '{}'", name))
+                    })?;
+                let group = builder.name.clone();
+                if let Some(mesh) =
+                    std::mem::replace(&mut builder, MeshBuilder::new(&group, Some(material)))
+                        .build()
+                {
+                    meshes.push(mesh);
+                }
+            }
+            "mtllib" => {
+                if args.is_empty() {
+                    return Err(LoadError::parse(path, line_number, "missing .mtl filename"));
+                }
+                let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+                for filename in args {
+                    materials.extend(load_mtl(&base_dir.join(filename))?);
+                }
+            }
+            // smoothing groups, lines, points and free-form geometry are not supported
+            _ => {}
+        }
+    }
+    if let Some(mesh) = builder.build() {
+        meshes.push(mesh);
+    }
+
+    Ok(ObjModel { meshes, materials })
+}
+
+pub fn parse_mtl(source: &str, path: &Path) -> Result<Vec<ObjMaterial>, LoadError> {
+    let mut materials: Vec<ObjMaterial> = Vec::new();
+    for (line_idx, line) in source.lines().enumerate() {
+        let line_number = line_idx + 1;
+        let line = line.split('#').next().unwrap_or("");
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let (keyword, args) = match tokens.split_first() {
+            Some((keyword, args)) => (*keyword, args),
+            None => continue,
+        };
+
+        if keyword == "newmtl" {
+            if args.is_empty() {
+                return Err(LoadError::parse(path, line_number, "missing material name"));
+            }
+            materials.push(ObjMaterial::new(&args.join(" ")));
+            continue;
+        }
+
+        let material = materials.last_mut().ok_or_else(|| {
+            LoadError::parse(
+                path,
+                line_number,
+                format!("'{}' appears before newmtl", keyword),
+            )
+        })?;
+        match keyword {
+            "Kd" => material.kd = parse_vec3(args, path, line_number)?,
+            "Ks" => material.ks = parse_vec3(args, path, line_number)?,
+            "Ke" => material.ke = parse_vec3(args, path, line_number)?,
+            "Ns" => material.ns = parse_real(args, path, line_number)?,
+            "Ni" => material.ni = parse_real(args, path, line_number)?,
+            "d" => material.d = parse_real(args, path, line_number)?,
+            "Tr" => material.d = 1.0 - parse_real(args, path, line_number)?,
+            "Pr" => material.pr = Some(parse_real(args, path, line_number)?),
+            "Pm" => material.pm = Some(parse_real(args, path, line_number)?),
+            "Ps" => material.ps = parse_real(args, path, line_number)?,
+            "Pc" => material.pc = parse_real(args, path, line_number)?,
+            "Pcr" => material.pcr = parse_real(args, path, line_number)?,
+            "map_Kd" | "map_Ke" => {
+                // texture options are not supported, filename is the last argument
+                let filename = args.last().ok_or_else(|| {
+                    LoadError::parse(path, line_number, "missing texture filename")
+                })?;
+                let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+                let texture = Some(base_dir.join(filename));
+                if keyword == "map_Kd" {
+                    material.map_kd = texture;
+                } else {
+                    material.map_ke = texture;
+                }
+            }
+            // other parameters are not used
+            _ => {}
+        }
+    }
+    Ok(materials)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::loader::obj::*;
+    use crate::test_dir::TestDir;
+
+    #[test]
+    fn parse_obj_quad() {
+        let source = "
+            # quad
+            v -1 0 1
+            v 1 0 1
+            v 1 0 -1
+            v -1 0 -1
+            vn 0 1 0
+            g quad
+            f 1//1 2//1 3//1 4//1
+        ";
+        let model = parse_obj(source, Path::new("quad.obj")).unwrap();
+        assert_eq!(model.meshes.len(), 1);
+        assert_eq!(model.meshes[0].name, "quad");
+        assert_eq!(model.meshes[0].mesh.n_faces(), 2);
+        assert_eq!(
+            model.meshes[0].mesh.get_normals(1),
+            Some([Vec3::new(0.0, 1.0, 0.0); 3])
+        );
+        assert_eq!(model.meshes[0].mesh.get_texcoords(0), None);
+    }
+
+    #[test]
+    fn parse_obj_negative_index() {
+        let source = "
+            v 0 0 0
+            v 1 0 0
+            v 0 1 0
+            vt 0 0
+            vt 1 0
+            vt 0 1
+            f -3/-3 -2/-2 -1/-1
+        ";
+        let model = parse_obj(source, Path::new("triangle.obj")).unwrap();
+        let mesh = &model.meshes[0].mesh;
+        assert_eq!(
+            mesh.get_vertices(0),
+            [
+                Vec3::new(0.0, 0.0, 0.0),
+                Vec3::new(1.0, 0.0, 0.0),
+                Vec3::new(0.0, 1.0, 0.0)
+            ]
+        );
+        assert_eq!(
+            mesh.get_texcoords(0),
+            Some([
+                Vec2::new(0.0, 0.0),
+                Vec2::new(1.0, 0.0),
+                Vec2::new(0.0, 1.0)
+            ])
+        );
+    }
+
+    #[test]
+    fn parse_obj_error_line() {
+        let source = "v 0 0 0\nv 1 0 0\nv 0 1 x\n";
+        match parse_obj(source, Path::new("broken.obj")) {
+            Err(LoadError::Parse { line, .. }) => assert_eq!(line, 3),
+            _ => panic!("expected parse error"),
+        }
+
+        let source = "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 4\n";
+        match parse_obj(source, Path::new("broken.obj")) {
+            Err(LoadError::Parse { line, .. }) => assert_eq!(line, 4),
+            _ => panic!("expected parse error"),
+        }
+    }
+
+    #[test]
+    fn parse_mtl_materials() {
+        let source = "
+            newmtl light
+            Kd 0.5 0.5 0.5
+            Ke 10 10 10
+            Ns 20
+            Ni 1.5
+            d 0.5
+            map_Kd -s 1 1 1 textures/light.png
+            map_Ke textures/glow.png
+            Pr 0.3
+            Pc 1
+        ";
+        let materials = parse_mtl(source, Path::new("models/scene.mtl")).unwrap();
+        assert_eq!(materials.len(), 1);
+        assert_eq!(materials[0].name, "light");
+        assert_eq!(materials[0].kd, Vec3::new(0.5, 0.5, 0.5));
+        assert_eq!(materials[0].ke, Vec3::new(10.0, 10.0, 10.0));
+        assert_eq!(materials[0].ns, 20.0);
+        assert_eq!(materials[0].ni, 1.5);
+        assert_eq!(materials[0].d, 0.5);
+        assert_eq!(materials[0].pr, Some(0.3));
+        assert_eq!(materials[0].pm, None);
+        assert_eq!(materials[0].pc, 1.0);
+        assert_eq!(
+            materials[0].map_kd,
+            Some(PathBuf::from("models/textures/light.png"))
+        );
+        assert_eq!(
+            materials[0].map_ke,
+            Some(PathBuf::from("models/textures/glow.png"))
+        );
+
+        match parse_mtl("Kd 1 1 1\n", Path::new("broken.mtl")) {
+            Err(LoadError::Parse { line, .. }) => assert_eq!(line, 1),
+            _ => panic!("expected parse error"),
+        }
+    }
+
+    #[test]
+    fn material_with_texture() {
+        let dir = TestDir::new("material_with_texture");
+        let path = dir.join("diffuse.png");
+        ::image::RgbImage::from_raw(1, 1, vec![255, 255, 255])
+            .unwrap()
+            .save(&path)
+            .unwrap();
+
+        let mut material = ObjMaterial::new("textured");
+        material.map_kd = Some(path.clone());
+        assert!(material.to_material().is_ok());
+
+        // textured emission
+        material.ke = Vec3::new(2.0, 2.0, 2.0);
+        material.map_ke = Some(path);
+        assert!(material.to_material().unwrap().is_emissive());
+
+        material.map_kd = Some(dir.join("missing.png"));
+        assert!(matches!(material.to_material(), Err(LoadError::Io { .. })));
+    }
+}
@@ -0,0 +1,1537 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use serde::Deserialize;
+
+use crate::camera::{Camera, PinholeCamera};
+use crate::core::IntersectableLocal;
+use crate::image::{ColorEncoding, Image};
+use crate::integrator::{Integrator, NormalIntegrator, PathTracingIntegrator};
+use crate::intersector::IntersectorType;
+use crate::light::{DirectionalLight, EnvironmentLight, Light, PointLight, SpotLight, SunLight};
+use crate::light_sampler::LightSamplerType;
+use crate::loader::obj::load_obj;
+use crate::loader::{read_to_string, LoadError};
+use crate::material::{
+    BumpMapped, Conductor, Diffuse, Emissive, Glass, Material, Mirror, Mix, NormalMapped, Plastic,
+    Principled,
+};
+use crate::scene::Scene;
+use crate::shape::{Plane, Sphere, TriangleMesh};
+use crate::sky::PreethamSky;
+use crate::texture::{
+    CheckerboardTexture, ConstantTexture, ImageTexture, PerlinTexture, ScaledTexture, Texture,
+    WrapMode,
+};
+use crate::types::Real;
+use crate::vec2::Vec2;
+use crate::vec3::Vec3;
+
+// scene description file written in TOML, see scenes/*.toml for examples
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SceneFile {
+    #[serde(default)]
+    film: FilmSettings,
+    #[serde(default)]
+    sampler: SamplerSettings,
+    #[serde(default)]
+    integrator: IntegratorDesc,
+    #[serde(default)]
+    intersector: IntersectorDesc,
+    #[serde(default)]
+    light_sampler: LightSamplerDesc,
+    camera: CameraDesc,
+    #[serde(default)]
+    textures: HashMap<String, TextureDesc>,
+    #[serde(default)]
+    materials: HashMap<String, MaterialDesc>,
+    #[serde(default)]
+    shapes: Vec<ShapeDesc>,
+    #[serde(default)]
+    lights: Vec<LightDesc>,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct FilmSettings {
+    pub width: usize,
+    pub height: usize,
+    pub output: PathBuf,
+}
+
+impl Default for FilmSettings {
+    fn default() -> Self {
+        FilmSettings {
+            width: 512,
+            height: 512,
+            output: PathBuf::from("output.ppm"),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, PartialEq, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct SamplerSettings {
+    pub n_samples: u32, // samples per pixel
+    pub seed: u64,      // offset added to per-pixel seed
+}
+
+impl Default for SamplerSettings {
+    fn default() -> Self {
+        SamplerSettings {
+            n_samples: 100,
+            seed: 0,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+enum IntegratorDesc {
+    Normal,
+    #[serde(rename = "pt")]
+    PathTracing {
+        #[serde(default = "default_max_depth")]
+        max_depth: u32,
+    },
+}
+
+fn default_max_depth() -> u32 {
+    100
+}
+
+impl Default for IntegratorDesc {
+    fn default() -> Self {
+        IntegratorDesc::PathTracing {
+            max_depth: default_max_depth(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct IntersectorDesc {
+    #[serde(rename = "type")]
+    intersector_type: String,
+}
+
+impl Default for IntersectorDesc {
+    fn default() -> Self {
+        IntersectorDesc {
+            intersector_type: String::from("bvh"),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LightSamplerDesc {
+    #[serde(rename = "type")]
+    light_sampler_type: String,
+}
+
+impl Default for LightSamplerDesc {
+    fn default() -> Self {
+        LightSamplerDesc {
+            light_sampler_type: String::from("bvh"),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+enum CameraDesc {
+    Pinhole {
+        position: [Real; 3],
+        forward: [Real; 3],
+        fov: Real, // vertical field of view in degrees
+    },
+}
+
+// constant given inline by number or [r, g, b]
+#[derive(Deserialize, Clone, Copy)]
+#[serde(untagged)]
+enum ValueDesc {
+    Scalar(Real),
+    Color([Real; 3]),
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+enum TextureDesc {
+    Constant {
+        value: ValueDesc,
+    },
+    Checkerboard {
+        even: ValueDesc,
+        odd: ValueDesc,
+        #[serde(default = "default_checkerboard_scale")]
+        scale: [Real; 2], // number of squares along u and v
+    },
+    Perlin {
+        #[serde(default = "default_perlin_scale")]
+        scale: Real, // frequency of noise in world space
+        #[serde(default = "default_perlin_octaves")]
+        octaves: u32,
+    },
+    // path is relative to scene file
+    Image {
+        path: PathBuf,
+        #[serde(default)]
+        encoding: EncodingDesc,
+        #[serde(default)]
+        wrap: WrapDesc,
+    },
+}
+
+// 8-bit images are sRGB unless they hold non-color data such as normals
+#[derive(Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+enum EncodingDesc {
+    #[default]
+    Srgb,
+    Linear,
+}
+
+#[derive(Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+enum WrapDesc {
+    #[default]
+    Repeat,
+    Clamp,
+    Mirror,
+}
+
+impl WrapDesc {
+    fn to_wrap_mode(self) -> WrapMode {
+        match self {
+            WrapDesc::Repeat => WrapMode::Repeat,
+            WrapDesc::Clamp => WrapMode::Clamp,
+            WrapDesc::Mirror => WrapMode::Mirror,
+        }
+    }
+}
+
+fn default_checkerboard_scale() -> [Real; 2] {
+    [8.0, 8.0]
+}
+
+fn default_perlin_scale() -> Real {
+    1.0
+}
+
+fn default_perlin_octaves() -> u32 {
+    4
+}
+
+// material parameter given by color or name of texture
+#[derive(Deserialize, Clone)]
+#[serde(untagged)]
+enum ColorDesc {
+    Value([Real; 3]),
+    Texture(String),
+}
+
+impl Default for ColorDesc {
+    fn default() -> Self {
+        ColorDesc::Value([0.0, 0.0, 0.0])
+    }
+}
+
+// specular color of plastic, or specular level of principled given by number
+#[derive(Deserialize, Clone)]
+#[serde(untagged)]
+enum SpecularDesc {
+    Level(Real),
+    Color(ColorDesc),
+}
+
+// material parameter given by number or name of texture
+#[derive(Deserialize, Clone)]
+#[serde(untagged)]
+enum FloatDesc {
+    Value(Real),
+    Texture(String),
+}
+
+impl Default for FloatDesc {
+    fn default() -> Self {
+        FloatDesc::Value(0.0)
+    }
+}
+
+impl FloatDesc {
+    fn is_zero(&self) -> bool {
+        matches!(self, FloatDesc::Value(v) if *v == 0.0)
+    }
+}
+
+#[derive(Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+enum MaterialType {
+    #[default]
+    Diffuse,
+    OrenNayar,
+    Mirror,
+    Glass,
+    Conductor,
+    Plastic,
+    Principled,
+    Mix,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MaterialDesc {
+    #[serde(default, rename = "type")]
+    material_type: MaterialType,
+    #[serde(default = "default_ior")]
+    ior: Real, // index of refraction of glass
+    eta: Option<[Real; 3]>, // complex ior of conductor
+    k: Option<[Real; 3]>,
+    roughness: Option<FloatDesc>, // zero if not given, except for principled
+    #[serde(default)]
+    anisotropic: Real,
+    #[serde(default)]
+    sigma: Real, // slope deviation of oren_nayar in degrees
+    materials: Option<Vec<String>>, // names of materials blended by mix
+    #[serde(default)]
+    weight: Real, // weight of the second material of mix
+    #[serde(default)]
+    diffuse: ColorDesc,
+    specular: Option<SpecularDesc>,
+    #[serde(default)]
+    emission: ColorDesc,
+    blackbody: Option<Real>, // temperature of blackbody emission in kelvin
+    #[serde(default = "default_emission_scale")]
+    emission_scale: Real, // multiplies emission, luminance of blackbody emission
+    #[serde(default = "default_two_sided")]
+    two_sided_emission: bool,
+    normal_map: Option<String>, // texture of tangent-space normal
+    bump_map: Option<String>,   // texture of height
+    #[serde(default = "default_bump_scale")]
+    bump_scale: Real,
+    // parameters of principled, unspecified ones take defaults of Principled
+    base_color: Option<ColorDesc>,
+    metallic: Option<Real>,
+    specular_tint: Option<Real>,
+    sheen: Option<Real>,
+    sheen_tint: Option<Real>,
+    clearcoat: Option<Real>,
+    clearcoat_gloss: Option<Real>,
+    transmission: Option<Real>,
+    subsurface: Option<Real>,
+}
+
+fn default_ior() -> Real {
+    1.5
+}
+
+fn default_bump_scale() -> Real {
+    1.0
+}
+
+fn default_emission_scale() -> Real {
+    1.0
+}
+
+fn default_two_sided() -> bool {
+    true
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+enum ShapeDesc {
+    Sphere {
+        center: [Real; 3],
+        radius: Real,
+        material: Option<String>,
+    },
+    Plane {
+        corner: [Real; 3],
+        right: [Real; 3],
+        up: [Real; 3],
+        material: Option<String>,
+    },
+    Mesh {
+        vertices: Vec<[Real; 3]>,
+        indices: Vec<u32>,
+        normals: Option<Vec<[Real; 3]>>,
+        texcoords: Option<Vec<[Real; 2]>>,
+        material: Option<String>,
+    },
+    // material overrides materials given by .mtl
+    Obj {
+        path: PathBuf,
+        material: Option<String>,
+    },
+}
+
+// area light is a shape emitting light without reflecting it
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+enum LightDesc {
+    Area {
+        #[serde(default)]
+        emission: ColorDesc,
+        blackbody: Option<Real>, // temperature in kelvin
+        #[serde(default = "default_emission_scale")]
+        scale: Real,
+        #[serde(default = "default_two_sided")]
+        two_sided: bool,
+        shape: ShapeDesc,
+    },
+    // equirectangular image surrounding the scene, path is relative to scene file
+    Environment {
+        path: PathBuf,
+        #[serde(default = "default_intensity")]
+        intensity: Real,
+        #[serde(default)]
+        rotation: Real, // rotation around y axis in degrees
+    },
+    // Preetham sky surrounding the scene with sun
+    Sky {
+        sun_direction: [Real; 3],
+        #[serde(default = "default_turbidity")]
+        turbidity: Real,
+        #[serde(default = "default_ground_albedo")]
+        ground_albedo: [Real; 3],
+        #[serde(default = "default_intensity")]
+        intensity: Real,
+        #[serde(default = "default_sun_radius")]
+        sun_radius: Real, // angular radius of sun in degrees
+    },
+    // delta lights below can only be reached by sampling them
+    Point {
+        position: [Real; 3],
+        intensity: [Real; 3],
+    },
+    // cone around direction, full intensity within inner angle and none beyond
+    // outer angle, both in degrees
+    Spot {
+        position: [Real; 3],
+        direction: [Real; 3],
+        intensity: [Real; 3],
+        #[serde(default)]
+        inner_angle: Real,
+        #[serde(default = "default_outer_angle")]
+        outer_angle: Real,
+    },
+    // parallel light travelling along direction
+    Directional {
+        direction: [Real; 3],
+        irradiance: [Real; 3],
+    },
+}
+
+fn default_intensity() -> Real {
+    1.0
+}
+
+fn default_turbidity() -> Real {
+    3.0
+}
+
+fn default_ground_albedo() -> [Real; 3] {
+    [0.3, 0.3, 0.3]
+}
+
+fn default_sun_radius() -> Real {
+    0.27
+}
+
+fn default_outer_angle() -> Real {
+    45.0
+}
+
+pub struct SceneDescription {
+    pub film: FilmSettings,
+    pub sampler: SamplerSettings,
+    pub camera: Box<dyn Camera + Send + Sync>,
+    pub scene: Scene,
+    pub integrator: Box<dyn Integrator + Send + Sync>,
+}
+
+pub fn load_scene_file(path: &Path) -> Result<SceneDescription, LoadError> {
+    parse_scene_file(&read_to_string(path)?, path)
+}
+
+fn vec3(v: [Real; 3]) -> Vec3 {
+    Vec3::new(v[0], v[1], v[2])
+}
+
+fn vec2(v: [Real; 2]) -> Vec2 {
+    Vec2::new(v[0], v[1])
+}
+
+// line number of byte offset
+fn line_of(source: &str, offset: usize) -> usize {
+    source[..offset.min(source.len())].lines().count().max(1)
+}
+
+pub fn parse_scene_file(source: &str, path: &Path) -> Result<SceneDescription, LoadError> {
+    let file: SceneFile = toml::from_str(source).map_err(|why| {
+        let line = why.span().map_or(0, |span| line_of(source, span.start));
+        LoadError::parse(path, line, why.message())
+    })?;
+
+    let camera: Box<dyn Camera + Send + Sync> = match file.camera {
+        CameraDesc::Pinhole {
+            position,
+            forward,
+            fov,
+        } => Box::new(PinholeCamera::new(
+            vec3(position),
+            vec3(forward).normalize(),
+            fov.to_radians(),
+        )),
+    };
+
+    let integrator: Box<dyn Integrator + Send + Sync> = match file.integrator {
+        IntegratorDesc::Normal => Box::new(NormalIntegrator::new()),
+        IntegratorDesc::PathTracing { max_depth } => {
+            Box::new(PathTracingIntegrator::new(max_depth))
+        }
+    };
+
+    let intersector_type = match file.intersector.intersector_type.as_str() {
+        "linear" => IntersectorType::Linear,
+        "bvh" => IntersectorType::BVH,
+        "grid" => IntersectorType::Grid,
+        "kdtree" => IntersectorType::KdTree,
+        other => {
+            return Err(LoadError::invalid(
+                path,
+                format!("unknown intersector '{}'", other),
+            ))
+        }
+    };
+
+    let light_sampler_type = match file.light_sampler.light_sampler_type.as_str() {
+        "uniform" => LightSamplerType::Uniform,
+        "power" => LightSamplerType::Power,
+        "bvh" => LightSamplerType::BVH,
+        other => {
+            return Err(LoadError::invalid(
+                path,
+                format!("unknown light sampler '{}'", other),
+            ))
+        }
+    };
+
+    let images = load_images(&file.textures, path)?;
+    let mut materials: HashMap<String, Arc<dyn Material>> = HashMap::new();
+    for name in file.materials.keys() {
+        build_material(
+            name,
+            &file.materials,
+            &file.textures,
+            &images,
+            &mut materials,
+            &mut Vec::new(),
+            path,
+        )?;
+    }
+
+    let mut builder = SceneBuilder {
+        path,
+        materials: &materials,
+        primitives: Vec::new(),
+        scene_materials: Vec::new(),
+    };
+    for shape in file.shapes {
+        builder.add_shape(shape, None)?;
+    }
+    // lights other than area lights are added once the scene is built
+    let mut lights: Vec<Box<dyn Light>> = Vec::new();
+    let mut has_environment = false;
+    for light in file.lights {
+        match light {
+            LightDesc::Area {
+                emission,
+                blackbody,
+                scale,
+                two_sided,
+                shape,
+            } => {
+                let material = add_emission(
+                    Arc::new(Diffuse::new(Arc::new(ConstantTexture::new(Vec3::new(
+                        0.0, 0.0, 0.0,
+                    ))))),
+                    &emission,
+                    blackbody,
+                    scale,
+                    two_sided,
+                    |desc| color_texture(desc, &file.textures, &images, path),
+                    path,
+                )?;
+                builder.add_shape(shape, Some(material))?;
+            }
+            LightDesc::Environment {
+                path: image_path,
+                intensity,
+                rotation,
+            } => {
+                if has_environment {
+                    return Err(LoadError::invalid(path, "more than one environment light"));
+                }
+                has_environment = true;
+                let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+                let image = Image::load(&base_dir.join(image_path), ColorEncoding::SRGB)?;
+                lights.push(Box::new(EnvironmentLight::new(
+                    Arc::new(image),
+                    intensity,
+                    rotation.to_radians(),
+                )));
+            }
+            LightDesc::Sky {
+                sun_direction,
+                turbidity,
+                ground_albedo,
+                intensity,
+                sun_radius,
+            } => {
+                if has_environment {
+                    return Err(LoadError::invalid(path, "more than one environment light"));
+                }
+                has_environment = true;
+                let sky = PreethamSky::new(vec3(sun_direction), turbidity, vec3(ground_albedo));
+                let image = sky.to_image(512, 256, sun_radius.to_radians());
+                lights.push(Box::new(EnvironmentLight::new(
+                    Arc::new(image),
+                    intensity,
+                    0.0,
+                )));
+                lights.push(Box::new(SunLight::new(
+                    sky.sun_direction(),
+                    intensity * sky.sun_radiance(),
+                    sun_radius.to_radians(),
+                )));
+            }
+            LightDesc::Point {
+                position,
+                intensity,
+            } => {
+                lights.push(Box::new(PointLight::new(vec3(position), vec3(intensity))));
+            }
+            LightDesc::Spot {
+                position,
+                direction,
+                intensity,
+                inner_angle,
+                outer_angle,
+            } => {
+                lights.push(Box::new(SpotLight::new(
+                    vec3(position),
+                    vec3(direction),
+                    vec3(intensity),
+                    inner_angle.to_radians(),
+                    outer_angle.to_radians(),
+                )));
+            }
+            LightDesc::Directional {
+                direction,
+                irradiance,
+            } => {
+                lights.push(Box::new(DirectionalLight::new(
+                    -vec3(direction),
+                    vec3(irradiance),
+                )));
+            }
+        }
+    }
+
+    let mut scene = Scene::new_with_intersector(
+        builder.primitives,
+        builder.scene_materials,
+        intersector_type,
+    );
+    scene.set_light_sampler(light_sampler_type);
+    for light in lights {
+        scene.add_light(light);
+    }
+
+    Ok(SceneDescription {
+        film: file.film,
+        sampler: file.sampler,
+        camera,
+        scene,
+        integrator,
+    })
+}
+
+// build material and materials it refers to, visiting holds names being built to detect cycles
+fn build_material(
+    name: &str,
+    descs: &HashMap<String, MaterialDesc>,
+    textures: &HashMap<String, TextureDesc>,
+    images: &HashMap<String, Arc<Image>>,
+    built: &mut HashMap<String, Arc<dyn Material>>,
+    visiting: &mut Vec<String>,
+    path: &Path,
+) -> Result<Arc<dyn Material>, LoadError> {
+    if let Some(material) = built.get(name) {
+        return Ok(material.clone());
+    }
+    let Some(desc) = descs.get(name) else {
+        return Err(LoadError::invalid(
+            path,
+            format!("unknown material '{}'", name),
+        ));
+    };
+    if visiting.iter().any(|n| n == name) {
+        return Err(LoadError::invalid(
+            path,
+            format!("material '{}' refers to itself", name),
+        ));
+    }
+    visiting.push(name.to_string());
+
+    let color = |desc: &ColorDesc| color_texture(desc, textures, images, path);
+    let float = |desc: &FloatDesc| float_texture(desc, textures, images, path);
+    let roughness = desc.roughness.clone().unwrap_or_default();
+    let material: Arc<dyn Material> = match desc.material_type {
+        MaterialType::Diffuse if roughness.is_zero() => {
+            Arc::new(Diffuse::new(color(&desc.diffuse)?))
+        }
+        MaterialType::Diffuse => {
+            Arc::new(Diffuse::rough(color(&desc.diffuse)?, float(&roughness)?))
+        }
+        MaterialType::OrenNayar => Arc::new(Diffuse::oren_nayar(
+            color(&desc.diffuse)?,
+            desc.sigma.to_radians(),
+        )),
+        MaterialType::Mirror => Arc::new(Mirror::new()),
+        MaterialType::Glass if roughness.is_zero() => Arc::new(Glass::new(desc.ior)),
+        MaterialType::Glass => {
+            Arc::new(Glass::rough(desc.ior, float(&roughness)?, desc.anisotropic))
+        }
+        MaterialType::Conductor => {
+            let (Some(eta), Some(k)) = (desc.eta, desc.k) else {
+                return Err(LoadError::invalid(
+                    path,
+                    format!("conductor material '{}' needs eta and k", name),
+                ));
+            };
+            Arc::new(Conductor::new(
+                vec3(eta),
+                vec3(k),
+                float(&roughness)?,
+                desc.anisotropic,
+            ))
+        }
+        MaterialType::Plastic => {
+            let specular = match &desc.specular {
+                Some(SpecularDesc::Color(specular)) => color(specular)?,
+                Some(SpecularDesc::Level(level)) => {
+                    Arc::new(ConstantTexture::new(Vec3::new(*level, *level, *level)))
+                }
+                None => color(&ColorDesc::default())?,
+            };
+            Arc::new(Plastic::new(
+                color(&desc.diffuse)?,
+                specular,
+                float(&roughness)?,
+            ))
+        }
+        MaterialType::Principled => {
+            let default = Principled::default();
+            // specular level follows ior as in glTF unless given
+            let specular = match &desc.specular {
+                Some(SpecularDesc::Level(level)) => *level,
+                Some(SpecularDesc::Color(_)) => {
+                    return Err(LoadError::invalid(
+                        path,
+                        format!("specular of principled material '{}' is a number", name),
+                    ))
+                }
+                None => Principled::specular_from_ior(desc.ior),
+            };
+            Arc::new(Principled {
+                base_color: match &desc.base_color {
+                    Some(base_color) => color(base_color)?,
+                    None => default.base_color,
+                },
+                metallic: match desc.metallic {
+                    Some(metallic) => Arc::new(ConstantTexture::new(metallic)),
+                    None => default.metallic,
+                },
+                roughness: match &desc.roughness {
+                    Some(roughness) => float(roughness)?,
+                    None => default.roughness,
+                },
+                anisotropic: desc.anisotropic,
+                specular,
+                specular_tint: desc.specular_tint.unwrap_or(default.specular_tint),
+                sheen: desc.sheen.unwrap_or(default.sheen),
+                sheen_tint: desc.sheen_tint.unwrap_or(default.sheen_tint),
+                clearcoat: desc.clearcoat.unwrap_or(default.clearcoat),
+                clearcoat_gloss: desc.clearcoat_gloss.unwrap_or(default.clearcoat_gloss),
+                transmission: desc.transmission.unwrap_or(default.transmission),
+                ior: desc.ior,
+                subsurface: desc.subsurface.unwrap_or(default.subsurface),
+            })
+        }
+        MaterialType::Mix => {
+            let names = desc.materials.as_deref().unwrap_or_default();
+            let [name1, name2] = names else {
+                return Err(LoadError::invalid(
+                    path,
+                    format!("mix material '{}' needs two materials", name),
+                ));
+            };
+            let material1 = build_material(name1, descs, textures, images, built, visiting, path)?;
+            let material2 = build_material(name2, descs, textures, images, built, visiting, path)?;
+            Arc::new(Mix::new(material1, material2, desc.weight))
+        }
+    };
+    let material: Arc<dyn Material> = match &desc.normal_map {
+        Some(name) => Arc::new(NormalMapped::new(
+            material,
+            color(&ColorDesc::Texture(name.clone()))?,
+        )),
+        None => material,
+    };
+    let material: Arc<dyn Material> = match &desc.bump_map {
+        Some(name) => Arc::new(BumpMapped::new(
+            material,
+            float(&FloatDesc::Texture(name.clone()))?,
+            desc.bump_scale,
+        )),
+        None => material,
+    };
+    let material = add_emission(
+        material,
+        &desc.emission,
+        desc.blackbody,
+        desc.emission_scale,
+        desc.two_sided_emission,
+        color,
+        path,
+    )?;
+
+    visiting.pop();
+    built.insert(name.to_string(), material.clone());
+    Ok(material)
+}
+
+// wraps material by Emissive if it emits color, texture or blackbody radiation
+fn add_emission(
+    material: Arc<dyn Material>,
+    emission: &ColorDesc,
+    blackbody: Option<Real>,
+    scale: Real,
+    two_sided: bool,
+    color: impl Fn(&ColorDesc) -> Result<Arc<dyn Texture<Vec3>>, LoadError>,
+    path: &Path,
+) -> Result<Arc<dyn Material>, LoadError> {
+    let emits_color = match emission {
+        ColorDesc::Value(c) => vec3(*c).max() > 0.0,
+        ColorDesc::Texture(_) => true,
+    };
+    let material: Arc<dyn Material> = match blackbody {
+        Some(_) if emits_color => {
+            return Err(LoadError::invalid(
+                path,
+                "emission and blackbody can't be given together",
+            ))
+        }
+        Some(temperature) => Arc::new(Emissive::from_blackbody(
+            material,
+            temperature,
+            scale,
+            two_sided,
+        )),
+        None if emits_color => Arc::new(Emissive::new(
+            material,
+            Arc::new(ScaledTexture::new(
+                color(emission)?,
+                Vec3::new(scale, scale, scale),
+            )),
+            two_sided,
+        )),
+        None => material,
+    };
+    Ok(material)
+}
+
+// images of image textures keyed by texture name, each file is read once
+fn load_images(
+    textures: &HashMap<String, TextureDesc>,
+    path: &Path,
+) -> Result<HashMap<String, Arc<Image>>, LoadError> {
+    let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+    let mut images = HashMap::new();
+    for (name, desc) in textures {
+        if let TextureDesc::Image {
+            path: image_path,
+            encoding,
+            ..
+        } = desc
+        {
+            let encoding = match encoding {
+                EncodingDesc::Srgb => ColorEncoding::SRGB,
+                EncodingDesc::Linear => ColorEncoding::Linear,
+            };
+            let image = Image::load(&base_dir.join(image_path), encoding)?;
+            images.insert(name.clone(), Arc::new(image));
+        }
+    }
+    Ok(images)
+}
+
+fn find_texture<'a>(
+    name: &str,
+    textures: &'a HashMap<String, TextureDesc>,
+    path: &Path,
+) -> Result<&'a TextureDesc, LoadError> {
+    textures
+        .get(name)
+        .ok_or_else(|| LoadError::invalid(path, format!("unknown texture '{}'", name)))
+}
+
+fn color_value(value: ValueDesc) -> Vec3 {
+    match value {
+        ValueDesc::Scalar(v) => Vec3::new(v, v, v),
+        ValueDesc::Color(c) => vec3(c),
+    }
+}
+
+fn float_value(value: ValueDesc, name: &str, path: &Path) -> Result<Real, LoadError> {
+    match value {
+        ValueDesc::Scalar(v) => Ok(v),
+        ValueDesc::Color(_) => Err(LoadError::invalid(
+            path,
+            format!("texture '{}' gives color where number is expected", name),
+        )),
+    }
+}
+
+fn color_texture(
+    desc: &ColorDesc,
+    textures: &HashMap<String, TextureDesc>,
+    images: &HashMap<String, Arc<Image>>,
+    path: &Path,
+) -> Result<Arc<dyn Texture<Vec3>>, LoadError> {
+    let name = match desc {
+        ColorDesc::Value(c) => return Ok(Arc::new(ConstantTexture::new(vec3(*c)))),
+        ColorDesc::Texture(name) => name,
+    };
+    let texture: Arc<dyn Texture<Vec3>> = match find_texture(name, textures, path)? {
+        TextureDesc::Constant { value } => Arc::new(ConstantTexture::new(color_value(*value))),
+        TextureDesc::Checkerboard { even, odd, scale } => Arc::new(CheckerboardTexture::new(
+            Arc::new(ConstantTexture::new(color_value(*even))),
+            Arc::new(ConstantTexture::new(color_value(*odd))),
+            vec2(*scale),
+        )),
+        TextureDesc::Perlin { scale, octaves } => Arc::new(PerlinTexture::new(*scale, *octaves)),
+        TextureDesc::Image { wrap, .. } => {
+            Arc::new(ImageTexture::new(images[name].clone(), wrap.to_wrap_mode()))
+        }
+    };
+    Ok(texture)
+}
+
+fn float_texture(
+    desc: &FloatDesc,
+    textures: &HashMap<String, TextureDesc>,
+    images: &HashMap<String, Arc<Image>>,
+    path: &Path,
+) -> Result<Arc<dyn Texture<Real>>, LoadError> {
+    let name = match desc {
+        FloatDesc::Value(v) => return Ok(Arc::new(ConstantTexture::new(*v))),
+        FloatDesc::Texture(name) => name,
+    };
+    let texture: Arc<dyn Texture<Real>> = match find_texture(name, textures, path)? {
+        TextureDesc::Constant { value } => {
+            Arc::new(ConstantTexture::new(float_value(*value, name, path)?))
+        }
+        TextureDesc::Checkerboard { even, odd, scale } => Arc::new(CheckerboardTexture::new(
+            Arc::new(ConstantTexture::new(float_value(*even, name, path)?)),
+            Arc::new(ConstantTexture::new(float_value(*odd, name, path)?)),
+            vec2(*scale),
+        )),
+        TextureDesc::Perlin { scale, octaves } => Arc::new(PerlinTexture::new(*scale, *octaves)),
+        TextureDesc::Image { wrap, .. } => {
+            Arc::new(ImageTexture::new(images[name].clone(), wrap.to_wrap_mode()))
+        }
+    };
+    Ok(texture)
+}
+
+struct SceneBuilder<'a> {
+    path: &'a Path,
+    materials: &'a HashMap<String, Arc<dyn Material>>,
+    primitives: Vec<Box<dyn IntersectableLocal + Send + Sync>>,
+    scene_materials: Vec<Arc<dyn Material>>,
+}
+
+impl SceneBuilder<'_> {
+    fn find_material(&self, name: &Option<String>) -> Result<Option<Arc<dyn Material>>, LoadError> {
+        match name {
+            Some(name) => match self.materials.get(name) {
+                Some(material) => Ok(Some(material.clone())),
+                None => Err(LoadError::invalid(
+                    self.path,
+                    format!("unknown material '{}'", name),
+                )),
+            },
+            None => Ok(None),
+        }
+    }
+
+    fn push(
+        &mut self,
+        primitive: Box<dyn IntersectableLocal + Send + Sync>,
+        material: Arc<dyn Material>,
+    ) {
+        self.primitives.push(primitive);
+        self.scene_materials.push(material);
+    }
+
+    // shapes without material become gray diffuse
+    fn add_shape(
+        &mut self,
+        shape: ShapeDesc,
+        material: Option<Arc<dyn Material>>,
+    ) -> Result<(), LoadError> {
+        let default_material: Arc<dyn Material> = Arc::new(Diffuse::new(Arc::new(
+            ConstantTexture::new(Vec3::new(0.8, 0.8, 0.8)),
+        )));
+
+        match shape {
+            ShapeDesc::Sphere {
+                center,
+                radius,
+                material: name,
+            } => {
+                let material = material
+                    .or(self.find_material(&name)?)
+                    .unwrap_or(default_material);
+                self.push(Box::new(Sphere::new(vec3(center), radius)), material);
+            }
+            ShapeDesc::Plane {
+                corner,
+                right,
+                up,
+                material: name,
+            } => {
+                let material = material
+                    .or(self.find_material(&name)?)
+                    .unwrap_or(default_material);
+                self.push(
+                    Box::new(Plane::new(vec3(corner), vec3(right), vec3(up))),
+                    material,
+                );
+            }
+            ShapeDesc::Mesh {
+                vertices,
+                indices,
+                normals,
+                texcoords,
+                material: name,
+            } => {
+                let material = material
+                    .or(self.find_material(&name)?)
+                    .unwrap_or(default_material);
+                let mesh = self.build_mesh(vertices, indices, normals, texcoords)?;
+                for triangle in TriangleMesh::triangles(&mesh) {
+                    self.push(Box::new(triangle), material.clone());
+                }
+            }
+            ShapeDesc::Obj {
+                path: obj_path,
+                material: name,
+            } => {
+                let material = material.or(self.find_material(&name)?);
+                let base_dir = self.path.parent().unwrap_or_else(|| Path::new(""));
+                let model = load_obj(&base_dir.join(obj_path))?;
+                // textures of .mtl are not read when they are overridden
+                let converted = match material {
+                    Some(_) => Vec::new(),
+                    None => model.build_materials()?,
+                };
+                for mesh in &model.meshes {
+                    let material = match (&material, mesh.material) {
+                        (Some(material), _) => material.clone(),
+                        (None, Some(idx)) => converted[idx].clone(),
+                        (None, None) => default_material.clone(),
+                    };
+                    for triangle in TriangleMesh::triangles(&mesh.mesh) {
+                        self.push(Box::new(triangle), material.clone());
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn build_mesh(
+        &self,
+        vertices: Vec<[Real; 3]>,
+        indices: Vec<u32>,
+        normals: Option<Vec<[Real; 3]>>,
+        texcoords: Option<Vec<[Real; 2]>>,
+    ) -> Result<Arc<TriangleMesh>, LoadError> {
+        // validate here since TriangleMesh panics on inconsistent buffers
+        if !indices.len().is_multiple_of(3) {
+            return Err(LoadError::invalid(
+                self.path,
+                "number of mesh indices is not a multiple of 3",
+            ));
+        }
+        if indices.iter().any(|idx| *idx as usize >= vertices.len()) {
+            return Err(LoadError::invalid(self.path, "mesh index is out of range"));
+        }
+        if normals.as_ref().is_some_and(|n| n.len() != vertices.len())
+            || texcoords
+                .as_ref()
+                .is_some_and(|t| t.len() != vertices.len())
+        {
+            return Err(LoadError::invalid(
+                self.path,
+                "number of mesh normals or texcoords does not equal to the number of vertices",
+            ));
+        }
+
+        Ok(Arc::new(TriangleMesh::new(
+            vertices.into_iter().map(vec3).collect(),
+            indices,
+            normals.map(|normals| normals.into_iter().map(vec3).collect()),
+            texcoords.map(|texcoords| texcoords.into_iter().map(vec2).collect()),
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bxdf::BxDFType;
+    use crate::core::{IntersectableGlobal, Ray};
+    use crate::loader::scene_file::*;
+    use crate::sampler::Sampler;
+    use crate::test_dir::TestDir;
+
+    const SIMPLE_SCENE: &str = r#"
+        [film]
+        width = 256
+        height = 128
+
+        [camera]
+        type = "pinhole"
+        position = [0.0, 0.0, 6.0]
+        forward = [0.0, 0.0, -1.0]
+        fov = 90.0
+
+        [materials.red]
+        diffuse = [0.8, 0.2, 0.2]
+
+        [[shapes]]
+        type = "sphere"
+        center = [0.0, 0.0, 0.0]
+        radius = 1.0
+        material = "red"
+
+        [[lights]]
+        type = "area"
+        emission = [1.0, 1.0, 1.0]
+        shape = { type = "plane", corner = [-1.0, 3.0, -1.0], right = [2.0, 0.0, 0.0], up = [0.0, 0.0, 2.0] }
+    "#;
+
+    #[test]
+    fn parse_simple_scene() {
+        let desc = parse_scene_file(SIMPLE_SCENE, Path::new("simple.toml")).unwrap();
+        assert_eq!(desc.film.width, 256);
+        assert_eq!(desc.film.height, 128);
+        assert_eq!(desc.sampler, SamplerSettings::default());
+
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 6.0), Vec3::new(0.0, 0.0, -1.0));
+        let info = desc.scene.intersect(&ray).unwrap();
+        assert_eq!(info.prim_idx, 0);
+        assert_eq!(info.t, 5.0);
+
+        let ray = Ray::new(Vec3::new(0.0, 2.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        let info = desc.scene.intersect(&ray).unwrap();
+        assert!(desc.scene.has_emission(info.prim_idx));
+    }
+
+    #[test]
+    fn parse_errors() {
+        let source = "[film]\nwidth = 512\nheight = \"tall\"\n";
+        match parse_scene_file(source, Path::new("broken.toml")) {
+            Err(LoadError::Parse { line, .. }) => assert_eq!(line, 3),
+            _ => panic!("expected parse error"),
+        }
+
+        let source = SIMPLE_SCENE.replace("material = \"red\"", "material = \"blue\"");
+        assert!(matches!(
+            parse_scene_file(&source, Path::new("broken.toml")),
+            Err(LoadError::Invalid { .. })
+        ));
+
+        let source = SIMPLE_SCENE.replace(
+            "diffuse = [0.8, 0.2, 0.2]",
+            "type = \"conductor\"\nroughness = 0.3",
+        );
+        assert!(matches!(
+            parse_scene_file(&source, Path::new("broken.toml")),
+            Err(LoadError::Invalid { .. })
+        ));
+    }
+
+    #[test]
+    fn parse_materials() {
+        let source = SIMPLE_SCENE.replace(
+            "[materials.red]",
+            r#"
+            [materials.gold]
+            type = "conductor"
+            eta = [0.143, 0.374, 1.442]
+            k = [3.983, 2.385, 1.603]
+            roughness = 0.3
+
+            [materials.frosted]
+            type = "glass"
+            roughness = 0.2
+
+            [materials.plastic]
+            type = "plastic"
+            diffuse = [0.5, 0.5, 0.5]
+            specular = [1.0, 1.0, 1.0]
+
+            [materials.glowing]
+            type = "mix"
+            materials = ["gold", "plastic"]
+            weight = 0.5
+            emission = [1.0, 1.0, 1.0]
+
+            [materials.red]"#,
+        );
+        let source = source.replace("material = \"red\"", "material = \"glowing\"");
+        let desc = parse_scene_file(&source, Path::new("materials.toml")).unwrap();
+
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let info = desc.scene.intersect(&ray).unwrap();
+        assert!(desc.scene.has_emission(info.prim_idx));
+        let shading_info = desc.scene.get_shading_info(-ray.direction, &info);
+        let bsdf = desc.scene.get_bsdf(info.prim_idx, &shading_info);
+        assert_eq!(bsdf.n_bxdfs(), 2);
+        assert!(bsdf
+            .bxdf_type()
+            .contains(BxDFType::GLOSSY | BxDFType::DIFFUSE));
+
+        // mix referring to itself
+        let source = SIMPLE_SCENE.replace(
+            "diffuse = [0.8, 0.2, 0.2]",
+            "type = \"mix\"\nmaterials = [\"red\", \"red\"]",
+        );
+        assert!(matches!(
+            parse_scene_file(&source, Path::new("broken.toml")),
+            Err(LoadError::Invalid { .. })
+        ));
+    }
+
+    // reflection of sphere hit from the camera, seen from another direction
+    fn sphere_reflection(source: &str) -> Vec3 {
+        let desc = parse_scene_file(source, Path::new("sphere.toml")).unwrap();
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 6.0), Vec3::new(0.0, 0.0, -1.0));
+        let info = desc.scene.intersect(&ray).unwrap();
+        let shading_info = desc.scene.get_shading_info(-ray.direction, &info);
+        let bsdf = desc.scene.get_bsdf(info.prim_idx, &shading_info);
+        bsdf.eval(-ray.direction, Vec3::new(0.0, 0.6, 0.8))
+    }
+
+    #[test]
+    fn parse_principled() {
+        // unspecified parameters take defaults of Principled
+        let principled = |parameters: &str| {
+            let source = SIMPLE_SCENE.replace(
+                "diffuse = [0.8, 0.2, 0.2]",
+                &format!("type = \"principled\"\n{}", parameters),
+            );
+            sphere_reflection(&source)
+        };
+        assert_eq!(principled(""), principled("roughness = 0.5"));
+        assert_ne!(principled(""), principled("roughness = 0.1"));
+
+        // specular level is derived from ior unless given
+        assert_eq!(principled("ior = 1.5"), principled("specular = 0.5"));
+        assert_ne!(principled(""), principled("specular = 1.0"));
+        let source = SIMPLE_SCENE.replace(
+            "diffuse = [0.8, 0.2, 0.2]",
+            "type = \"principled\"\nspecular = [1.0, 1.0, 1.0]",
+        );
+        assert!(matches!(
+            parse_scene_file(&source, Path::new("broken.toml")),
+            Err(LoadError::Invalid { .. })
+        ));
+    }
+
+    #[test]
+    fn parse_textures() {
+        let source = SIMPLE_SCENE.replace(
+            "[materials.red]\n        diffuse = [0.8, 0.2, 0.2]",
+            r#"
+            [textures.checker]
+            type = "checkerboard"
+            even = [0.8, 0.2, 0.2]
+            odd = 0.1
+            scale = [4.0, 2.0]
+
+            [textures.noise]
+            type = "perlin"
+            scale = 2.0
+
+            [materials.red]
+            type = "plastic"
+            diffuse = "checker"
+            specular = [1.0, 1.0, 1.0]
+            roughness = "noise"
+            bump_map = "noise"
+            bump_scale = 0.1"#,
+        );
+        assert!(parse_scene_file(&source, Path::new("textures.toml")).is_ok());
+
+        let normal_mapped = source.replace("bump_map", "normal_map");
+        assert!(parse_scene_file(&normal_mapped, Path::new("textures.toml")).is_ok());
+
+        // height given by color
+        let broken = source.replace("bump_map = \"noise\"", "bump_map = \"checker\"");
+        assert!(matches!(
+            parse_scene_file(&broken, Path::new("broken.toml")),
+            Err(LoadError::Invalid { .. })
+        ));
+
+        // color given where roughness is expected
+        let broken = source.replace("roughness = \"noise\"", "roughness = \"checker\"");
+        assert!(matches!(
+            parse_scene_file(&broken, Path::new("broken.toml")),
+            Err(LoadError::Invalid { .. })
+        ));
+
+        // emission of area light looked up by uv of plane
+        let textured_light = source.replace("emission = [1.0, 1.0, 1.0]", "emission = \"checker\"");
+        let textured_light = textured_light.replace("scale = [4.0, 2.0]", "scale = [2.0, 2.0]");
+        let desc = parse_scene_file(&textured_light, Path::new("textures.toml")).unwrap();
+        let emission = |x: Real| {
+            let ray = Ray::new(Vec3::new(x, 2.0, -0.5), Vec3::new(0.0, 1.0, 0.0));
+            let info = desc.scene.intersect(&ray).unwrap();
+            desc.scene.get_emission(-ray.direction, &info)
+        };
+        assert_eq!(emission(-0.5), Vec3::new(0.8, 0.2, 0.2));
+        assert_eq!(emission(0.5), Vec3::new(0.1, 0.1, 0.1));
+
+        let broken = source.replace("diffuse = \"checker\"", "diffuse = \"marble\"");
+        assert!(matches!(
+            parse_scene_file(&broken, Path::new("broken.toml")),
+            Err(LoadError::Invalid { .. })
+        ));
+    }
+
+    #[test]
+    fn parse_image_texture() {
+        let dir = TestDir::new("parse_image_texture");
+        ::image::RgbImage::from_raw(1, 1, vec![255, 128, 0])
+            .unwrap()
+            .save(dir.join("texture.png"))
+            .unwrap();
+        let source = SIMPLE_SCENE.replace(
+            "[materials.red]\n        diffuse = [0.8, 0.2, 0.2]",
+            r#"
+            [textures.wood]
+            type = "image"
+            path = "texture.png"
+
+            [textures.normals]
+            type = "image"
+            path = "texture.png"
+            encoding = "linear"
+            wrap = "clamp"
+
+            [materials.red]
+            diffuse = "wood"
+            normal_map = "normals""#,
+        );
+        assert!(parse_scene_file(&source, &dir.join("image.toml")).is_ok());
+
+        let broken = source.replace("texture.png", "missing.png");
+        assert!(matches!(
+            parse_scene_file(&broken, &dir.join("broken.toml")),
+            Err(LoadError::Io { .. })
+        ));
+    }
+
+    #[test]
+    fn parse_environment_light() {
+        let dir = TestDir::new("parse_environment_light");
+        ::image::Rgb32FImage::from_raw(2, 1, vec![1.0, 2.0, 3.0, 0.5, 0.5, 0.5])
+            .unwrap()
+            .save(dir.join("environment.hdr"))
+            .unwrap();
+        let environment = r#"
+        [[lights]]
+        type = "environment"
+        path = "environment.hdr"
+        intensity = 2.0
+        rotation = 90.0
+        "#;
+        let source = format!("{}{}", SIMPLE_SCENE, environment);
+        let desc = parse_scene_file(&source, &dir.join("environment.toml")).unwrap();
+        assert_eq!(desc.scene.n_lights(), 2);
+
+        let source = format!("{}{}{}", SIMPLE_SCENE, environment, environment);
+        assert!(matches!(
+            parse_scene_file(&source, &dir.join("broken.toml")),
+            Err(LoadError::Invalid { .. })
+        ));
+
+        // sky adds environment light and sun
+        let sky = r#"
+        [[lights]]
+        type = "sky"
+        sun_direction = [1.0, 1.0, 0.0]
+        turbidity = 4.0
+        "#;
+        let source = format!("{}{}", SIMPLE_SCENE, sky);
+        let desc = parse_scene_file(&source, &dir.join("sky.toml")).unwrap();
+        assert_eq!(desc.scene.n_lights(), 3);
+
+        let source = format!("{}{}{}", SIMPLE_SCENE, environment, sky);
+        assert!(matches!(
+            parse_scene_file(&source, &dir.join("broken.toml")),
+            Err(LoadError::Invalid { .. })
+        ));
+    }
+
+    #[test]
+    fn parse_delta_lights() {
+        let lights = r#"
+        [[lights]]
+        type = "point"
+        position = [0.0, 2.0, 0.0]
+        intensity = [5.0, 5.0, 5.0]
+
+        [[lights]]
+        type = "spot"
+        position = [0.0, 2.0, 0.0]
+        direction = [0.0, -1.0, 0.0]
+        intensity = [10.0, 10.0, 10.0]
+        inner_angle = 20.0
+
+        [[lights]]
+        type = "directional"
+        direction = [0.0, -1.0, 1.0]
+        irradiance = [1.0, 1.0, 1.0]
+        "#;
+        let source = format!("{}{}", SIMPLE_SCENE, lights);
+        let desc = parse_scene_file(&source, Path::new("lights.toml")).unwrap();
+        assert_eq!(desc.scene.n_lights(), 4);
+        assert!(desc.scene.lights()[1..]
+            .iter()
+            .all(|light| light.light_type().is_delta()));
+
+        // directional light reaches point below it
+        let mut sampler = Sampler::new(0);
+        let sample = desc.scene.lights()[3]
+            .sample_li(Vec3::new(0.0, 0.0, 0.0), &mut sampler)
+            .unwrap();
+        assert!(sample.wi.y() > 0.0 && sample.wi.z() < 0.0);
+
+        for light_sampler in ["uniform", "power", "bvh"] {
+            let source = format!(
+                "[light_sampler]\ntype = \"{}\"\n{}{}",
+                light_sampler, SIMPLE_SCENE, lights
+            );
+            let desc = parse_scene_file(&source, Path::new("lights.toml")).unwrap();
+            assert!(desc
+                .scene
+                .sample_light(Vec3::new(0.0, 0.0, 0.0), &mut sampler)
+                .is_some());
+        }
+        let source = format!("[light_sampler]\ntype = \"tree\"\n{}", SIMPLE_SCENE);
+        assert!(matches!(
+            parse_scene_file(&source, Path::new("broken.toml")),
+            Err(LoadError::Invalid { .. })
+        ));
+    }
+
+    #[test]
+    fn parse_emitters() {
+        // light emitting only in red channel is still light
+        let source =
+            SIMPLE_SCENE.replace("emission = [1.0, 1.0, 1.0]", "emission = [1.0, 0.0, 0.0]");
+        let desc = parse_scene_file(&source, Path::new("emitters.toml")).unwrap();
+        assert_eq!(desc.scene.n_lights(), 1);
+
+        // material whose emission texture is black everywhere isn't sampled as light
+        let source = SIMPLE_SCENE.replace(
+            "[materials.red]\n        diffuse = [0.8, 0.2, 0.2]",
+            "[textures.dark]\ntype = \"constant\"\nvalue = 0.0\n\n[materials.red]\nemission = \"dark\"",
+        );
+        let desc = parse_scene_file(&source, Path::new("emitters.toml")).unwrap();
+        assert_eq!(desc.scene.n_lights(), 1);
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 6.0), Vec3::new(0.0, 0.0, -1.0));
+        let info = desc.scene.intersect(&ray).unwrap();
+        assert!(desc.scene.has_emission(info.prim_idx));
+        assert_eq!(
+            desc.scene
+                .pdf_light(ray.origin, ray.direction, info.prim_idx),
+            0.0
+        );
+
+        // one-sided light faces down towards sphere
+        let source = SIMPLE_SCENE.replace(
+            "emission = [1.0, 1.0, 1.0]",
+            "emission = [1.0, 1.0, 1.0]\ntwo_sided = false",
+        );
+        let desc = parse_scene_file(&source, Path::new("emitters.toml")).unwrap();
+        let emission = |origin: Vec3, direction: Vec3| {
+            let ray = Ray::new(origin, direction);
+            let info = desc.scene.intersect(&ray).unwrap();
+            desc.scene.get_emission(-ray.direction, &info)
+        };
+        let below = emission(Vec3::new(0.0, 2.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        assert_eq!(below, Vec3::new(1.0, 1.0, 1.0));
+        let above = emission(Vec3::new(0.0, 4.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        assert_eq!(above, Vec3::new(0.0, 0.0, 0.0));
+
+        // blackbody of material is scaled to given luminance
+        let source = SIMPLE_SCENE.replace(
+            "diffuse = [0.8, 0.2, 0.2]",
+            "diffuse = [0.8, 0.2, 0.2]\nblackbody = 6500.0\nemission_scale = 2.0",
+        );
+        let desc = parse_scene_file(&source, Path::new("emitters.toml")).unwrap();
+        assert_eq!(desc.scene.n_lights(), 2);
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 6.0), Vec3::new(0.0, 0.0, -1.0));
+        let info = desc.scene.intersect(&ray).unwrap();
+        let le = desc.scene.get_emission(-ray.direction, &info);
+        assert!((le.y() - 2.0).abs() < 0.2 && le.min() > 1.5);
+
+        let source = SIMPLE_SCENE.replace(
+            "emission = [1.0, 1.0, 1.0]",
+            "emission = [1.0, 1.0, 1.0]\nblackbody = 3000.0",
+        );
+        assert!(matches!(
+            parse_scene_file(&source, Path::new("broken.toml")),
+            Err(LoadError::Invalid { .. })
+        ));
+    }
+
+    #[test]
+    fn parse_example_scenes() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("scenes");
+        for name in [
+            "simple.toml",
+            "cornellbox.toml",
+            "glass.toml",
+            "materials.toml",
+            "principled.toml",
+        ] {
+            if let Err(why) = load_scene_file(&dir.join(name)) {
+                panic!("{}", why);
+            }
+        }
+    }
+}
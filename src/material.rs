@@ -0,0 +1,621 @@
+use std::sync::Arc;
+
+use crate::bsdf::BSDF;
+use crate::bxdf::{
+    roughness_to_alpha, BxDF, CoatedDiffuse, Dielectric, DisneyClearcoat, DisneyDiffuse,
+    DisneySpecular, EnergyPreservingOrenNayar, IdealReflection, Lambert, MicrofacetConductor,
+    MicrofacetDielectric, OrenNayar,
+};
+use crate::color::blackbody;
+use crate::core::ShadingInfo;
+use crate::texture::{ConstantTexture, Texture};
+use crate::types::Real;
+use crate::vec2::Vec2;
+use crate::vec3::Vec3;
+
+pub trait Material: Send + Sync {
+    // build BSDF describing scattering at surface
+    fn get_bsdf(&self, info: &ShadingInfo) -> BSDF;
+
+    // radiance emitted towards info.wo, frame of info is built around geometric normal
+    // so that wo is above it on the front side of surface
+    fn emission(&self, _info: &ShadingInfo) -> Vec3 {
+        Vec3::new(0.0, 0.0, 0.0)
+    }
+
+    // emissive primitives are registered as area lights
+    fn is_emissive(&self) -> bool {
+        false
+    }
+
+    // shading normal perturbed by normal or bump map, info holds unperturbed frame
+    fn shading_normal(&self, info: &ShadingInfo, _dpdu: Vec3, _dpdv: Vec3) -> Vec3 {
+        info.n
+    }
+}
+
+enum DiffuseModel {
+    Lambert,
+    OrenNayar(Real), // sigma in radians
+    EnergyPreservingOrenNayar(Arc<dyn Texture<Real>>),
+}
+
+pub struct Diffuse {
+    rho: Arc<dyn Texture<Vec3>>, // albedo
+    model: DiffuseModel,
+}
+
+impl Diffuse {
+    pub fn new(rho: Arc<dyn Texture<Vec3>>) -> Self {
+        Diffuse {
+            rho,
+            model: DiffuseModel::Lambert,
+        }
+    }
+
+    // energy-preserving rough diffuse, roughness is in [0, 1]
+    pub fn rough(rho: Arc<dyn Texture<Vec3>>, roughness: Arc<dyn Texture<Real>>) -> Self {
+        Diffuse {
+            rho,
+            model: DiffuseModel::EnergyPreservingOrenNayar(roughness),
+        }
+    }
+
+    // classic Oren-Nayar with slope deviation sigma in radians
+    pub fn oren_nayar(rho: Arc<dyn Texture<Vec3>>, sigma: Real) -> Self {
+        Diffuse {
+            rho,
+            model: DiffuseModel::OrenNayar(sigma),
+        }
+    }
+}
+
+impl Material for Diffuse {
+    fn get_bsdf(&self, info: &ShadingInfo) -> BSDF {
+        let rho = self.rho.eval(info);
+        let bxdf: Box<dyn BxDF> = match &self.model {
+            DiffuseModel::Lambert => Box::new(Lambert::new(rho)),
+            DiffuseModel::OrenNayar(sigma) => Box::new(OrenNayar::new(rho, *sigma)),
+            DiffuseModel::EnergyPreservingOrenNayar(roughness) => {
+                Box::new(EnergyPreservingOrenNayar::new(rho, roughness.eval(info)))
+            }
+        };
+        BSDF::from_bxdf(info, bxdf)
+    }
+}
+
+pub struct Mirror {}
+
+impl Mirror {
+    pub fn new() -> Self {
+        Mirror {}
+    }
+}
+
+impl Default for Mirror {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Material for Mirror {
+    fn get_bsdf(&self, info: &ShadingInfo) -> BSDF {
+        BSDF::from_bxdf(info, Box::new(IdealReflection::new()))
+    }
+}
+
+// smooth glass without roughness, frosted glass otherwise
+pub struct Glass {
+    ior: Real,
+    roughness: Option<Arc<dyn Texture<Real>>>,
+    anisotropic: Real,
+}
+
+impl Glass {
+    pub fn new(ior: Real) -> Self {
+        Glass {
+            ior,
+            roughness: None,
+            anisotropic: 0.0,
+        }
+    }
+
+    pub fn rough(ior: Real, roughness: Arc<dyn Texture<Real>>, anisotropic: Real) -> Self {
+        Glass {
+            ior,
+            roughness: Some(roughness),
+            anisotropic,
+        }
+    }
+}
+
+impl Material for Glass {
+    fn get_bsdf(&self, info: &ShadingInfo) -> BSDF {
+        match &self.roughness {
+            None => BSDF::from_bxdf(info, Box::new(Dielectric::new(self.ior))),
+            Some(roughness) => {
+                let (alpha_x, alpha_z) = roughness_to_alpha(roughness.eval(info), self.anisotropic);
+                BSDF::from_bxdf(
+                    info,
+                    Box::new(MicrofacetDielectric::new(self.ior, alpha_x, alpha_z)),
+                )
+            }
+        }
+    }
+}
+
+// rough metal with complex ior eta + i k
+pub struct Conductor {
+    eta: Vec3,
+    k: Vec3,
+    roughness: Arc<dyn Texture<Real>>,
+    anisotropic: Real,
+}
+
+impl Conductor {
+    pub fn new(eta: Vec3, k: Vec3, roughness: Arc<dyn Texture<Real>>, anisotropic: Real) -> Self {
+        Conductor {
+            eta,
+            k,
+            roughness,
+            anisotropic,
+        }
+    }
+
+    // metal whose reflectance at normal incidence is f0, assuming eta = 1
+    pub fn from_reflectance(
+        f0: Vec3,
+        roughness: Arc<dyn Texture<Real>>,
+        anisotropic: Real,
+    ) -> Self {
+        let k = |f0: Real| {
+            let f0 = f0.clamp(0.0, 0.999);
+            2.0 * (f0 / (1.0 - f0)).sqrt()
+        };
+        Conductor::new(
+            Vec3::new(1.0, 1.0, 1.0),
+            Vec3::new(k(f0.x()), k(f0.y()), k(f0.z())),
+            roughness,
+            anisotropic,
+        )
+    }
+}
+
+impl Material for Conductor {
+    fn get_bsdf(&self, info: &ShadingInfo) -> BSDF {
+        let (alpha_x, alpha_z) = roughness_to_alpha(self.roughness.eval(info), self.anisotropic);
+        BSDF::from_bxdf(
+            info,
+            Box::new(MicrofacetConductor::new(self.eta, self.k, alpha_x, alpha_z)),
+        )
+    }
+}
+
+pub struct Plastic {
+    diffuse: Arc<dyn Texture<Vec3>>,
+    specular: Arc<dyn Texture<Vec3>>,
+    roughness: Arc<dyn Texture<Real>>,
+}
+
+impl Plastic {
+    pub fn new(
+        diffuse: Arc<dyn Texture<Vec3>>,
+        specular: Arc<dyn Texture<Vec3>>,
+        roughness: Arc<dyn Texture<Real>>,
+    ) -> Self {
+        Plastic {
+            diffuse,
+            specular,
+            roughness,
+        }
+    }
+}
+
+impl Material for Plastic {
+    fn get_bsdf(&self, info: &ShadingInfo) -> BSDF {
+        let (alpha, _) = roughness_to_alpha(self.roughness.eval(info), 0.0);
+        BSDF::from_bxdf(
+            info,
+            Box::new(CoatedDiffuse::new(
+                self.diffuse.eval(info),
+                self.specular.eval(info),
+                1.5,
+                alpha,
+                alpha,
+            )),
+        )
+    }
+}
+
+// Disney principled material, parameters other than base_color and ior are in [0, 1]
+pub struct Principled {
+    pub base_color: Arc<dyn Texture<Vec3>>,
+    pub metallic: Arc<dyn Texture<Real>>,
+    pub roughness: Arc<dyn Texture<Real>>, // perceptual roughness
+    pub anisotropic: Real,
+    pub specular: Real, // 0.5 corresponds to reflectance 0.04 at normal incidence
+    pub specular_tint: Real,
+    pub sheen: Real,
+    pub sheen_tint: Real,
+    pub clearcoat: Real,
+    pub clearcoat_gloss: Real,
+    pub transmission: Real,
+    pub ior: Real, // index of refraction of transmission lobe
+    pub subsurface: Real,
+}
+
+impl Principled {
+    pub fn new(
+        base_color: Arc<dyn Texture<Vec3>>,
+        metallic: Arc<dyn Texture<Real>>,
+        roughness: Arc<dyn Texture<Real>>,
+    ) -> Self {
+        Principled {
+            base_color,
+            metallic,
+            roughness,
+            ..Default::default()
+        }
+    }
+
+    // specular level whose reflectance at normal incidence is that of ior, as in glTF
+    pub fn specular_from_ior(ior: Real) -> Real {
+        ((ior - 1.0) / (ior + 1.0)).powi(2) / 0.08
+    }
+}
+
+impl Default for Principled {
+    fn default() -> Self {
+        Principled {
+            base_color: Arc::new(ConstantTexture::new(Vec3::new(0.8, 0.8, 0.8))),
+            metallic: Arc::new(ConstantTexture::new(0.0)),
+            roughness: Arc::new(ConstantTexture::new(0.5)),
+            anisotropic: 0.0,
+            specular: 0.5,
+            specular_tint: 0.0,
+            sheen: 0.0,
+            sheen_tint: 0.5,
+            clearcoat: 0.0,
+            clearcoat_gloss: 1.0,
+            transmission: 0.0,
+            ior: 1.5,
+            subsurface: 0.0,
+        }
+    }
+}
+
+impl Material for Principled {
+    fn get_bsdf(&self, info: &ShadingInfo) -> BSDF {
+        let white = Vec3::new(1.0, 1.0, 1.0);
+        let lerp = |a: Vec3, b: Vec3, t: Real| (1.0 - t) * a + t * b;
+        let base_color = self.base_color.eval(info);
+        let metallic = self.metallic.eval(info);
+        let roughness = self.roughness.eval(info);
+        let (alpha_x, alpha_z) = roughness_to_alpha(roughness, self.anisotropic);
+        let dielectric = 1.0 - metallic;
+
+        // base color normalized by luminance, used to tint specular and sheen
+        let luminance = base_color.dot(Vec3::new(0.3, 0.6, 0.1));
+        let tint = if luminance > 0.0 {
+            base_color / luminance
+        } else {
+            white
+        };
+
+        let mut bsdf = BSDF::new(info);
+        let sheen = self.sheen * lerp(white, tint, self.sheen_tint);
+        bsdf.add(
+            Box::new(DisneyDiffuse::new(
+                base_color,
+                roughness,
+                self.subsurface,
+                sheen,
+            )),
+            dielectric * (1.0 - self.transmission),
+        );
+
+        // metal reflects with base color, dielectric with possibly tinted fresnel
+        let specular = 0.08 * self.specular * lerp(white, tint, self.specular_tint);
+        bsdf.add(
+            Box::new(DisneySpecular::new(
+                lerp(specular, base_color, metallic),
+                alpha_x,
+                alpha_z,
+            )),
+            1.0 - dielectric * self.transmission,
+        );
+
+        // transmitted light is colored by base color on entering and leaving
+        bsdf.add(
+            Box::new(MicrofacetDielectric::new_with_tint(
+                self.ior,
+                alpha_x,
+                alpha_z,
+                Vec3::new(
+                    base_color.x().sqrt(),
+                    base_color.y().sqrt(),
+                    base_color.z().sqrt(),
+                ),
+            )),
+            dielectric * self.transmission,
+        );
+
+        let clearcoat_alpha = 0.1 * (1.0 - self.clearcoat_gloss) + 0.001 * self.clearcoat_gloss;
+        bsdf.add(
+            Box::new(DisneyClearcoat::new(clearcoat_alpha)),
+            0.25 * self.clearcoat,
+        );
+        bsdf
+    }
+}
+
+// blend of two materials, weight is given to the second one
+pub struct Mix {
+    material1: Arc<dyn Material>,
+    material2: Arc<dyn Material>,
+    weight: Real,
+}
+
+impl Mix {
+    pub fn new(material1: Arc<dyn Material>, material2: Arc<dyn Material>, weight: Real) -> Self {
+        Mix {
+            material1,
+            material2,
+            weight,
+        }
+    }
+}
+
+impl Material for Mix {
+    fn get_bsdf(&self, info: &ShadingInfo) -> BSDF {
+        let mut bsdf = BSDF::new(info);
+        bsdf.merge(self.material1.get_bsdf(info), 1.0 - self.weight);
+        bsdf.merge(self.material2.get_bsdf(info), self.weight);
+        bsdf
+    }
+
+    fn emission(&self, info: &ShadingInfo) -> Vec3 {
+        (1.0 - self.weight) * self.material1.emission(info)
+            + self.weight * self.material2.emission(info)
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.material1.is_emissive() || self.material2.is_emissive()
+    }
+
+    fn shading_normal(&self, info: &ShadingInfo, dpdu: Vec3, dpdv: Vec3) -> Vec3 {
+        let n1 = self.material1.shading_normal(info, dpdu, dpdv);
+        let n2 = self.material2.shading_normal(info, dpdu, dpdv);
+        let n = (1.0 - self.weight) * n1 + self.weight * n2;
+        if n.length2() > 0.0 {
+            n.normalize()
+        } else {
+            info.n
+        }
+    }
+}
+
+// adds emission to material, one-sided emission leaves only the front side of surface
+pub struct Emissive {
+    material: Arc<dyn Material>,
+    radiance: Arc<dyn Texture<Vec3>>,
+    two_sided: bool,
+}
+
+impl Emissive {
+    pub fn new(
+        material: Arc<dyn Material>,
+        radiance: Arc<dyn Texture<Vec3>>,
+        two_sided: bool,
+    ) -> Self {
+        Emissive {
+            material,
+            radiance,
+            two_sided,
+        }
+    }
+
+    // color of blackbody at temperature in kelvin, luminance of radiance is scale
+    pub fn from_blackbody(
+        material: Arc<dyn Material>,
+        temperature: Real,
+        scale: Real,
+        two_sided: bool,
+    ) -> Self {
+        let radiance = scale * blackbody(temperature);
+        Emissive::new(
+            material,
+            Arc::new(ConstantTexture::new(radiance)),
+            two_sided,
+        )
+    }
+}
+
+impl Material for Emissive {
+    fn get_bsdf(&self, info: &ShadingInfo) -> BSDF {
+        self.material.get_bsdf(info)
+    }
+
+    fn emission(&self, info: &ShadingInfo) -> Vec3 {
+        if self.two_sided || info.wo.y() > 0.0 {
+            self.radiance.eval(info)
+        } else {
+            Vec3::new(0.0, 0.0, 0.0)
+        }
+    }
+
+    fn is_emissive(&self) -> bool {
+        true
+    }
+
+    fn shading_normal(&self, info: &ShadingInfo, dpdu: Vec3, dpdv: Vec3) -> Vec3 {
+        self.material.shading_normal(info, dpdu, dpdv)
+    }
+}
+
+// perturbs shading normal by tangent-space normal map,
+// rgb in [0, 1] encodes components along dpdu, dpdv and normal
+pub struct NormalMapped {
+    material: Arc<dyn Material>,
+    normal_map: Arc<dyn Texture<Vec3>>,
+}
+
+impl NormalMapped {
+    pub fn new(material: Arc<dyn Material>, normal_map: Arc<dyn Texture<Vec3>>) -> Self {
+        NormalMapped {
+            material,
+            normal_map,
+        }
+    }
+}
+
+impl Material for NormalMapped {
+    fn get_bsdf(&self, info: &ShadingInfo) -> BSDF {
+        self.material.get_bsdf(info)
+    }
+
+    fn emission(&self, info: &ShadingInfo) -> Vec3 {
+        self.material.emission(info)
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.material.is_emissive()
+    }
+
+    fn shading_normal(&self, info: &ShadingInfo, _dpdu: Vec3, dpdv: Vec3) -> Vec3 {
+        let m = 2.0 * self.normal_map.eval(info) - Vec3::new(1.0, 1.0, 1.0);
+        // bitangent of frame may point against dpdv
+        let b = if info.b.dot(dpdv) < 0.0 {
+            -info.b
+        } else {
+            info.b
+        };
+        let n = m.x() * info.t + m.y() * b + m.z() * info.n;
+        if n.length2() > 0.0 {
+            n.normalize()
+        } else {
+            info.n
+        }
+    }
+}
+
+// perturbs shading normal by gradient of height map
+pub struct BumpMapped {
+    material: Arc<dyn Material>,
+    height: Arc<dyn Texture<Real>>,
+    scale: Real, // height is multiplied by scale
+}
+
+impl BumpMapped {
+    pub fn new(material: Arc<dyn Material>, height: Arc<dyn Texture<Real>>, scale: Real) -> Self {
+        BumpMapped {
+            material,
+            height,
+            scale,
+        }
+    }
+}
+
+impl Material for BumpMapped {
+    fn get_bsdf(&self, info: &ShadingInfo) -> BSDF {
+        self.material.get_bsdf(info)
+    }
+
+    fn emission(&self, info: &ShadingInfo) -> Vec3 {
+        self.material.emission(info)
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.material.is_emissive()
+    }
+
+    fn shading_normal(&self, info: &ShadingInfo, dpdu: Vec3, dpdv: Vec3) -> Vec3 {
+        // forward differences with fixed step since rays carry no differentials
+        const DELTA: Real = 0.0005;
+        let height = |du: Real, dv: Real| {
+            self.scale
+                * self.height.eval(&ShadingInfo {
+                    x: info.x + du * dpdu + dv * dpdv,
+                    uv: info.uv + Vec2::new(du, dv),
+                    ..*info
+                })
+        };
+        let h = height(0.0, 0.0);
+        let dpdu = dpdu + (height(DELTA, 0.0) - h) / DELTA * info.n;
+        let dpdv = dpdv + (height(0.0, DELTA) - h) / DELTA * info.n;
+
+        let n = dpdu.cross(dpdv);
+        if n.length2() == 0.0 {
+            return info.n;
+        }
+        let n = n.normalize();
+        if n.dot(info.n) < 0.0 {
+            -n
+        } else {
+            n
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::material::*;
+
+    // height rising along u
+    struct Ramp {}
+
+    impl Texture<Real> for Ramp {
+        fn eval(&self, info: &ShadingInfo) -> Real {
+            0.5 * info.uv.x()
+        }
+    }
+
+    fn shading_info() -> ShadingInfo {
+        ShadingInfo {
+            x: Vec3::new(0.0, 0.0, 0.0),
+            n: Vec3::new(0.0, 1.0, 0.0),
+            wo: Vec3::new(0.0, 1.0, 0.0),
+            t: Vec3::new(1.0, 0.0, 0.0),
+            b: Vec3::new(0.0, 0.0, 1.0),
+            uv: Vec2::new(0.5, 0.5),
+        }
+    }
+
+    #[test]
+    fn normal_map() {
+        let info = shading_info();
+        let dpdu = Vec3::new(1.0, 0.0, 0.0);
+        let dpdv = Vec3::new(0.0, 0.0, -1.0);
+        let diffuse: Arc<dyn Material> = Arc::new(Diffuse::new(Arc::new(ConstantTexture::new(
+            Vec3::new(0.5, 0.5, 0.5),
+        ))));
+        let normal = |rgb: Vec3| {
+            NormalMapped::new(diffuse.clone(), Arc::new(ConstantTexture::new(rgb)))
+                .shading_normal(&info, dpdu, dpdv)
+        };
+
+        assert_eq!(normal(Vec3::new(0.5, 0.5, 1.0)), info.n);
+        assert_eq!(normal(Vec3::new(1.0, 0.5, 0.5)), dpdu);
+        // green follows dpdv even if bitangent of frame points against it
+        assert_eq!(normal(Vec3::new(0.5, 1.0, 0.5)), dpdv);
+    }
+
+    #[test]
+    fn bump_map() {
+        let info = shading_info();
+        let dpdu = Vec3::new(1.0, 0.0, 0.0);
+        let dpdv = Vec3::new(0.0, 0.0, 1.0);
+        let diffuse: Arc<dyn Material> = Arc::new(Diffuse::new(Arc::new(ConstantTexture::new(
+            Vec3::new(0.5, 0.5, 0.5),
+        ))));
+
+        // constant height keeps normal
+        let flat = BumpMapped::new(diffuse.clone(), Arc::new(ConstantTexture::new(1.0)), 1.0);
+        assert_eq!(flat.shading_normal(&info, dpdu, dpdv), info.n);
+
+        // slope of 0.5 along u tilts normal towards -u
+        let ramp = BumpMapped::new(diffuse, Arc::new(Ramp {}), 1.0);
+        let n = ramp.shading_normal(&info, dpdu, dpdv);
+        assert!((n - Vec3::new(-0.5, 1.0, 0.0).normalize()).length() < 1e-3);
+    }
+}
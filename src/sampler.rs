@@ -41,3 +41,28 @@ pub fn cosine_weighted_hemisphere(uv: Vec2) -> (Vec3, Real) {
 
     (spherical_to_cartesian(theta, phi), pdf)
 }
+
+pub fn uniform_sphere(uv: Vec2) -> (Vec3, Real) {
+    let theta = (1.0 - 2.0 * uv.x()).clamp(-1.0, 1.0).acos();
+    let phi = 2.0 * PI * uv.y();
+
+    (spherical_to_cartesian(theta, phi), 0.25 * FRAC_1_PI)
+}
+
+// sample direction inside cone around +y whose half angle is acos(cos_theta_max). cone is
+// given by 1 - cos_theta_max, which loses precision when computed from cosine of narrow cone
+pub fn uniform_cone(uv: Vec2, one_minus_cos_theta_max: Real) -> (Vec3, Real) {
+    // 1 - cos(theta) = 2 sin^2(theta / 2)
+    let one_minus_cos_theta = uv.x() * one_minus_cos_theta_max;
+    let theta = 2.0 * (0.5 * one_minus_cos_theta).sqrt().min(1.0).asin();
+    let phi = 2.0 * PI * uv.y();
+
+    let pdf = 1.0 / (2.0 * PI * one_minus_cos_theta_max);
+    (spherical_to_cartesian(theta, phi), pdf)
+}
+
+// returns barycentric coordinates (b1, b2) uniformly distributed over triangle
+pub fn uniform_triangle(uv: Vec2) -> (Real, Real) {
+    let su0 = uv.x().sqrt();
+    (1.0 - su0, uv.y() * su0)
+}
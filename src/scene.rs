@@ -1,75 +1,205 @@
-use crate::bxdf::{BxDF, Lambert};
+use crate::bounds::Bounds3;
+use crate::bsdf::BSDF;
 use crate::core::{IntersectInfoGlobal, IntersectableGlobal, IntersectableLocal, Ray, ShadingInfo};
-use crate::intersector::Intersector;
+use crate::intersector::{create_intersector, Intersector, IntersectorType};
+use crate::light::{AreaLight, Light, LightSample, LightType};
+use crate::light_sampler::{create_light_sampler, LightSampler, LightSamplerType};
+use crate::material::Material;
+use crate::sampler::Sampler;
+use crate::types::Real;
 use crate::vec3::{build_orthonormal_basis, Vec3};
 
 use std::sync::Arc;
 
-#[derive(Clone)]
-pub struct Material {
-    pub diffuse: Vec3,
-    pub specular: Vec3,
-    pub emission: Vec3,
-}
-
-impl Material {
-    pub fn new(diffuse: Vec3, specular: Vec3, emission: Vec3) -> Self {
-        Material {
-            diffuse,
-            specular,
-            emission,
-        }
-    }
-}
-
-// TODO: make intersector selectable
 pub struct Scene {
-    _primitives: Arc<Vec<Box<dyn IntersectableLocal + Send + Sync>>>,
-    materials: Vec<Material>,
-    intersector: Intersector,
+    materials: Vec<Arc<dyn Material>>,
+    lights: Vec<Box<dyn Light>>,
+    light_indices: Vec<Option<usize>>, // light index of each primitive
+    infinite_lights: Vec<usize>,       // indices of lights seen by escaping rays
+    bounds: Bounds3,
+    light_sampler_type: LightSamplerType,
+    light_sampler: Box<dyn LightSampler>, // chooses light for next event estimation
+    intersector: Box<dyn Intersector>,
 }
 
 impl Scene {
     pub fn new(
         primitives: Vec<Box<dyn IntersectableLocal + Send + Sync>>,
-        materials: Vec<Material>,
+        materials: Vec<Arc<dyn Material>>,
+    ) -> Self {
+        Scene::new_with_intersector(primitives, materials, IntersectorType::BVH)
+    }
+
+    pub fn new_with_intersector(
+        primitives: Vec<Box<dyn IntersectableLocal + Send + Sync>>,
+        materials: Vec<Arc<dyn Material>>,
+        intersector_type: IntersectorType,
     ) -> Self {
         if primitives.len() != materials.len() {
             panic!("number of primitives does not equal to the number of materials.");
         }
 
         let primitives = Arc::new(primitives);
+        let bounds = primitives.iter().fold(Bounds3::empty(), |bounds, prim| {
+            bounds.union(&prim.bounds())
+        });
+
+        // register emissive primitives as lights
+        let mut lights: Vec<Box<dyn Light>> = Vec::new();
+        let mut light_indices = Vec::with_capacity(materials.len());
+        for (prim_idx, material) in materials.iter().enumerate() {
+            if !material.is_emissive() {
+                light_indices.push(None);
+                continue;
+            }
+            // primitives on black texels of textured emission aren't worth sampling, their
+            // emission is still found by BSDF sampling
+            let light = AreaLight::new(primitives.clone(), prim_idx as u32, material.clone());
+            if light.power().max() > 0.0 {
+                light_indices.push(Some(lights.len()));
+                lights.push(Box::new(light));
+            } else {
+                light_indices.push(None);
+            }
+        }
+
         Scene {
-            _primitives: primitives.clone(),
             materials,
-            intersector: Intersector::new(primitives),
+            light_sampler: create_light_sampler(LightSamplerType::BVH, &lights),
+            light_sampler_type: LightSamplerType::BVH,
+            lights,
+            light_indices,
+            infinite_lights: Vec::new(),
+            bounds,
+            intersector: create_intersector(intersector_type, primitives),
         }
     }
 
+    pub fn bounds(&self) -> Bounds3 {
+        self.bounds
+    }
+
     pub fn has_emission(&self, prim_idx: u32) -> bool {
-        let emission = self.materials[prim_idx as usize].emission;
-        emission.x() > 0.0 && emission.y() > 0.0 && emission.z() > 0.0
+        self.materials[prim_idx as usize].is_emissive()
     }
 
-    pub fn get_emission(&self, prim_idx: u32) -> Vec3 {
-        let material = &self.materials[prim_idx as usize];
-        material.emission
+    // radiance emitted towards wo_global from hit point, front side is where geometric
+    // normal points
+    pub fn get_emission(&self, wo_global: Vec3, info: &IntersectInfoGlobal) -> Vec3 {
+        let emitter_info = shading_info(wo_global, info, info.normal);
+        self.materials[info.prim_idx as usize].emission(&emitter_info)
     }
 
+    // shading frame is built from normal perturbed by material and tangent along dpdu
     pub fn get_shading_info(&self, wo_global: Vec3, info: &IntersectInfoGlobal) -> ShadingInfo {
-        let (t, n, b) = build_orthonormal_basis(info.normal);
-        ShadingInfo {
-            x: info.pos,
-            n,
-            wo: wo_global.world_to_local(t, n, b),
-            t,
-            b,
+        let material = self.materials[info.prim_idx as usize].as_ref();
+        let unperturbed = shading_info(wo_global, info, info.shading_normal);
+        let n = material.shading_normal(&unperturbed, info.dpdu, info.dpdv);
+        let n = keep_above_surface(n, info.normal, info.shading_normal);
+        shading_info(wo_global, info, n)
+    }
+
+    pub fn get_bsdf(&self, prim_idx: u32, info: &ShadingInfo) -> BSDF {
+        self.materials[prim_idx as usize].get_bsdf(info)
+    }
+
+    // add light which isn't part of geometry, e.g. delta lights and environment
+    pub fn add_light(&mut self, mut light: Box<dyn Light>) {
+        light.preprocess(&self.bounds);
+        if light.light_type() == LightType::Infinite {
+            self.infinite_lights.push(self.lights.len());
         }
+        self.lights.push(light);
+        self.light_sampler = create_light_sampler(self.light_sampler_type, &self.lights);
     }
 
-    pub fn get_bxdf(&self, prim_idx: u32) -> Box<dyn BxDF> {
-        let material = &self.materials[prim_idx as usize];
-        Box::new(Lambert::new(material.diffuse))
+    // strategy of choosing light to sample, light BVH by default
+    pub fn set_light_sampler(&mut self, light_sampler_type: LightSamplerType) {
+        self.light_sampler_type = light_sampler_type;
+        self.light_sampler = create_light_sampler(light_sampler_type, &self.lights);
+    }
+
+    pub fn lights(&self) -> &[Box<dyn Light>] {
+        &self.lights
+    }
+
+    pub fn n_lights(&self) -> usize {
+        self.lights.len()
+    }
+
+    // choose one light by light sampler and sample incoming direction at x from it
+    pub fn sample_light(&self, x: Vec3, sampler: &mut Sampler) -> Option<LightSample> {
+        let (light_idx, pmf) = self.light_sampler.sample(x, sampler.next_1d())?;
+        let mut sample = self.lights[light_idx].sample_li(x, sampler)?;
+        sample.pdf *= pmf;
+        Some(sample)
+    }
+
+    // pdf of sample_light choosing direction wi from x which hits primitive
+    pub fn pdf_light(&self, x: Vec3, wi: Vec3, prim_idx: u32) -> Real {
+        match self.light_indices[prim_idx as usize] {
+            Some(light_idx) => {
+                self.lights[light_idx].pdf_li(x, wi) * self.light_sampler.pmf(x, light_idx)
+            }
+            None => 0.0,
+        }
+    }
+
+    // radiance of each infinite light arriving along ray from x with direction wi
+    // that escapes the scene, paired with pdf of sample_light choosing wi
+    pub fn infinite_light_emissions(
+        &self,
+        x: Vec3,
+        wi: Vec3,
+    ) -> impl Iterator<Item = (Vec3, Real)> + '_ {
+        self.infinite_lights.iter().map(move |&light_idx| {
+            let light = &self.lights[light_idx];
+            let pmf = self.light_sampler.pmf(x, light_idx);
+            (light.le(wi), light.pdf_li(x, wi) * pmf)
+        })
+    }
+
+    // check whether segment from x towards wi with given distance is blocked
+    pub fn is_occluded(&self, x: Vec3, wi: Vec3, distance: Real) -> bool {
+        let mut ray = Ray::new(x, wi);
+        ray.tmax = (1.0 - 1E-3) * distance;
+        self.intersect(&ray).is_some()
+    }
+}
+
+fn shading_info(wo_global: Vec3, info: &IntersectInfoGlobal, n: Vec3) -> ShadingInfo {
+    // project dpdu onto tangent plane, it vanishes at poles of sphere
+    let t = info.dpdu - info.dpdu.dot(n) * n;
+    let (t, n, b) = if t.length2() > 1E-12 {
+        let t = t.normalize();
+        (t, n, t.cross(n).normalize())
+    } else {
+        build_orthonormal_basis(n)
+    };
+    ShadingInfo {
+        x: info.pos,
+        n,
+        wo: wo_global.world_to_local(t, n, b),
+        t,
+        b,
+        uv: info.uv,
+    }
+}
+
+// shading normal must not go below geometric surface seen from the side of
+// interpolated normal, otherwise shading frame turns inside out
+fn keep_above_surface(n: Vec3, geometric_normal: Vec3, interpolated_normal: Vec3) -> Vec3 {
+    const MIN_COS: Real = 0.01;
+    let ng = if geometric_normal.dot(interpolated_normal) < 0.0 {
+        -geometric_normal
+    } else {
+        geometric_normal
+    };
+    let cos = n.dot(ng);
+    if cos >= MIN_COS {
+        n
+    } else {
+        (n + (MIN_COS - cos) * ng).normalize()
     }
 }
 
@@ -1,6 +1,15 @@
-use crate::core::{IntersectInfoLocal, IntersectableLocal, Ray};
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+use crate::bounds::Bounds3;
+use crate::core::{
+    area_to_solid_angle, area_to_solid_angle_pdf, IntersectInfoLocal, IntersectableLocal, Ray,
+    SurfaceSample,
+};
+use crate::sampler::{uniform_cone, uniform_sphere, uniform_triangle, Sampler};
 use crate::types::Real;
-use crate::vec3::Vec3;
+use crate::vec2::Vec2;
+use crate::vec3::{build_orthonormal_basis, Vec3};
 
 pub struct Sphere {
     center: Vec3,
@@ -11,6 +20,28 @@ impl Sphere {
     pub fn new(center: Vec3, radius: Real) -> Self {
         Sphere { center, radius }
     }
+
+    // u goes around y axis from +x towards +z, v goes from bottom pole to top pole
+    fn parameterize(&self, pos: Vec3) -> (Vec2, Vec3, Vec3) {
+        let p = pos - self.center;
+        let mut phi = p.z().atan2(p.x());
+        if phi < 0.0 {
+            phi += 2.0 * PI;
+        }
+        let theta = (p.y() / self.radius).clamp(-1.0, 1.0).acos();
+        let uv = Vec2::new(phi / (2.0 * PI), 1.0 - theta / PI);
+
+        // azimuth is arbitrary at poles
+        let rho = (p.x() * p.x() + p.z() * p.z()).sqrt();
+        let (cos_phi, sin_phi) = if rho > 0.0 {
+            (p.x() / rho, p.z() / rho)
+        } else {
+            (1.0, 0.0)
+        };
+        let dpdu = 2.0 * PI * Vec3::new(-p.z(), 0.0, p.x());
+        let dpdv = PI * Vec3::new(-p.y() * cos_phi, rho, -p.y() * sin_phi);
+        (uv, dpdu, dpdv)
+    }
 }
 
 impl IntersectableLocal for Sphere {
@@ -31,12 +62,81 @@ impl IntersectableLocal for Sphere {
         }
 
         let pos = ray.position(t);
+        let normal = (pos - self.center).normalize();
+        let (uv, dpdu, dpdv) = self.parameterize(pos);
         Some(IntersectInfoLocal {
             t,
             pos,
-            normal: (pos - self.center).normalize(),
+            normal,
+            shading_normal: normal,
+            uv,
+            dpdu,
+            dpdv,
         })
     }
+
+    fn bounds(&self) -> Bounds3 {
+        let r = Vec3::new(self.radius, self.radius, self.radius);
+        Bounds3::new(self.center - r, self.center + r)
+    }
+
+    fn area(&self) -> Real {
+        4.0 * PI * self.radius * self.radius
+    }
+
+    fn sample_area(&self, sampler: &mut Sampler) -> SurfaceSample {
+        let (normal, _) = uniform_sphere(sampler.next_2d());
+        let pos = self.center + self.radius * normal;
+        SurfaceSample {
+            pos,
+            normal,
+            uv: self.parameterize(pos).0,
+            pdf: 1.0 / self.area(),
+        }
+    }
+
+    // sample cone of directions subtended by sphere
+    fn sample_solid_angle(&self, x: Vec3, sampler: &mut Sampler) -> SurfaceSample {
+        let dc2 = (self.center - x).length2();
+        let r2 = self.radius * self.radius;
+        if dc2 <= r2 {
+            return area_to_solid_angle(x, self.sample_area(sampler));
+        }
+
+        let dc = dc2.sqrt();
+        let (dir, pdf) = uniform_cone(sampler.next_2d(), one_minus_cos_theta_max(r2, dc2));
+
+        // distance to the nearest intersection along sampled direction
+        let cos_theta = dir.y();
+        let sin2_theta = dir.x() * dir.x() + dir.z() * dir.z();
+        let ds = dc * cos_theta - (r2 - dc2 * sin2_theta).max(0.0).sqrt();
+
+        let (t, n, b) = build_orthonormal_basis((self.center - x) / dc);
+        let pos = x + ds * dir.local_to_world(t, n, b);
+        SurfaceSample {
+            pos,
+            normal: (pos - self.center).normalize(),
+            uv: self.parameterize(pos).0,
+            pdf,
+        }
+    }
+
+    fn pdf_solid_angle(&self, x: Vec3, pos: Vec3, normal: Vec3) -> Real {
+        let dc2 = (self.center - x).length2();
+        let r2 = self.radius * self.radius;
+        if dc2 <= r2 {
+            return area_to_solid_angle_pdf(x, pos, normal, 1.0 / self.area());
+        }
+
+        1.0 / (2.0 * PI * one_minus_cos_theta_max(r2, dc2))
+    }
+}
+
+// 1 - cos of half angle of cone subtended by sphere, written without cancellation since
+// cos_theta_max rounds to 1 for small distant spheres
+fn one_minus_cos_theta_max(r2: Real, dc2: Real) -> Real {
+    let sin2_theta_max = r2 / dc2;
+    sin2_theta_max / (1.0 + (1.0 - sin2_theta_max).max(0.0).sqrt())
 }
 
 pub struct Plane {
@@ -66,7 +166,8 @@ impl Plane {
 impl IntersectableLocal for Plane {
     fn intersect(&self, ray: &Ray) -> Option<IntersectInfoLocal> {
         let t = -(ray.origin - self.center).dot(self.normal) / ray.direction.dot(self.normal);
-        if t < ray.tmin || t > ray.tmax {
+        // ray lying in plane gives NaN, which must not be reported as hit
+        if !(t >= ray.tmin && t <= ray.tmax) {
             return None;
         }
 
@@ -81,8 +182,322 @@ impl IntersectableLocal for Plane {
             t,
             pos,
             normal: self.normal,
+            shading_normal: self.normal,
+            uv: Vec2::new(dx / self.right_dir_length, dy / self.up_dir_length),
+            dpdu: self.right_dir_length * self.right_dir,
+            dpdv: self.up_dir_length * self.up_dir,
         })
     }
+
+    fn bounds(&self) -> Bounds3 {
+        let right = self.right_dir_length * self.right_dir;
+        let up = self.up_dir_length * self.up_dir;
+        Bounds3::from_points(&[
+            self.left_corner_point,
+            self.left_corner_point + right,
+            self.left_corner_point + up,
+            self.left_corner_point + right + up,
+        ])
+    }
+
+    fn area(&self) -> Real {
+        self.right_dir_length * self.up_dir_length
+    }
+
+    fn normal_bounds(&self) -> (Vec3, Real) {
+        (self.normal, 1.0)
+    }
+
+    fn sample_area(&self, sampler: &mut Sampler) -> SurfaceSample {
+        let uv = sampler.next_2d();
+        SurfaceSample {
+            pos: self.left_corner_point
+                + uv.x() * self.right_dir_length * self.right_dir
+                + uv.y() * self.up_dir_length * self.up_dir,
+            normal: self.normal,
+            uv,
+            pdf: 1.0 / self.area(),
+        }
+    }
+}
+
+// vertex and index buffers shared by all triangles of a mesh
+pub struct TriangleMesh {
+    vertices: Vec<Vec3>,          // vertex positions
+    indices: Vec<u32>,            // vertex indices, 3 per face
+    normals: Option<Vec<Vec3>>,   // per-vertex normals
+    texcoords: Option<Vec<Vec2>>, // per-vertex uvs
+}
+
+impl TriangleMesh {
+    pub fn new(
+        vertices: Vec<Vec3>,
+        indices: Vec<u32>,
+        normals: Option<Vec<Vec3>>,
+        texcoords: Option<Vec<Vec2>>,
+    ) -> Self {
+        if !indices.len().is_multiple_of(3) {
+            panic!("number of indices is not a multiple of 3.");
+        }
+        if let Some(normals) = &normals {
+            if normals.len() != vertices.len() {
+                panic!("number of normals does not equal to the number of vertices.");
+            }
+        }
+        if let Some(texcoords) = &texcoords {
+            if texcoords.len() != vertices.len() {
+                panic!("number of texcoords does not equal to the number of vertices.");
+            }
+        }
+
+        TriangleMesh {
+            vertices,
+            indices,
+            normals,
+            texcoords,
+        }
+    }
+
+    pub fn n_faces(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    fn face_indices(&self, face_idx: usize) -> [usize; 3] {
+        [
+            self.indices[3 * face_idx] as usize,
+            self.indices[3 * face_idx + 1] as usize,
+            self.indices[3 * face_idx + 2] as usize,
+        ]
+    }
+
+    pub fn get_vertices(&self, face_idx: usize) -> [Vec3; 3] {
+        let [i0, i1, i2] = self.face_indices(face_idx);
+        [self.vertices[i0], self.vertices[i1], self.vertices[i2]]
+    }
+
+    pub fn get_normals(&self, face_idx: usize) -> Option<[Vec3; 3]> {
+        let [i0, i1, i2] = self.face_indices(face_idx);
+        self.normals
+            .as_ref()
+            .map(|normals| [normals[i0], normals[i1], normals[i2]])
+    }
+
+    pub fn get_texcoords(&self, face_idx: usize) -> Option<[Vec2; 3]> {
+        let [i0, i1, i2] = self.face_indices(face_idx);
+        self.texcoords
+            .as_ref()
+            .map(|texcoords| [texcoords[i0], texcoords[i1], texcoords[i2]])
+    }
+
+    // split mesh into triangles which can be registered as individual primitives
+    pub fn triangles(mesh: &Arc<TriangleMesh>) -> Vec<Triangle> {
+        (0..mesh.n_faces())
+            .map(|face_idx| Triangle::new(mesh.clone(), face_idx))
+            .collect()
+    }
+}
+
+impl IntersectableLocal for TriangleMesh {
+    fn intersect(&self, ray: &Ray) -> Option<IntersectInfoLocal> {
+        let mut ray = ray.clone();
+        let mut info: Option<IntersectInfoLocal> = None;
+        for face_idx in 0..self.n_faces() {
+            if let Some(face_info) = intersect_face(self, face_idx, &ray) {
+                ray.tmax = face_info.t;
+                info = Some(face_info);
+            }
+        }
+        info
+    }
+
+    fn bounds(&self) -> Bounds3 {
+        Bounds3::from_points(&self.vertices)
+    }
+
+    fn area(&self) -> Real {
+        (0..self.n_faces())
+            .map(|face_idx| face_area(self, face_idx))
+            .sum()
+    }
+
+    // choose face proportional to its area, then sample point on it
+    fn sample_area(&self, sampler: &mut Sampler) -> SurfaceSample {
+        let area = self.area();
+        let mut target = sampler.next_1d() * area;
+        let mut face_idx = self.n_faces() - 1;
+        for i in 0..self.n_faces() {
+            target -= face_area(self, i);
+            if target <= 0.0 {
+                face_idx = i;
+                break;
+            }
+        }
+
+        let (pos, normal, uv) = sample_face(self, face_idx, sampler);
+        SurfaceSample {
+            pos,
+            normal,
+            uv,
+            pdf: 1.0 / area,
+        }
+    }
+}
+
+pub struct Triangle {
+    mesh: Arc<TriangleMesh>,
+    face_idx: usize,
+}
+
+impl Triangle {
+    pub fn new(mesh: Arc<TriangleMesh>, face_idx: usize) -> Self {
+        if face_idx >= mesh.n_faces() {
+            panic!("face index {} is out of range.", face_idx);
+        }
+        Triangle { mesh, face_idx }
+    }
+
+    // returns distance and barycentric coordinates (b1, b2) of hit point
+    pub fn intersect_barycentric(&self, ray: &Ray) -> Option<(Real, Real, Real)> {
+        intersect_barycentric(&self.mesh.get_vertices(self.face_idx), ray)
+    }
+}
+
+impl IntersectableLocal for Triangle {
+    fn intersect(&self, ray: &Ray) -> Option<IntersectInfoLocal> {
+        intersect_face(&self.mesh, self.face_idx, ray)
+    }
+
+    fn bounds(&self) -> Bounds3 {
+        Bounds3::from_points(&self.mesh.get_vertices(self.face_idx))
+    }
+
+    fn area(&self) -> Real {
+        face_area(&self.mesh, self.face_idx)
+    }
+
+    fn normal_bounds(&self) -> (Vec3, Real) {
+        let vertices = self.mesh.get_vertices(self.face_idx);
+        let normal = (vertices[1] - vertices[0])
+            .cross(vertices[2] - vertices[0])
+            .normalize();
+        (normal, 1.0)
+    }
+
+    fn sample_area(&self, sampler: &mut Sampler) -> SurfaceSample {
+        let (pos, normal, uv) = sample_face(&self.mesh, self.face_idx, sampler);
+        SurfaceSample {
+            pos,
+            normal,
+            uv,
+            pdf: 1.0 / self.area(),
+        }
+    }
+}
+
+// Moller-Trumbore ray-triangle intersection
+fn intersect_barycentric(vertices: &[Vec3; 3], ray: &Ray) -> Option<(Real, Real, Real)> {
+    let e1 = vertices[1] - vertices[0];
+    let e2 = vertices[2] - vertices[0];
+
+    let p = ray.direction.cross(e2);
+    let det = e1.dot(p);
+    if det.abs() < 1E-9 {
+        return None;
+    }
+    let inv_det = 1.0 / det;
+
+    let s = ray.origin - vertices[0];
+    let b1 = s.dot(p) * inv_det;
+    if !(0.0..=1.0).contains(&b1) {
+        return None;
+    }
+
+    let q = s.cross(e1);
+    let b2 = ray.direction.dot(q) * inv_det;
+    if b2 < 0.0 || b1 + b2 > 1.0 {
+        return None;
+    }
+
+    let t = e2.dot(q) * inv_det;
+    if t < ray.tmin || t > ray.tmax {
+        return None;
+    }
+
+    Some((t, b1, b2))
+}
+
+fn intersect_face(mesh: &TriangleMesh, face_idx: usize, ray: &Ray) -> Option<IntersectInfoLocal> {
+    let vertices = mesh.get_vertices(face_idx);
+    let (t, b1, b2) = intersect_barycentric(&vertices, ray)?;
+    let b0 = 1.0 - b1 - b2;
+
+    let normal = (vertices[1] - vertices[0])
+        .cross(vertices[2] - vertices[0])
+        .normalize();
+    let shading_normal = match mesh.get_normals(face_idx) {
+        Some(normals) => (b0 * normals[0] + b1 * normals[1] + b2 * normals[2]).normalize(),
+        None => normal,
+    };
+    let texcoords = face_texcoords(mesh, face_idx);
+    let uv = b0 * texcoords[0] + b1 * texcoords[1] + b2 * texcoords[2];
+
+    // solve dp = dpdu du + dpdv dv along two edges
+    let duv02 = texcoords[0] - texcoords[2];
+    let duv12 = texcoords[1] - texcoords[2];
+    let dp02 = vertices[0] - vertices[2];
+    let dp12 = vertices[1] - vertices[2];
+    let det = duv02.x() * duv12.y() - duv02.y() * duv12.x();
+    let (dpdu, dpdv) = if det.abs() < 1E-9 {
+        // degenerate uvs, any tangent frame will do
+        let (t, _, b) = build_orthonormal_basis(normal);
+        (t, b)
+    } else {
+        let inv_det = 1.0 / det;
+        (
+            inv_det * (duv12.y() * dp02 - duv02.y() * dp12),
+            inv_det * (duv02.x() * dp12 - duv12.x() * dp02),
+        )
+    };
+
+    Some(IntersectInfoLocal {
+        t,
+        pos: ray.position(t),
+        normal,
+        shading_normal,
+        uv,
+        dpdu,
+        dpdv,
+    })
+}
+
+fn face_area(mesh: &TriangleMesh, face_idx: usize) -> Real {
+    let vertices = mesh.get_vertices(face_idx);
+    0.5 * (vertices[1] - vertices[0])
+        .cross(vertices[2] - vertices[0])
+        .length()
+}
+
+// returns uniformly sampled position, geometric normal and uv on face
+fn sample_face(mesh: &TriangleMesh, face_idx: usize, sampler: &mut Sampler) -> (Vec3, Vec3, Vec2) {
+    let vertices = mesh.get_vertices(face_idx);
+    let (b1, b2) = uniform_triangle(sampler.next_2d());
+    let b0 = 1.0 - b1 - b2;
+    let pos = b0 * vertices[0] + b1 * vertices[1] + b2 * vertices[2];
+    let normal = (vertices[1] - vertices[0])
+        .cross(vertices[2] - vertices[0])
+        .normalize();
+    let texcoords = face_texcoords(mesh, face_idx);
+    let uv = b0 * texcoords[0] + b1 * texcoords[1] + b2 * texcoords[2];
+    (pos, normal, uv)
+}
+
+// faces without uvs are parameterized by barycentric coordinates
+fn face_texcoords(mesh: &TriangleMesh, face_idx: usize) -> [Vec2; 3] {
+    mesh.get_texcoords(face_idx).unwrap_or([
+        Vec2::new(0.0, 0.0),
+        Vec2::new(1.0, 0.0),
+        Vec2::new(0.0, 1.0),
+    ])
 }
 
 #[cfg(test)]
@@ -98,7 +513,11 @@ mod tests {
             Some(IntersectInfoLocal {
                 t: 1.0,
                 pos: Vec3::new(0.0, 0.0, -1.0),
-                normal: Vec3::new(0.0, 0.0, -1.0)
+                normal: Vec3::new(0.0, 0.0, -1.0),
+                shading_normal: Vec3::new(0.0, 0.0, -1.0),
+                uv: Vec2::new(0.75, 0.5),
+                dpdu: Vec3::new(2.0 * PI, 0.0, 0.0),
+                dpdv: Vec3::new(0.0, PI, 0.0),
             })
         );
     }
@@ -116,8 +535,154 @@ mod tests {
             Some(IntersectInfoLocal {
                 t: 1.0,
                 pos: Vec3::new(0.0, 0.0, 0.0),
-                normal: Vec3::new(0.0, 1.0, 0.0)
+                normal: Vec3::new(0.0, 1.0, 0.0),
+                shading_normal: Vec3::new(0.0, 1.0, 0.0),
+                uv: Vec2::new(0.5, 0.5),
+                dpdu: Vec3::new(2.0, 0.0, 0.0),
+                dpdv: Vec3::new(0.0, 0.0, -2.0),
             })
         )
     }
+
+    fn quad_mesh(normals: Option<Vec<Vec3>>) -> Arc<TriangleMesh> {
+        Arc::new(TriangleMesh::new(
+            vec![
+                Vec3::new(-1.0, 0.0, 1.0),
+                Vec3::new(1.0, 0.0, 1.0),
+                Vec3::new(1.0, 0.0, -1.0),
+                Vec3::new(-1.0, 0.0, -1.0),
+            ],
+            vec![0, 1, 2, 0, 2, 3],
+            normals,
+            None,
+        ))
+    }
+
+    #[test]
+    fn triangle_intersect() {
+        let mesh = quad_mesh(None);
+        let triangles = TriangleMesh::triangles(&mesh);
+        assert_eq!(triangles.len(), 2);
+
+        let ray = Ray::new(Vec3::new(0.5, 1.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        assert_eq!(
+            triangles[0].intersect(&ray),
+            Some(IntersectInfoLocal {
+                t: 1.0,
+                pos: Vec3::new(0.5, 0.0, 0.0),
+                normal: Vec3::new(0.0, 1.0, 0.0),
+                shading_normal: Vec3::new(0.0, 1.0, 0.0),
+                uv: Vec2::new(0.25, 0.5),
+                dpdu: Vec3::new(2.0, 0.0, 0.0),
+                dpdv: Vec3::new(2.0, 0.0, -2.0),
+            })
+        );
+        assert_eq!(triangles[1].intersect(&ray), None);
+        assert_eq!(
+            triangles[0].intersect_barycentric(&ray),
+            Some((1.0, 0.25, 0.5))
+        );
+    }
+
+    #[test]
+    fn triangle_texcoord_derivatives() {
+        // texture is rotated by 90 degrees and stretched relative to positions
+        let mesh = Arc::new(TriangleMesh::new(
+            vec![
+                Vec3::new(0.0, 0.0, 0.0),
+                Vec3::new(2.0, 0.0, 0.0),
+                Vec3::new(0.0, 0.0, -1.0),
+            ],
+            vec![0, 1, 2],
+            None,
+            Some(vec![
+                Vec2::new(0.0, 0.0),
+                Vec2::new(0.0, 1.0),
+                Vec2::new(1.0, 0.0),
+            ]),
+        ));
+        let ray = Ray::new(Vec3::new(0.5, 1.0, -0.25), Vec3::new(0.0, -1.0, 0.0));
+        let info = mesh.intersect(&ray).unwrap();
+        assert_eq!(info.uv, Vec2::new(0.25, 0.25));
+        assert_eq!(info.dpdu, Vec3::new(0.0, 0.0, -1.0));
+        assert_eq!(info.dpdv, Vec3::new(2.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn triangle_mesh_intersect() {
+        let n = Vec3::new(0.0, 1.0, 0.0);
+        let tilted = Vec3::new(1.0, 1.0, 0.0).normalize();
+        let mesh = quad_mesh(Some(vec![n, tilted, tilted, n]));
+
+        let ray = Ray::new(Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        let info = mesh.intersect(&ray).unwrap();
+        assert_eq!(info.t, 1.0);
+        assert_eq!(info.normal, n);
+        assert_eq!(info.shading_normal, (0.5 * n + 0.5 * tilted).normalize());
+
+        let ray = Ray::new(Vec3::new(2.0, 1.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        assert_eq!(mesh.intersect(&ray), None);
+    }
+
+    #[test]
+    fn plane_sample_area() {
+        let plane = Plane::new(
+            Vec3::new(-1.0, 0.0, 1.0),
+            Vec3::new(2.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, -2.0),
+        );
+        assert_eq!(plane.area(), 4.0);
+
+        let mut sampler = Sampler::new(0);
+        for _ in 0..100 {
+            let sample = plane.sample_area(&mut sampler);
+            assert_eq!(sample.pos.y(), 0.0);
+            assert!(sample.pos.x().abs() <= 1.0 && sample.pos.z().abs() <= 1.0);
+            assert_eq!(sample.normal, Vec3::new(0.0, 1.0, 0.0));
+            assert_eq!(sample.pdf, 0.25);
+        }
+    }
+
+    #[test]
+    fn sphere_sample_solid_angle() {
+        let sphere = Sphere::new(Vec3::new(0.0, 0.0, 0.0), 1.0);
+        let x = Vec3::new(0.0, 0.0, -2.0);
+
+        let mut sampler = Sampler::new(0);
+        for _ in 0..100 {
+            let sample = sphere.sample_solid_angle(x, &mut sampler);
+            assert!((sample.pos.length() - 1.0).abs() < 1E-4);
+            // sampled point is visible from x
+            assert!((x - sample.pos).dot(sample.normal) >= 0.0);
+            let cos_theta_max = (3.0 as Real).sqrt() / 2.0;
+            assert!((sample.pdf * 2.0 * PI * (1.0 - cos_theta_max) - 1.0).abs() < 1E-4);
+        }
+
+        // cos_theta_max of small distant sphere rounds to 1 but pdf stays finite and accurate
+        let sphere = Sphere::new(Vec3::new(0.0, 0.0, 0.0), 1E-3);
+        let x = Vec3::new(0.0, 0.0, -10.0);
+        let solid_angle = PI * 1E-8;
+        for _ in 0..100 {
+            let sample = sphere.sample_solid_angle(x, &mut sampler);
+            assert!((sample.pos.length() - 1E-3).abs() < 1E-5);
+            assert!((sample.pdf * solid_angle - 1.0).abs() < 1E-3);
+            let pdf = sphere.pdf_solid_angle(x, sample.pos, sample.normal);
+            assert!((pdf * solid_angle - 1.0).abs() < 1E-3);
+        }
+    }
+
+    #[test]
+    fn triangle_mesh_sample_area() {
+        let mesh = quad_mesh(None);
+        assert_eq!(mesh.area(), 4.0);
+        assert_eq!(TriangleMesh::triangles(&mesh)[0].area(), 2.0);
+
+        let mut sampler = Sampler::new(0);
+        for _ in 0..100 {
+            let sample = mesh.sample_area(&mut sampler);
+            assert_eq!(sample.pos.y(), 0.0);
+            assert!(sample.pos.x().abs() <= 1.0 && sample.pos.z().abs() <= 1.0);
+            assert_eq!(sample.pdf, 0.25);
+        }
+    }
 }
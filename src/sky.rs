@@ -0,0 +1,238 @@
+use std::f32::consts::{FRAC_PI_2, PI};
+
+use crate::color::xyy_to_rgb;
+use crate::core::spherical_to_cartesian;
+use crate::image::Image;
+use crate::types::Real;
+use crate::vec3::Vec3;
+
+// radiance is scaled so that white diffuse surface lit by noon sun and sky is about 1
+const RADIANCE_SCALE: Real = 1.0 / 40.0;
+
+// luminance of sun outside atmosphere in kcd/m^2
+const SUN_LUMINANCE: Real = 2.0E6;
+
+// coefficients of Perez distribution, linear in turbidity
+struct Perez {
+    a: Real,
+    b: Real,
+    c: Real,
+    d: Real,
+    e: Real,
+}
+
+impl Perez {
+    fn new(t: Real, coefficients: [[Real; 2]; 5]) -> Self {
+        let c = |i: usize| coefficients[i][0] * t + coefficients[i][1];
+        Perez {
+            a: c(0),
+            b: c(1),
+            c: c(2),
+            d: c(3),
+            e: c(4),
+        }
+    }
+
+    // relative distribution at view zenith angle theta and angle gamma from sun
+    fn eval(&self, cos_theta: Real, gamma: Real) -> Real {
+        let cos_gamma = gamma.cos();
+        (1.0 + self.a * (self.b / cos_theta.max(1E-3)).exp())
+            * (1.0 + self.c * (self.d * gamma).exp() + self.e * cos_gamma * cos_gamma)
+    }
+}
+
+// Preetham daylight model, sky color depends on sun direction and turbidity of atmosphere
+pub struct PreethamSky {
+    sun_direction: Vec3, // direction towards sun
+    theta_sun: Real,     // zenith angle of sun
+    turbidity: Real,
+    ground_albedo: Vec3,
+    zenith: [Real; 3], // luminance Y and chromaticity x, y at zenith
+    perez: [Perez; 3], // distributions of Y, x, y
+}
+
+impl PreethamSky {
+    pub fn new(sun_direction: Vec3, turbidity: Real, ground_albedo: Vec3) -> Self {
+        let sun_direction = sun_direction.normalize();
+        // model is not valid for sun below horizon or for very clear and hazy skies
+        let theta_sun = sun_direction
+            .y()
+            .clamp(-1.0, 1.0)
+            .acos()
+            .min(FRAC_PI_2 - 1E-3);
+        let t = turbidity.clamp(1.7, 10.0);
+
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_sun);
+        let zenith_y = ((4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192).max(0.0);
+        let chromaticity = |m: [[Real; 4]; 3]| {
+            let th = [theta_sun.powi(3), theta_sun.powi(2), theta_sun, 1.0];
+            let tv = [t * t, t, 1.0];
+            (0..3)
+                .map(|i| tv[i] * (0..4).map(|j| m[i][j] * th[j]).sum::<Real>())
+                .sum::<Real>()
+        };
+        let zenith_x = chromaticity([
+            [0.00166, -0.00375, 0.00209, 0.0],
+            [-0.02903, 0.06377, -0.03202, 0.00394],
+            [0.11693, -0.21196, 0.06052, 0.25886],
+        ]);
+        let zenith_yy = chromaticity([
+            [0.00275, -0.00610, 0.00317, 0.0],
+            [-0.04214, 0.08970, -0.04153, 0.00516],
+            [0.15346, -0.26756, 0.06670, 0.26688],
+        ]);
+
+        let perez = [
+            Perez::new(
+                t,
+                [
+                    [0.1787, -1.4630],
+                    [-0.3554, 0.4275],
+                    [-0.0227, 5.3251],
+                    [0.1206, -2.5771],
+                    [-0.0670, 0.3703],
+                ],
+            ),
+            Perez::new(
+                t,
+                [
+                    [-0.0193, -0.2592],
+                    [-0.0665, 0.0008],
+                    [-0.0004, 0.2125],
+                    [-0.0641, -0.8989],
+                    [-0.0033, 0.0452],
+                ],
+            ),
+            Perez::new(
+                t,
+                [
+                    [-0.0167, -0.2608],
+                    [-0.0950, 0.0092],
+                    [-0.0079, 0.2102],
+                    [-0.0441, -1.6537],
+                    [-0.0109, 0.0529],
+                ],
+            ),
+        ];
+
+        PreethamSky {
+            sun_direction,
+            theta_sun,
+            turbidity: t,
+            ground_albedo,
+            zenith: [zenith_y, zenith_x, zenith_yy],
+            perez,
+        }
+    }
+
+    pub fn sun_direction(&self) -> Vec3 {
+        self.sun_direction
+    }
+
+    // radiance of sky seen along direction w in upper hemisphere
+    pub fn radiance(&self, w: Vec3) -> Vec3 {
+        let cos_theta = w.y().max(0.0);
+        let gamma = w.dot(self.sun_direction).clamp(-1.0, 1.0).acos();
+        let value = |k: usize| {
+            self.zenith[k] * self.perez[k].eval(cos_theta, gamma)
+                / self.perez[k].eval(1.0, self.theta_sun)
+        };
+        RADIANCE_SCALE * xyy_to_rgb(value(1), value(2), value(0))
+    }
+
+    // radiance of sun disk attenuated by Rayleigh and aerosol scattering
+    pub fn sun_radiance(&self) -> Vec3 {
+        if self.sun_direction.y() <= 0.0 {
+            return Vec3::new(0.0, 0.0, 0.0);
+        }
+
+        // relative optical mass of atmosphere along sun ray
+        let theta_degrees = self.theta_sun.to_degrees();
+        let m = 1.0 / (self.theta_sun.cos() + 0.15 * (93.885 - theta_degrees).powf(-1.253));
+        let beta = 0.04608 * self.turbidity - 0.04586;
+        // wavelengths in micrometers representing red, green and blue
+        let transmittance = |lambda: Real| {
+            let rayleigh = (-0.008735 * lambda.powf(-4.08) * m).exp();
+            let aerosol = (-beta * lambda.powf(-1.3) * m).exp();
+            rayleigh * aerosol
+        };
+        RADIANCE_SCALE
+            * SUN_LUMINANCE
+            * Vec3::new(
+                transmittance(0.68),
+                transmittance(0.55),
+                transmittance(0.44),
+            )
+    }
+
+    // equirectangular image of sky whose layout follows EnvironmentLight, sun is not included.
+    // lower hemisphere is ground lit by sky and sun of given angular radius
+    pub fn to_image(&self, width: usize, height: usize, sun_radius: Real) -> Image {
+        let mut image = Image::new(width, height);
+        let mut irradiance = Vec3::new(0.0, 0.0, 0.0);
+        for i in 0..height {
+            let theta = PI * (i as Real + 0.5) / height as Real;
+            for j in 0..width {
+                let phi = 2.0 * PI * (j as Real + 0.5) / width as Real;
+                let w = spherical_to_cartesian(theta, phi);
+                if w.y() > 0.0 {
+                    let l = self.radiance(w);
+                    image.set_pixel(i, j, l);
+                    // solid angle of texel times cosine
+                    let d_omega = 2.0 * PI * PI / (width * height) as Real * theta.sin();
+                    irradiance += d_omega * w.y() * l;
+                }
+            }
+        }
+
+        let sun_solid_angle = 2.0 * PI * (1.0 - sun_radius.cos());
+        irradiance += sun_solid_angle * self.sun_direction.y().max(0.0) * self.sun_radiance();
+        let ground = self.ground_albedo / PI * irradiance;
+        for i in 0..height {
+            let theta = PI * (i as Real + 0.5) / height as Real;
+            if theta.cos() <= 0.0 {
+                for j in 0..width {
+                    image.set_pixel(i, j, ground);
+                }
+            }
+        }
+        image
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sky::*;
+
+    #[test]
+    fn preetham_sky() {
+        let sun = Vec3::new(0.0, 1.0, 1.0).normalize();
+        let sky = PreethamSky::new(sun, 3.0, Vec3::new(0.3, 0.3, 0.3));
+
+        // sky is brighter around sun and blue away from it
+        let near_sun = sky.radiance(Vec3::new(0.0, 0.6, 0.8));
+        let opposite = sky.radiance(Vec3::new(0.0, 0.6, -0.8));
+        assert!(near_sun.y() > opposite.y());
+        assert!(opposite.z() > opposite.x());
+
+        // sun turns red near horizon
+        let noon = sky.sun_radiance();
+        let sunset = PreethamSky::new(Vec3::new(0.0, 0.05, 1.0), 3.0, Vec3::new(0.3, 0.3, 0.3))
+            .sun_radiance();
+        assert!(noon.y() > sunset.y());
+        assert!(sunset.x() / sunset.z() > noon.x() / noon.z());
+    }
+
+    #[test]
+    fn sky_image() {
+        let sky = PreethamSky::new(Vec3::new(1.0, 1.0, 0.0), 3.0, Vec3::new(0.3, 0.3, 0.3));
+        let image = sky.to_image(64, 32, 0.01);
+
+        // zenith row matches model and ground is uniform
+        let zenith = sky.radiance(spherical_to_cartesian(PI / 64.0, PI / 64.0));
+        assert!((image.get_pixel(0, 0) - zenith).length() < 1E-4 * zenith.length());
+        let ground = image.get_pixel(31, 0);
+        assert!(ground.min() > 0.0);
+        assert_eq!(image.get_pixel(20, 10), ground);
+    }
+}
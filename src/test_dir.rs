@@ -0,0 +1,25 @@
+use std::path::PathBuf;
+
+// temporary directory for files written by test, unique to test name and process so that
+// concurrent test runs don't collide. it is removed with its contents on drop
+pub struct TestDir {
+    path: PathBuf,
+}
+
+impl TestDir {
+    pub fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("rusmallpt_{}_{}", name, std::process::id()));
+        std::fs::create_dir_all(&path).unwrap();
+        TestDir { path }
+    }
+
+    pub fn join(&self, filename: &str) -> PathBuf {
+        self.path.join(filename)
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
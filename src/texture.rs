@@ -0,0 +1,346 @@
+use std::sync::Arc;
+
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use rand_pcg::Pcg32;
+
+use crate::core::ShadingInfo;
+use crate::image::Image;
+use crate::types::Real;
+use crate::vec2::Vec2;
+use crate::vec3::Vec3;
+
+// spatially varying value evaluated at surface point
+pub trait Texture<T>: Send + Sync {
+    fn eval(&self, info: &ShadingInfo) -> T;
+}
+
+pub struct ConstantTexture<T> {
+    value: T,
+}
+
+impl<T> ConstantTexture<T> {
+    pub fn new(value: T) -> Self {
+        ConstantTexture { value }
+    }
+}
+
+impl<T: Copy + Send + Sync> Texture<T> for ConstantTexture<T> {
+    fn eval(&self, _info: &ShadingInfo) -> T {
+        self.value
+    }
+}
+
+// texture multiplied by constant color
+pub struct ScaledTexture {
+    texture: Arc<dyn Texture<Vec3>>,
+    scale: Vec3,
+}
+
+impl ScaledTexture {
+    pub fn new(texture: Arc<dyn Texture<Vec3>>, scale: Vec3) -> Self {
+        ScaledTexture { texture, scale }
+    }
+}
+
+impl Texture<Vec3> for ScaledTexture {
+    fn eval(&self, info: &ShadingInfo) -> Vec3 {
+        self.scale * self.texture.eval(info)
+    }
+}
+
+// one channel of color texture multiplied by constant, e.g. packed metallic-roughness
+pub struct ChannelTexture {
+    texture: Arc<dyn Texture<Vec3>>,
+    channel: usize, // 0, 1, 2 for red, green, blue
+    scale: Real,
+}
+
+impl ChannelTexture {
+    pub fn new(texture: Arc<dyn Texture<Vec3>>, channel: usize, scale: Real) -> Self {
+        ChannelTexture {
+            texture,
+            channel: channel.min(2),
+            scale,
+        }
+    }
+}
+
+impl Texture<Real> for ChannelTexture {
+    fn eval(&self, info: &ShadingInfo) -> Real {
+        self.scale * self.texture.eval(info)[self.channel]
+    }
+}
+
+// alternates two textures on grid of uv, scale is number of squares along u and v
+pub struct CheckerboardTexture<T> {
+    even: Arc<dyn Texture<T>>,
+    odd: Arc<dyn Texture<T>>,
+    scale: Vec2,
+}
+
+impl<T> CheckerboardTexture<T> {
+    pub fn new(even: Arc<dyn Texture<T>>, odd: Arc<dyn Texture<T>>, scale: Vec2) -> Self {
+        CheckerboardTexture { even, odd, scale }
+    }
+}
+
+impl<T> Texture<T> for CheckerboardTexture<T> {
+    fn eval(&self, info: &ShadingInfo) -> T {
+        let u = (info.uv.x() * self.scale.x()).floor() as i64;
+        let v = (info.uv.y() * self.scale.y()).floor() as i64;
+        if (u + v).rem_euclid(2) == 0 {
+            self.even.eval(info)
+        } else {
+            self.odd.eval(info)
+        }
+    }
+}
+
+// how texel indices outside of image are mapped inside
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum WrapMode {
+    Repeat,
+    Clamp,
+    Mirror,
+}
+
+impl WrapMode {
+    fn wrap(&self, i: i64, n: usize) -> usize {
+        let n = n as i64;
+        let i = match self {
+            WrapMode::Repeat => i.rem_euclid(n),
+            WrapMode::Clamp => i.clamp(0, n - 1),
+            WrapMode::Mirror => {
+                let m = i.rem_euclid(2 * n);
+                if m >= n {
+                    2 * n - 1 - m
+                } else {
+                    m
+                }
+            }
+        };
+        i as usize
+    }
+}
+
+// image looked up by uv with bilinear filtering, v = 0 is the bottom row
+pub struct ImageTexture {
+    image: Arc<Image>,
+    wrap_mode: WrapMode,
+}
+
+impl ImageTexture {
+    pub fn new(image: Arc<Image>, wrap_mode: WrapMode) -> Self {
+        ImageTexture { image, wrap_mode }
+    }
+
+    fn texel(&self, x: i64, y: i64) -> Vec3 {
+        let j = self.wrap_mode.wrap(x, self.image.get_width());
+        let i = self.wrap_mode.wrap(y, self.image.get_height());
+        self.image.get_pixel(i, j)
+    }
+
+    fn bilinear(&self, uv: Vec2) -> Vec3 {
+        // texel centers are at half-integer coordinates
+        let x = uv.x() * self.image.get_width() as Real - 0.5;
+        let y = (1.0 - uv.y()) * self.image.get_height() as Real - 0.5;
+        let x0 = x.floor();
+        let y0 = y.floor();
+        let dx = x - x0;
+        let dy = y - y0;
+        let (x0, y0) = (x0 as i64, y0 as i64);
+
+        (1.0 - dx) * (1.0 - dy) * self.texel(x0, y0)
+            + dx * (1.0 - dy) * self.texel(x0 + 1, y0)
+            + (1.0 - dx) * dy * self.texel(x0, y0 + 1)
+            + dx * dy * self.texel(x0 + 1, y0 + 1)
+    }
+}
+
+impl Texture<Vec3> for ImageTexture {
+    fn eval(&self, info: &ShadingInfo) -> Vec3 {
+        self.bilinear(info.uv)
+    }
+}
+
+// scalar is read from the first channel, assuming grayscale image
+impl Texture<Real> for ImageTexture {
+    fn eval(&self, info: &ShadingInfo) -> Real {
+        self.bilinear(info.uv).x()
+    }
+}
+
+// fractal sum of Perlin gradient noise over position, value is in [0, 1]
+pub struct PerlinTexture {
+    scale: Real, // frequency of the first octave
+    octaves: u32,
+    permutation: [u8; 256],
+}
+
+impl PerlinTexture {
+    pub fn new(scale: Real, octaves: u32) -> Self {
+        let mut permutation = [0u8; 256];
+        for (i, p) in permutation.iter_mut().enumerate() {
+            *p = i as u8;
+        }
+        // fixed seed keeps noise identical between renders
+        permutation.shuffle(&mut Pcg32::seed_from_u64(0));
+        PerlinTexture {
+            scale,
+            octaves: octaves.max(1),
+            permutation,
+        }
+    }
+
+    fn hash(&self, x: i64, y: i64, z: i64) -> u8 {
+        let p = |i: i64| self.permutation[(i & 255) as usize] as i64;
+        p(p(p(x) + y) + z) as u8
+    }
+
+    // dot product of pseudo-random gradient and offset from lattice point
+    fn gradient(hash: u8, x: Real, y: Real, z: Real) -> Real {
+        let h = hash & 15;
+        let u = if h < 8 { x } else { y };
+        let v = if h < 4 {
+            y
+        } else if h == 12 || h == 14 {
+            x
+        } else {
+            z
+        };
+        (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+    }
+
+    // improved Perlin noise in about [-1, 1]
+    pub fn noise(&self, p: Vec3) -> Real {
+        let fade = |t: Real| t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+        let lerp = |t: Real, a: Real, b: Real| a + t * (b - a);
+
+        let (xf, yf, zf) = (p.x().floor(), p.y().floor(), p.z().floor());
+        let (xi, yi, zi) = (xf as i64, yf as i64, zf as i64);
+        let (x, y, z) = (p.x() - xf, p.y() - yf, p.z() - zf);
+        let (u, v, w) = (fade(x), fade(y), fade(z));
+
+        let g = |dx: i64, dy: i64, dz: i64| {
+            Self::gradient(
+                self.hash(xi + dx, yi + dy, zi + dz),
+                x - dx as Real,
+                y - dy as Real,
+                z - dz as Real,
+            )
+        };
+        lerp(
+            w,
+            lerp(
+                v,
+                lerp(u, g(0, 0, 0), g(1, 0, 0)),
+                lerp(u, g(0, 1, 0), g(1, 1, 0)),
+            ),
+            lerp(
+                v,
+                lerp(u, g(0, 0, 1), g(1, 0, 1)),
+                lerp(u, g(0, 1, 1), g(1, 1, 1)),
+            ),
+        )
+    }
+
+    fn fbm(&self, p: Vec3) -> Real {
+        let mut sum = 0.0;
+        let mut amplitude = 0.5;
+        let mut frequency = self.scale;
+        for _ in 0..self.octaves {
+            sum += amplitude * self.noise(frequency * p);
+            amplitude *= 0.5;
+            frequency *= 2.0;
+        }
+        (0.5 + sum).clamp(0.0, 1.0)
+    }
+}
+
+impl Texture<Real> for PerlinTexture {
+    fn eval(&self, info: &ShadingInfo) -> Real {
+        self.fbm(info.x)
+    }
+}
+
+impl Texture<Vec3> for PerlinTexture {
+    fn eval(&self, info: &ShadingInfo) -> Vec3 {
+        let v = self.fbm(info.x);
+        Vec3::new(v, v, v)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::texture::*;
+
+    fn shading_info(x: Vec3, uv: Vec2) -> ShadingInfo {
+        ShadingInfo {
+            x,
+            n: Vec3::new(0.0, 1.0, 0.0),
+            wo: Vec3::new(0.0, 1.0, 0.0),
+            t: Vec3::new(1.0, 0.0, 0.0),
+            b: Vec3::new(0.0, 0.0, 1.0),
+            uv,
+        }
+    }
+
+    #[test]
+    fn checkerboard() {
+        let texture = CheckerboardTexture::new(
+            Arc::new(ConstantTexture::new(1.0)),
+            Arc::new(ConstantTexture::new(0.0)),
+            Vec2::new(4.0, 4.0),
+        );
+        let x = Vec3::new(0.0, 0.0, 0.0);
+        assert_eq!(texture.eval(&shading_info(x, Vec2::new(0.1, 0.1))), 1.0);
+        assert_eq!(texture.eval(&shading_info(x, Vec2::new(0.3, 0.1))), 0.0);
+        assert_eq!(texture.eval(&shading_info(x, Vec2::new(0.3, 0.3))), 1.0);
+        assert_eq!(texture.eval(&shading_info(x, Vec2::new(-0.1, 0.1))), 0.0);
+    }
+
+    #[test]
+    fn image_texture_bilinear() {
+        // 2x1 image, black on the left and white on the right
+        let mut image = Image::new(2, 1);
+        image.set_pixel(0, 1, Vec3::new(1.0, 1.0, 1.0));
+        let image = Arc::new(image);
+        let x = Vec3::new(0.0, 0.0, 0.0);
+        let eval = |wrap_mode: WrapMode, u: Real| -> Real {
+            ImageTexture::new(image.clone(), wrap_mode).eval(&shading_info(x, Vec2::new(u, 0.5)))
+        };
+
+        // texel centers
+        assert_eq!(eval(WrapMode::Clamp, 0.25), 0.0);
+        assert_eq!(eval(WrapMode::Clamp, 0.75), 1.0);
+        assert_eq!(eval(WrapMode::Clamp, 0.5), 0.5);
+
+        // outside of texel centers
+        assert_eq!(eval(WrapMode::Clamp, 1.0), 1.0);
+        assert_eq!(eval(WrapMode::Repeat, 1.0), 0.5);
+        assert_eq!(eval(WrapMode::Mirror, 1.0), 1.0);
+        assert_eq!(eval(WrapMode::Repeat, 1.25), 0.0);
+        assert_eq!(eval(WrapMode::Mirror, 1.25), 1.0);
+    }
+
+    #[test]
+    fn perlin_noise() {
+        let texture = PerlinTexture::new(1.0, 4);
+        let uv = Vec2::new(0.0, 0.0);
+
+        // noise vanishes on lattice points
+        assert_eq!(texture.noise(Vec3::new(1.0, 2.0, 3.0)), 0.0);
+
+        let mut min: Real = 1.0;
+        let mut max: Real = 0.0;
+        for i in 0..1000 {
+            let x = Vec3::new(0.37 * i as Real, 0.11 * i as Real, 0.53 * i as Real);
+            let v: Real = texture.eval(&shading_info(x, uv));
+            assert!((0.0..=1.0).contains(&v));
+            min = min.min(v);
+            max = max.max(v);
+        }
+        assert!(max - min > 0.3);
+    }
+}
@@ -0,0 +1,158 @@
+use std::ops::Mul;
+
+use crate::types::Real;
+use crate::vec3::Vec3;
+
+// 4x4 affine transform, m[row][col]
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Transform {
+    m: [[Real; 4]; 4],
+}
+
+impl Transform {
+    pub fn new(m: [[Real; 4]; 4]) -> Self {
+        Transform { m }
+    }
+
+    pub fn identity() -> Self {
+        Transform::new([
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    // build from column-major matrix such as glTF
+    pub fn from_cols(cols: [[Real; 4]; 4]) -> Self {
+        let mut m = [[0.0; 4]; 4];
+        for (col, c) in cols.iter().enumerate() {
+            for (row, v) in c.iter().enumerate() {
+                m[row][col] = *v;
+            }
+        }
+        Transform::new(m)
+    }
+
+    pub fn translate(v: Vec3) -> Self {
+        Transform::new([
+            [1.0, 0.0, 0.0, v.x()],
+            [0.0, 1.0, 0.0, v.y()],
+            [0.0, 0.0, 1.0, v.z()],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn scale(v: Vec3) -> Self {
+        Transform::new([
+            [v.x(), 0.0, 0.0, 0.0],
+            [0.0, v.y(), 0.0, 0.0],
+            [0.0, 0.0, v.z(), 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn transform_point(&self, p: Vec3) -> Vec3 {
+        let m = &self.m;
+        Vec3::new(
+            m[0][0] * p.x() + m[0][1] * p.y() + m[0][2] * p.z() + m[0][3],
+            m[1][0] * p.x() + m[1][1] * p.y() + m[1][2] * p.z() + m[1][3],
+            m[2][0] * p.x() + m[2][1] * p.y() + m[2][2] * p.z() + m[2][3],
+        )
+    }
+
+    pub fn transform_vector(&self, v: Vec3) -> Vec3 {
+        let m = &self.m;
+        Vec3::new(
+            m[0][0] * v.x() + m[0][1] * v.y() + m[0][2] * v.z(),
+            m[1][0] * v.x() + m[1][1] * v.y() + m[1][2] * v.z(),
+            m[2][0] * v.x() + m[2][1] * v.y() + m[2][2] * v.z(),
+        )
+    }
+
+    // normals are transformed by inverse transpose of upper 3x3 matrix
+    pub fn transform_normal(&self, n: Vec3) -> Vec3 {
+        let m = &self.m;
+        let c = [
+            [
+                m[1][1] * m[2][2] - m[1][2] * m[2][1],
+                m[1][2] * m[2][0] - m[1][0] * m[2][2],
+                m[1][0] * m[2][1] - m[1][1] * m[2][0],
+            ],
+            [
+                m[0][2] * m[2][1] - m[0][1] * m[2][2],
+                m[0][0] * m[2][2] - m[0][2] * m[2][0],
+                m[0][1] * m[2][0] - m[0][0] * m[2][1],
+            ],
+            [
+                m[0][1] * m[1][2] - m[0][2] * m[1][1],
+                m[0][2] * m[1][0] - m[0][0] * m[1][2],
+                m[0][0] * m[1][1] - m[0][1] * m[1][0],
+            ],
+        ];
+        let det = m[0][0] * c[0][0] + m[0][1] * c[0][1] + m[0][2] * c[0][2];
+        let n = Vec3::new(
+            c[0][0] * n.x() + c[0][1] * n.y() + c[0][2] * n.z(),
+            c[1][0] * n.x() + c[1][1] * n.y() + c[1][2] * n.z(),
+            c[2][0] * n.x() + c[2][1] * n.y() + c[2][2] * n.z(),
+        );
+        // inverse transpose is cofactor matrix divided by determinant, only its sign matters here
+        if det < 0.0 {
+            -n.normalize()
+        } else {
+            n.normalize()
+        }
+    }
+}
+
+impl Mul for Transform {
+    type Output = Transform;
+
+    fn mul(self, rhs: Transform) -> Self::Output {
+        let mut m = [[0.0; 4]; 4];
+        for (row, m_row) in m.iter_mut().enumerate() {
+            for (col, v) in m_row.iter_mut().enumerate() {
+                *v = (0..4).map(|k| self.m[row][k] * rhs.m[k][col]).sum();
+            }
+        }
+        Transform::new(m)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::transform::*;
+
+    #[test]
+    fn transform_point_vector() {
+        let t = Transform::translate(Vec3::new(1.0, 2.0, 3.0))
+            * Transform::scale(Vec3::new(2.0, 2.0, 2.0));
+        assert_eq!(
+            t.transform_point(Vec3::new(1.0, 1.0, 1.0)),
+            Vec3::new(3.0, 4.0, 5.0)
+        );
+        assert_eq!(
+            t.transform_vector(Vec3::new(1.0, 1.0, 1.0)),
+            Vec3::new(2.0, 2.0, 2.0)
+        );
+    }
+
+    #[test]
+    fn transform_normal() {
+        let t = Transform::scale(Vec3::new(1.0, 2.0, 1.0));
+        let n = Vec3::new(1.0, 1.0, 0.0);
+        assert_eq!(t.transform_normal(n), Vec3::new(2.0, 1.0, 0.0).normalize());
+    }
+
+    #[test]
+    fn transform_from_cols() {
+        let t = Transform::from_cols([
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [1.0, 2.0, 3.0, 1.0],
+        ]);
+        assert_eq!(t, Transform::translate(Vec3::new(1.0, 2.0, 3.0)));
+        assert_eq!(t * Transform::identity(), t);
+    }
+}
@@ -1,4 +1,4 @@
-use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+use std::ops::{Add, AddAssign, Div, DivAssign, Index, Mul, MulAssign, Neg, Sub, SubAssign};
 
 use crate::types::Real;
 
@@ -62,6 +62,18 @@ impl Vec3 {
         self.x().max(self.y()).max(self.z())
     }
 
+    pub fn min(&self) -> Real {
+        self.x().min(self.y()).min(self.z())
+    }
+
+    pub fn min3(&self, v: Vec3) -> Vec3 {
+        Vec3::new(
+            self.x().min(v.x()),
+            self.y().min(v.y()),
+            self.z().min(v.z()),
+        )
+    }
+
     pub fn max3(&self, v: Vec3) -> Vec3 {
         Vec3::new(
             self.x().max(v.x()),
@@ -195,6 +207,14 @@ impl_vec3_scalar_operator!(Div, div, &Vec3, /, Real);
 impl_scalar_vec3_operator!(Div, div, Real, /, Vec3);
 impl_scalar_vec3_operator!(Div, div, Real, /, &Vec3);
 
+impl Index<usize> for Vec3 {
+    type Output = Real;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.elements[index]
+    }
+}
+
 impl Neg for Vec3 {
     type Output = Vec3;
 
@@ -476,6 +496,22 @@ mod tests {
         assert_eq!(v.local_to_world(lx, ly, lz), Vec3::new(1.0, 0.0, 0.0));
     }
 
+    #[test]
+    fn vec3_min3_max3() {
+        let v1 = Vec3::new(1.0, 5.0, 3.0);
+        let v2 = Vec3::new(4.0, 2.0, 6.0);
+        assert_eq!(v1.min3(v2), Vec3::new(1.0, 2.0, 3.0));
+        assert_eq!(v1.max3(v2), Vec3::new(4.0, 5.0, 6.0));
+    }
+
+    #[test]
+    fn vec3_index() {
+        let v = Vec3::new(1.0, 2.0, 3.0);
+        assert_eq!(v[0], 1.0);
+        assert_eq!(v[1], 2.0);
+        assert_eq!(v[2], 3.0);
+    }
+
     #[test]
     fn vec3_orthonormal_basis() {
         let v = Vec3::new(0.0, 1.0, 0.0);